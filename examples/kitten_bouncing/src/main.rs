@@ -1,165 +1,198 @@
-extern crate cgmath;
-#[macro_use]
 extern crate gfx;
-extern crate gfx_window_glutin;
 extern crate glutin;
-extern crate time;
-extern crate image;
+extern crate learn_gfx_2d;
+
+use gfx::Device;
+use glutin::dpi::LogicalSize;
+use glutin::{ElementState, Event, VirtualKeyCode, WindowEvent};
+use learn_gfx_2d::{ColorFormat, DepthFormat};
+use learn_gfx_2d::capture::{Capture, DEFAULT_GIF_FPS, GifRecording, save_png};
+use learn_gfx_2d::clock::{Clock, FrameClock, ManualClock, SystemClock};
+// how the kitten moves lives in the library, where the golden image check draws it too
+use learn_gfx_2d::scenes::kitten_bouncing::{CLEAR_COLOR, SPRITE_SIZE, kitten_position};
+use learn_gfx_2d::sprite::SpriteRenderer;
+use learn_gfx_2d::texture::load_texture;
+use learn_gfx_2d::window;
+use std::env;
 
-pub type ColorFormat = gfx::format::Rgba8;
-pub type DepthFormat = gfx::format::Depth;
+const WINDOW_WIDTH: u32 = 640;
+const WINDOW_HEIGHT: u32 = 480;
 
-use gfx::{Device, texture};
-use gfx::traits::FactoryExt;
-use std::io::Cursor;
+// where the hotkeys save to, relative to where the example is ran from
+const SCREENSHOT_FILE: &'static str = "screenshot.png";
+const RECORDING_FILE: &'static str = "recording.gif";
 
-gfx_defines!{
-    constant View {
-        model: [[f32; 4]; 4] = "model",
-        projection: [[f32; 4]; 4] = "projection",
-    }
+// two seconds, about one trip across the window
+const DEFAULT_GIF_FRAMES: usize = 50;
 
-    vertex Vertex {
-        position: [f32; 2] = "position",
-        tex_coord: [f32; 2] = "texture_coord",
-    }
+// frames are kept in memory until the gif is saved, so recordings started with F11 save
+// themselves after ten seconds
+const MAX_RECORDING_FRAMES: usize = 10 * DEFAULT_GIF_FPS as usize;
 
-    pipeline pipe {
-        vbuf: gfx::VertexBuffer<Vertex> = (),
-        view: gfx::ConstantBuffer<View> = "View",
-        texture_sampler: gfx::TextureSampler<[f32; 4]> = "texture_sampler",
-        out: gfx::BlendTarget<ColorFormat> = ("color", gfx::state::MASK_ALL, gfx::preset::blend::ALPHA),
-    }
+struct Args {
+    screenshot: Option<String>,
+    gif: Option<String>,
+    frames: usize,
 }
 
-const CLEAR_COLOR: [f32; 4] = [0.59, 0.93, 0.59, 1.0];
-
-// each texture quad's position and what point on the texture to tie it to
-const TEX_QUAD: [Vertex; 4] = [Vertex {
-                                   position: [0.5, 0.5],
-                                   tex_coord: [1.0, 1.0],
-                               },
-                               Vertex {
-                                   position: [0.5, -0.5],
-                                   tex_coord: [1.0, 0.0],
-                               },
-                               Vertex {
-                                   position: [-0.5, -0.5],
-                                   tex_coord: [0.0, 0.0],
-                               },
-                               Vertex {
-                                   position: [-0.5, 0.5],
-                                   tex_coord: [0.0, 1.0],
-                               }];
-
-// the indices used by the element buffer
-const TEX_INDICES: [u16; 6] = [0, 3, 1, 1, 3, 2];
-
-const NEAR_PLANE: f32 = -1.0;
-const FAR_PLANE: f32 = 10.0;
-
-const WINDOW_WIDTH: u32 = 640;
-const WINDOW_HEIGHT: u32 = 480;
-
-const SPRITE_X: f32 = WINDOW_WIDTH as f32 / 2.0;
-const SPRITE_Y: f32 = WINDOW_HEIGHT as f32 / 2.0;
-const SPRITE_WIDTH: f32 = 266.0;
-const SPRITE_HEIGHT: f32 = 266.0;
-
-// stolen from: https://github.com/gfx-rs/gfx/tree/master/examples/blend
-fn load_texture<R, F>(factory: &mut F,
-                      data: &[u8])
-                      -> Result<gfx::handle::ShaderResourceView<R, [f32; 4]>, String>
-    where R: gfx::Resources,
-          F: gfx::Factory<R>
-{
-    let img = image::load(Cursor::new(data), image::PNG).unwrap().to_rgba();
-    let (width, height) = img.dimensions();
-    let kind = texture::Kind::D2(width as texture::Size,
-                                 height as texture::Size,
-                                 texture::AaMode::Single);
-    let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Rgba8>(kind, &[&img])
-        .unwrap();
-    Ok(view)
+// `--screenshot <file>` saves the first frame as a png and `--gif <file>` records
+// `--frames <n>` frames as an animated gif, and either way the example exits once it's done
+fn parse_args() -> Args {
+    let mut parsed = Args {
+        screenshot: None,
+        gif: None,
+        frames: DEFAULT_GIF_FRAMES,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--screenshot" => parsed.screenshot = args.next(),
+            "--gif" => parsed.gif = args.next(),
+            "--frames" => {
+                parsed.frames = args.next()
+                    .and_then(|frames| frames.parse().ok())
+                    .unwrap_or(DEFAULT_GIF_FRAMES)
+            }
+            _ => println!("ignoring unknown argument {}", arg),
+        }
+    }
+    parsed
 }
 
 fn main() {
+    let args = parse_args();
+    let capturing = args.screenshot.is_some() || args.gif.is_some();
+
+    let mut events_loop = glutin::EventsLoop::new();
     let builder = glutin::WindowBuilder::new()
         .with_title("Bouncing Kitten")
-        .with_dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64));
+    let context = glutin::ContextBuilder::new()
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
         .with_gl_profile(glutin::GlProfile::Core)
-        .with_vsync();
+        .with_vsync(true);
 
     let (window, mut device, mut factory, main_color, _) =
-        gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
-
-    let pso = factory.create_pipeline_simple(include_bytes!("shaders/sprite_vert.glsl"),
-                                include_bytes!("shaders/sprite_frag.glsl"),
-                                pipe::new())
-        .unwrap();
-
-    let (vertex_buffer, slice) =
-        factory.create_vertex_buffer_with_slice(&TEX_QUAD, &TEX_INDICES[..]);
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
+
+    // the sprite renderer owns the pipeline, quad and sampler the kitten is drawn with
+    let mut renderer = SpriteRenderer::new(&mut factory,
+                                           main_color.clone(),
+                                           WINDOW_WIDTH as f32,
+                                           WINDOW_HEIGHT as f32);
+
+    // the frame is drawn into here on its way to the window so it can be read back
+    let capture = Capture::new(&mut factory, WINDOW_WIDTH, WINDOW_HEIGHT).unwrap();
+    renderer.set_render_target(capture.target());
+    let mut recording = args.gif.as_ref().map(|_| {
+        GifRecording::new(DEFAULT_GIF_FPS).with_limit(args.frames)
+    });
+    let gif_frame_time = 1.0 / DEFAULT_GIF_FPS as f64;
+    let mut next_gif_frame = 0.0;
 
     let kitty_texture = load_texture(&mut factory, &include_bytes!("../images/kitty.png")[..])
         .unwrap();
-    let sampler = factory.create_sampler_linear();
-
-    let projection = cgmath::ortho(0.0,
-                                   WINDOW_WIDTH as f32,
-                                   0.0,
-                                   WINDOW_HEIGHT as f32,
-                                   NEAR_PLANE,
-                                   FAR_PLANE);
-
-    let mut translation =
-        cgmath::Matrix4::from_translation(cgmath::Vector3::new(SPRITE_X, SPRITE_Y, 0.0));
-    let scale = cgmath::Matrix4::from_nonuniform_scale(SPRITE_WIDTH, SPRITE_HEIGHT, 1.0);
-
-    let mut view = View {
-        model: (translation * scale).into(),
-        projection: projection.into(),
-    };
 
-    let data = pipe::Data {
-        view: factory.create_constant_buffer(1),
-        texture_sampler: (kitty_texture, sampler),
-        out: main_color,
-        vbuf: vertex_buffer,
+    // the bounce follows game time, so pausing with space freezes the kitten mid air. captures
+    // asked for on the command line step the clock by one gif frame a frame instead, so they
+    // come out the same on every machine.
+    let capture_clock = ManualClock::new();
+    let system_clock: Box<dyn Clock> = if capturing {
+        Box::new(capture_clock.clone())
+    } else {
+        Box::new(SystemClock)
     };
+    let mut clock = FrameClock::new(system_clock);
+
+    let mut screenshot = args.screenshot.clone();
 
     'main: loop {
         let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
-        for event in window.poll_events() {
-            match event {
-                glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) => {
-                    break 'main
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
+            let input = match event {
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => break 'main,
+                Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => input,
+                _ => continue,
+            };
+            match (input.state, input.virtual_keycode) {
+                (_, Some(VirtualKeyCode::Escape)) => break 'main,
+                (ElementState::Pressed, Some(VirtualKeyCode::Space)) => clock.toggle_pause(),
+                (ElementState::Pressed, Some(VirtualKeyCode::F12)) => {
+                    screenshot = Some(SCREENSHOT_FILE.to_string())
+                }
+                (ElementState::Pressed, Some(VirtualKeyCode::F11)) => {
+                    match recording.take() {
+                        Some(finished) => save_recording(&finished, RECORDING_FILE),
+                        None => {
+                            recording = Some(GifRecording::new(DEFAULT_GIF_FPS)
+                                .with_limit(MAX_RECORDING_FRAMES));
+                            next_gif_frame = clock.elapsed();
+                        }
+                    }
                 }
-                glutin::Event::Closed => break 'main,
                 _ => {}
             }
         }
 
-        let seconds = time::precise_time_s() as f32;
-        let y_offset = seconds.sin().abs();
-        translation = cgmath::Matrix4::from_translation(cgmath::Vector3::new(SPRITE_X,
-                                                                             SPRITE_HEIGHT / 2.0 +
-                                                                             (WINDOW_HEIGHT as f32 -
-                                                                              SPRITE_HEIGHT) *
-                                                                             y_offset,
-                                                                             0.0));
-
+        clock.tick();
+        let position = kitten_position(clock.elapsed() as f32);
 
-        view.model = (translation * scale).into();
+        // gifs are recorded at their own frame rate, whatever rate the window is drawn at.
+        // the capture clock already moves one gif frame at a time, so it takes every frame.
+        let record_frame = recording.is_some() &&
+                           (capturing || clock.elapsed() >= next_gif_frame);
+        if record_frame {
+            next_gif_frame += gif_frame_time;
+        }
+        let read_back = record_frame || screenshot.is_some();
 
         // render everything
-        encoder.update_constant_buffer(&data.view, &view);
-        encoder.clear(&data.out, CLEAR_COLOR);
-        encoder.draw(&slice, &pso, &data);
+        renderer.clear(&mut encoder, CLEAR_COLOR);
+        renderer.draw_sprite(&mut encoder, &kitty_texture, position, SPRITE_SIZE);
+        capture.present(&mut encoder, &main_color);
+        if read_back {
+            capture.request(&mut encoder).unwrap();
+        }
         encoder.flush(&mut device);
+
+        if read_back {
+            let frame = capture.read(&mut factory).unwrap();
+            if let Some(path) = screenshot.take() {
+                match save_png(&frame, &path) {
+                    Ok(()) => println!("saved {}", path),
+                    Err(err) => println!("couldn't save {}: {}", path, err),
+                }
+            }
+            if record_frame {
+                if let Some(ref mut recording) = recording {
+                    recording.add_frame(frame);
+                }
+            }
+        }
+
         window.swap_buffers().unwrap();
         device.cleanup();
+
+        if recording.as_ref().is_some_and(|recording| recording.is_full()) {
+            let path = args.gif.as_ref().map_or(RECORDING_FILE, |path| path.as_str());
+            save_recording(recording.as_ref().unwrap(), path);
+            recording = None;
+        }
+        if capturing {
+            if screenshot.is_none() && recording.is_none() {
+                break 'main;
+            }
+            capture_clock.advance(gif_frame_time);
+        }
+    }
+}
+
+fn save_recording(recording: &GifRecording, path: &str) {
+    match recording.save(path) {
+        Ok(()) => println!("saved {} frames to {}", recording.len(), path),
+        Err(err) => println!("couldn't save {}: {}", path, err),
     }
 }
@@ -1,184 +1,186 @@
-extern crate cgmath;
-#[macro_use]
 extern crate gfx;
-extern crate gfx_window_glutin;
 extern crate glutin;
-extern crate time;
-extern crate image;
+extern crate learn_gfx_2d;
+
+use gfx::Device;
+use glutin::dpi::LogicalSize;
+use glutin::{Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use learn_gfx_2d::{ColorFormat, DepthFormat};
+use learn_gfx_2d::bindings::{BindingsError, load_bindings};
+use learn_gfx_2d::clock::SystemClock;
+use learn_gfx_2d::game_loop::GameLoop;
+use learn_gfx_2d::input::{Binding, InputMap};
+use learn_gfx_2d::replay::{Recorder, Recording, Replayer};
+use learn_gfx_2d::scenes::kitten_keyboard::{CLEAR_COLOR, SPRITE_SIZE, start_position};
+use learn_gfx_2d::sprite::SpriteRenderer;
+use learn_gfx_2d::texture::load_texture;
+use learn_gfx_2d::viewport::{ScalingPolicy, Viewport};
+use learn_gfx_2d::window;
+use std::env;
 
-pub mod input;
+const WINDOW_WIDTH: u32 = 640;
+const WINDOW_HEIGHT: u32 = 480;
 
-pub type ColorFormat = gfx::format::Rgba8;
-pub type DepthFormat = gfx::format::Depth;
+// in pixels per second
+const KITTEN_SPEED: f32 = 360.0;
 
-use gfx::{Device, texture};
-use gfx::traits::FactoryExt;
-use glutin::{Event, VirtualKeyCode};
-use input::Input;
-use std::io::Cursor;
+const UPDATES_PER_SECOND: f64 = 60.0;
 
-gfx_defines!{
-    constant View {
-        model: [[f32; 4]; 4] = "model",
-        projection: [[f32; 4]; 4] = "projection",
-    }
+// players can rebind the controls by writing this file next to where the game is ran from
+const BINDINGS_FILE: &'static str = "kitten_keyboard_bindings.toml";
 
-    vertex Vertex {
-        position: [f32; 2] = "position",
-        tex_coord: [f32; 2] = "texture_coord",
-    }
+// how the 640x480 play area is fit into the window after it is resized
+const SCALING_POLICY: ScalingPolicy = ScalingPolicy::Letterbox;
 
-    pipeline pipe {
-        vbuf: gfx::VertexBuffer<Vertex> = (),
-        view: gfx::ConstantBuffer<View> = "View",
-        texture_sampler: gfx::TextureSampler<[f32; 4]> = "texture_sampler",
-        out: gfx::BlendTarget<ColorFormat> = ("color", gfx::state::MASK_ALL, gfx::preset::blend::ALPHA),
+// `--record <file>` saves the session's input when the window closes, and `--replay <file>`
+// plays a saved session back instead of reading the keyboard
+fn parse_args() -> (Option<String>, Option<String>) {
+    let mut record = None;
+    let mut replay = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = args.next(),
+            "--replay" => replay = args.next(),
+            _ => println!("ignoring unknown argument {}", arg),
+        }
     }
-}
-
-const CLEAR_COLOR: [f32; 4] = [0.59, 0.93, 0.59, 1.0];
-
-// each texture quad's position and what point on the texture to tie it to
-const TEX_QUAD: [Vertex; 4] = [Vertex {
-                                   position: [0.5, 0.5],
-                                   tex_coord: [1.0, 1.0],
-                               },
-                               Vertex {
-                                   position: [0.5, -0.5],
-                                   tex_coord: [1.0, 0.0],
-                               },
-                               Vertex {
-                                   position: [-0.5, -0.5],
-                                   tex_coord: [0.0, 0.0],
-                               },
-                               Vertex {
-                                   position: [-0.5, 0.5],
-                                   tex_coord: [0.0, 1.0],
-                               }];
-
-// the indices used by the element buffer
-const TEX_INDICES: [u16; 6] = [0, 3, 1, 1, 3, 2];
-
-const NEAR_PLANE: f32 = -1.0;
-const FAR_PLANE: f32 = 10.0;
-
-const WINDOW_WIDTH: u32 = 640;
-const WINDOW_HEIGHT: u32 = 480;
-
-const SPRITE_WIDTH: f32 = 266.0;
-const SPRITE_HEIGHT: f32 = 266.0;
-
-const KITTEN_SPEED: f32 = 6.0;
-
-// stolen from: https://github.com/gfx-rs/gfx/tree/master/examples/blend
-fn load_texture<R, F>(factory: &mut F,
-                      data: &[u8])
-                      -> Result<gfx::handle::ShaderResourceView<R, [f32; 4]>, String>
-    where R: gfx::Resources,
-          F: gfx::Factory<R>
-{
-    let img = image::load(Cursor::new(data), image::PNG).unwrap().to_rgba();
-    let (width, height) = img.dimensions();
-    let kind = texture::Kind::D2(width as texture::Size,
-                                 height as texture::Size,
-                                 texture::AaMode::Single);
-    let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Rgba8>(kind, &[&img])
-        .unwrap();
-    Ok(view)
+    (record, replay)
 }
 
 fn main() {
+    let (record_path, replay_path) = parse_args();
+    let mut recorder = record_path.as_ref().map(|_| Recorder::new());
+    let mut replayer = replay_path.map(|path| Replayer::new(Recording::load(path).unwrap()));
+
+    let mut events_loop = glutin::EventsLoop::new();
     let builder = glutin::WindowBuilder::new()
         .with_title("Kitten Keyboard")
-        .with_dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64));
+    let context = glutin::ContextBuilder::new()
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
         .with_gl_profile(glutin::GlProfile::Core)
-        .with_vsync();
+        .with_vsync(true);
 
-    let (window, mut device, mut factory, main_color, _) =
-        gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
+    let (window, mut device, mut factory, mut main_color, mut main_depth) =
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
 
-    let pso = factory.create_pipeline_simple(include_bytes!("shaders/sprite_vert.glsl"),
-                                include_bytes!("shaders/sprite_frag.glsl"),
-                                pipe::new())
-        .unwrap();
-
-    let (vertex_buffer, slice) =
-        factory.create_vertex_buffer_with_slice(&TEX_QUAD, &TEX_INDICES[..]);
+    // the sprite renderer owns the pipeline, quad and sampler the kitten is drawn with
+    let mut renderer = SpriteRenderer::new(&mut factory,
+                                           main_color.clone(),
+                                           WINDOW_WIDTH as f32,
+                                           WINDOW_HEIGHT as f32);
 
     let kitty_texture = load_texture(&mut factory, &include_bytes!("../images/kitty.png")[..])
         .unwrap();
-    let sampler = factory.create_sampler_linear();
-
-    let projection = cgmath::ortho(0.0,
-                                   WINDOW_WIDTH as f32,
-                                   0.0,
-                                   WINDOW_HEIGHT as f32,
-                                   NEAR_PLANE,
-                                   FAR_PLANE);
-
-    let mut kitten_position = [WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0];
 
-    let mut translation =
-        cgmath::Matrix4::from_translation(cgmath::Vector3::new(kitten_position[0],
-                                                               kitten_position[1],
-                                                               0.0));
-    let scale = cgmath::Matrix4::from_nonuniform_scale(SPRITE_WIDTH, SPRITE_HEIGHT, 1.0);
-
-
-    let mut view = View {
-        model: (translation * scale).into(),
-        projection: projection.into(),
-    };
-
-    let data = pipe::Data {
-        view: factory.create_constant_buffer(1),
-        texture_sampler: (kitty_texture, sampler),
-        out: main_color,
-        vbuf: vertex_buffer,
-    };
-
-    let mut input = Input::new();
+    let mut kitten_position = start_position();
+    let mut previous_position = kitten_position;
+
+    let mut game_loop = GameLoop::new(SystemClock, UPDATES_PER_SECOND);
+
+    // the arrow keys and WASD both move the kitten
+    let mut input = InputMap::new();
+    input.bind("move_left", Binding::Key(VirtualKeyCode::Left));
+    input.bind("move_left", Binding::Key(VirtualKeyCode::A));
+    input.bind("move_right", Binding::Key(VirtualKeyCode::Right));
+    input.bind("move_right", Binding::Key(VirtualKeyCode::D));
+    input.bind("move_down", Binding::Key(VirtualKeyCode::Down));
+    input.bind("move_down", Binding::Key(VirtualKeyCode::S));
+    input.bind("move_up", Binding::Key(VirtualKeyCode::Up));
+    input.bind("move_up", Binding::Key(VirtualKeyCode::W));
+    input.bind_axis("horizontal", "move_left", "move_right");
+    input.bind_axis("vertical", "move_down", "move_up");
+
+    // a missing bindings file just means the defaults above are used
+    match load_bindings(&mut input, BINDINGS_FILE) {
+        Ok(warnings) => {
+            for warning in warnings {
+                println!("{}: {:?}", BINDINGS_FILE, warning);
+            }
+        }
+        Err(BindingsError::Io(_)) => {}
+        Err(err) => println!("{}: {}, using the default bindings", BINDINGS_FILE, err),
+    }
 
     'main: loop {
         let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
-        for event in window.poll_events() {
+        if let Some(ref mut recorder) = recorder {
+            recorder.begin_frame();
+        }
+        match replayer {
+            Some(ref mut replayer) => {
+                replayer.next_frame(&mut input);
+            }
+            None => input.begin_frame(),
+        }
+
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
             match event {
-                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) |
-                Event::Closed => break 'main,
-                Event::KeyboardInput(_, _, _) => {
-                    input.handle_keyboard_input(event);
+                Event::WindowEvent {
+                    event: WindowEvent::KeyboardInput {
+                        input: KeyboardInput { virtual_keycode: Some(VirtualKeyCode::Escape), .. },
+                        ..
+                    },
+                    ..
+                } |
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => break 'main,
+                Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {
+                    // the window's color and depth views have to be recreated at the new size
+                    let size = size.to_physical(window.get_hidpi_factor());
+                    window.resize(size);
+                    window::update_views(&window, &mut main_color, &mut main_depth);
+                    renderer.set_target(main_color.clone());
+
+                    let viewport = Viewport::new(SCALING_POLICY,
+                                                 [WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32],
+                                                 [size.width as u32, size.height as u32]);
+                    renderer.set_viewport(&viewport);
+                }
+                _ => {
+                    if let Some(ref mut recorder) = recorder {
+                        recorder.record(&event);
+                    }
+                    // while replaying, the recording is the only source of input
+                    if replayer.is_none() {
+                        input.handle_event(&event);
+                    }
                 }
-                _ => {}
             }
         }
 
-        if input.left_is_pressed && !input.right_is_pressed {
-            kitten_position[0] -= KITTEN_SPEED;
-        } else if input.right_is_pressed && !input.left_is_pressed {
-            kitten_position[0] += KITTEN_SPEED;
+        // a replay runs the updates each frame ran when it was recorded, so it doesn't depend on
+        // how fast this machine draws. it shows every frame at its last update.
+        let step = game_loop.step();
+        let (updates, alpha) = match replayer {
+            Some(ref replayer) => (replayer.updates(), 1.0),
+            None => (step.updates, step.alpha),
+        };
+        if let Some(ref mut recorder) = recorder {
+            recorder.record_updates(updates);
         }
-
-        if input.down_is_pressed && !input.up_is_pressed {
-            kitten_position[1] -= KITTEN_SPEED;
-        } else if input.up_is_pressed && !input.down_is_pressed {
-            kitten_position[1] += KITTEN_SPEED;
+        for _ in 0..updates {
+            let dt = game_loop.timestep();
+            previous_position = kitten_position;
+            kitten_position[0] += input.axis_value("horizontal") * KITTEN_SPEED * dt;
+            kitten_position[1] += input.axis_value("vertical") * KITTEN_SPEED * dt;
         }
 
-        translation = cgmath::Matrix4::from_translation(cgmath::Vector3::new(kitten_position[0],
-                                                                             kitten_position[1],
-                                                                             0.0));
+        // draw the kitten between its last two updates so it moves smoothly at any frame rate
+        let position = [previous_position[0] + (kitten_position[0] - previous_position[0]) * alpha,
+                        previous_position[1] + (kitten_position[1] - previous_position[1]) * alpha];
 
-
-        view.model = (translation * scale).into();
-
-        // render everything
-        encoder.update_constant_buffer(&data.view, &view);
-        encoder.clear(&data.out, CLEAR_COLOR);
-        encoder.draw(&slice, &pso, &data);
+        renderer.clear(&mut encoder, CLEAR_COLOR);
+        renderer.draw_sprite(&mut encoder, &kitty_texture, position, SPRITE_SIZE);
         encoder.flush(&mut device);
         window.swap_buffers().unwrap();
         device.cleanup();
     }
+
+    if let (Some(recorder), Some(path)) = (recorder, record_path) {
+        recorder.finish().save(path).unwrap();
+    }
 }
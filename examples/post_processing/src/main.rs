@@ -0,0 +1,105 @@
+extern crate gfx;
+extern crate glutin;
+extern crate learn_gfx_2d;
+
+use gfx::Device;
+use glutin::dpi::LogicalSize;
+use glutin::{ElementState, Event, VirtualKeyCode, WindowEvent};
+use learn_gfx_2d::{ColorFormat, DepthFormat};
+use learn_gfx_2d::clock::{FrameClock, SystemClock};
+use learn_gfx_2d::post_process::{Effect, PostProcessChain, lut_from_fn};
+// the same bouncing kitten as the kitten_bouncing example, to have something to apply effects to
+use learn_gfx_2d::scenes::kitten_bouncing::{CLEAR_COLOR, SPRITE_SIZE, kitten_position};
+use learn_gfx_2d::sprite::SpriteRenderer;
+use learn_gfx_2d::texture::load_texture;
+use learn_gfx_2d::window;
+
+const WINDOW_WIDTH: u32 = 640;
+const WINDOW_HEIGHT: u32 = 480;
+
+fn main() {
+    let mut events_loop = glutin::EventsLoop::new();
+    let builder = glutin::WindowBuilder::new()
+        .with_title("Post Processing")
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64));
+    let context = glutin::ContextBuilder::new()
+        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
+        .with_gl_profile(glutin::GlProfile::Core)
+        .with_vsync(true);
+
+    let (window, mut device, mut factory, main_color, _) =
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
+
+    let mut renderer = SpriteRenderer::new(&mut factory,
+                                           main_color.clone(),
+                                           WINDOW_WIDTH as f32,
+                                           WINDOW_HEIGHT as f32);
+
+    // the kitten is drawn into the chain's scene target, and the number keys switch its
+    // effects on and off before it reaches the window
+    let mut post_process = PostProcessChain::new(&mut factory, WINDOW_WIDTH, WINDOW_HEIGHT)
+        .unwrap();
+    let sepia = lut_from_fn(16, |color| {
+        let luma = color[0] * 0.299 + color[1] * 0.587 + color[2] * 0.114;
+        [luma * 1.07, luma * 0.74, luma * 0.43]
+    });
+    post_process.set_lut(&mut factory, &sepia).unwrap();
+    for &effect in &[Effect::grayscale(),
+                     Effect::vignette(),
+                     Effect::scanlines(),
+                     Effect::bloom(),
+                     Effect::color_grade()] {
+        let index = post_process.push(effect);
+        post_process.set_enabled(index, false);
+    }
+    renderer.set_render_target(post_process.scene());
+
+    let kitty_texture = load_texture(&mut factory, &include_bytes!("../images/kitty.png")[..])
+        .unwrap();
+
+    let mut clock = FrameClock::new(SystemClock);
+
+    'main: loop {
+        let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
+            let input = match event {
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => break 'main,
+                Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => input,
+                _ => continue,
+            };
+            match (input.state, input.virtual_keycode) {
+                (_, Some(VirtualKeyCode::Escape)) => break 'main,
+                (ElementState::Pressed, Some(VirtualKeyCode::Space)) => clock.toggle_pause(),
+                (ElementState::Pressed, Some(key)) => {
+                    let pass = match key {
+                        VirtualKeyCode::Key1 => Some(0),
+                        VirtualKeyCode::Key2 => Some(1),
+                        VirtualKeyCode::Key3 => Some(2),
+                        VirtualKeyCode::Key4 => Some(3),
+                        VirtualKeyCode::Key5 => Some(4),
+                        _ => None,
+                    };
+                    if let Some(pass) = pass {
+                        post_process.toggle(pass);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        clock.tick();
+        let position = kitten_position(clock.elapsed() as f32);
+
+        // render everything
+        renderer.clear(&mut encoder, CLEAR_COLOR);
+        renderer.draw_sprite(&mut encoder, &kitty_texture, position, SPRITE_SIZE);
+        post_process.apply(&mut encoder, &main_color);
+        encoder.flush(&mut device);
+
+        window.swap_buffers().unwrap();
+        device.cleanup();
+    }
+}
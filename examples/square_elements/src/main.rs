@@ -1,13 +1,18 @@
 #[macro_use]
 extern crate gfx;
-extern crate gfx_window_glutin;
 extern crate glutin;
+extern crate learn_gfx_2d;
 
 pub type ColorFormat = gfx::format::Rgba8;
 pub type DepthFormat = gfx::format::DepthStencil;
 
 use gfx::traits::FactoryExt;
 use gfx::Device;
+use glutin::dpi::LogicalSize;
+// the square's four corners and the indices tying them into two triangles live in the library,
+// where the golden image check draws them too
+use learn_gfx_2d::scenes::square_elements::{CLEAR_COLOR, square_indices, square_vertices};
+use learn_gfx_2d::window;
 
 gfx_defines! {
     vertex Vertex {
@@ -21,42 +26,21 @@ gfx_defines! {
     }
 }
 
-const CLEAR_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
-
-// because we will specify the indices, we only need to define four vertices here
-const SQUARE_VERTICES: [Vertex; 4] = [Vertex {
-                                          position: [0.5, 0.5],
-                                          color: [1.0, 1.0, 0.0],
-                                      },
-                                      Vertex {
-                                          position: [0.5, -0.5],
-                                          color: [0.0, 1.0, 0.0],
-                                      },
-                                      Vertex {
-                                          position: [-0.5, -0.5],
-                                          color: [1.0, 0.0, 0.0],
-                                      },
-                                      Vertex {
-                                          position: [-0.5, 0.5],
-                                          color: [0.0, 0.0, 1.0],
-                                      }];
-
-// here we define the two triangles by saying which indices to use in what order
-const SQUARE_INDICES: [u16; 6] = [0, 3, 1, 1, 3, 2];
-
 const WINDOW_WIDTH: u32 = 640;
 const WINDOW_HEIGHT: u32 = 480;
 
 pub fn main() {
+    let mut events_loop = glutin::EventsLoop::new();
     let builder = glutin::WindowBuilder::new()
-        .with_dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64))
+        .with_title("Square Elements");
+    let context = glutin::ContextBuilder::new()
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
         .with_gl_profile(glutin::GlProfile::Core)
-        .with_title("Square Elements")
-        .with_vsync();
+        .with_vsync(true);
 
     let (window, mut device, mut factory, main_color, _) =
-        gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
 
     let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
@@ -65,8 +49,18 @@ pub fn main() {
                                 pipe::new())
         .unwrap();
 
+    // copy the square's corners into the vertex type our pipeline was defined with
+    let square_vertices: Vec<Vertex> = square_vertices().iter()
+        .map(|vertex| {
+            Vertex {
+                position: vertex.position,
+                color: vertex.color,
+            }
+        })
+        .collect();
+
     let (vertex_buffer, slice) =
-        factory.create_vertex_buffer_with_slice(&SQUARE_VERTICES, &SQUARE_INDICES[..]);
+        factory.create_vertex_buffer_with_slice(&square_vertices, &square_indices()[..]);
 
     let data = pipe::Data {
         vertex_buffer: vertex_buffer,
@@ -74,10 +68,22 @@ pub fn main() {
     };
 
     'main: loop {
-        for event in window.poll_events() {
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
             match event {
-                glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) |
-                glutin::Event::Closed => break 'main,
+                glutin::Event::WindowEvent {
+                    event: glutin::WindowEvent::KeyboardInput {
+                        input: glutin::KeyboardInput {
+                            virtual_keycode: Some(glutin::VirtualKeyCode::Escape), ..
+                        },
+                        ..
+                    },
+                    ..
+                } |
+                glutin::Event::WindowEvent { event: glutin::WindowEvent::CloseRequested, .. } => {
+                    break 'main
+                }
                 _ => {}
             }
         }
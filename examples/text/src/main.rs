@@ -0,0 +1,201 @@
+extern crate gfx;
+extern crate glutin;
+extern crate learn_gfx_2d;
+
+use gfx::Device;
+use glutin::dpi::LogicalSize;
+use glutin::{ElementState, Event, VirtualKeyCode, WindowEvent};
+use learn_gfx_2d::{ColorFormat, DepthFormat};
+use learn_gfx_2d::batch::SpriteBatchRenderer;
+use learn_gfx_2d::clock::{FrameClock, SystemClock};
+use learn_gfx_2d::text::bmfont::BitmapFont;
+use learn_gfx_2d::text::glyph_cache::GlyphCache;
+use learn_gfx_2d::text::layout::{Align, Layout};
+use learn_gfx_2d::text::markup::{RichText, Typewriter};
+use learn_gfx_2d::text::truetype::{TrueTypeFont, shape};
+use learn_gfx_2d::texture::load_texture;
+use learn_gfx_2d::window;
+
+const WINDOW_WIDTH: u32 = 640;
+const WINDOW_HEIGHT: u32 = 480;
+
+const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [1.0, 0.9, 0.3, 1.0];
+const TRUETYPE_COLOR: [f32; 4] = [0.8, 0.9, 1.0, 1.0];
+const DIALOGUE_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const DIALOGUE_BLIP_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+const MARGIN: f32 = 40.0;
+const MIN_WRAP_WIDTH: f32 = 80.0;
+// how much the arrow keys widen or narrow the text each press
+const WRAP_STEP: f32 = 20.0;
+
+// the truetype text can be drawn at any size, which the plus and minus keys change
+const MIN_FONT_SIZE: f32 = 8.0;
+const MAX_FONT_SIZE: f32 = 64.0;
+const FONT_SIZE_STEP: f32 = 2.0;
+
+const TEXT: &'static str = "The quick brown fox jumps over the lazy dog. Kerning pulls pairs such \
+                            as AV, To and Wa closer together.\nNew lines start new paragraphs, \
+                            and long lines wrap at the last space that fits.";
+
+const TRUETYPE_TEXT: &'static str = "TrueType, at any size: café, naïve\nКириллица: съешь же ещё \
+                                     этих мягких булок\nΕλληνικά: γαζέες καὶ μυρτιὲς";
+
+const DIALOGUE: &'static str = "[color=#8cf]Kitten:[/color] Did you see [b]that[/b]? The \
+                                [wave]fish[/wave] in the pond just \
+                                [shake][color=#f64]jumped[/color][/shake] out of the water!";
+
+// how fast the dialogue types itself out
+const CHARS_PER_SECOND: f32 = 20.0;
+
+// how long the dialogue stays brighter after each character is typed, in seconds
+const BLIP_TIME: f32 = 0.05;
+
+fn main() {
+    let mut events_loop = glutin::EventsLoop::new();
+    let builder = glutin::WindowBuilder::new()
+        .with_title("Text")
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64));
+    let context = glutin::ContextBuilder::new()
+        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
+        .with_gl_profile(glutin::GlProfile::Core)
+        .with_vsync(true);
+
+    let (window, mut device, mut factory, main_color, _) =
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
+
+    let mut renderer = SpriteBatchRenderer::new(&mut factory,
+                                                main_color.clone(),
+                                                WINDOW_WIDTH as f32,
+                                                WINDOW_HEIGHT as f32);
+
+    // the font's one page is registered with the renderer like any other texture
+    let font = BitmapFont::parse(include_str!("../fonts/dejavu_sans.fnt")).unwrap();
+    let page = load_texture(&mut factory, &include_bytes!("../fonts/dejavu_sans_0.png")[..])
+        .unwrap();
+    let pages = [renderer.add_texture(page)];
+
+    // truetype glyphs are rasterized into the cache as they're first drawn at each size, and
+    // the cache's texture is swapped for a new one whenever that happens
+    let mut glyph_cache = GlyphCache::new();
+    let font_data = include_bytes!("../fonts/DejaVuSans.ttf").to_vec();
+    let truetype_font = glyph_cache.add_font(TrueTypeFont::from_bytes(font_data).unwrap());
+    let cache_texture = glyph_cache.update_texture(&mut factory).unwrap().unwrap();
+    let cache_texture = renderer.add_texture(cache_texture);
+    let truetype_text = shape(TRUETYPE_TEXT);
+    let mut font_size = 24.0;
+
+    // space skips to the end of the dialogue, or starts it over once it's all showing
+    let mut dialogue = RichText::parse(DIALOGUE).unwrap();
+    let mut typewriter = Typewriter::new(dialogue.text(), CHARS_PER_SECOND);
+    let mut blip = 0.0;
+    let mut clock = FrameClock::new(SystemClock);
+
+    let mut batch = renderer.batch();
+    let mut align = Align::Left;
+    let mut wrap_width = WINDOW_WIDTH as f32 - MARGIN * 2.0;
+
+    'main: loop {
+        let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
+            let input = match event {
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => break 'main,
+                Event::WindowEvent { event: WindowEvent::KeyboardInput { input, .. }, .. } => input,
+                _ => continue,
+            };
+            match (input.state, input.virtual_keycode) {
+                (_, Some(VirtualKeyCode::Escape)) => break 'main,
+                (ElementState::Pressed, Some(key)) => {
+                    match key {
+                        VirtualKeyCode::L => align = Align::Left,
+                        VirtualKeyCode::C => align = Align::Center,
+                        VirtualKeyCode::R => align = Align::Right,
+                        VirtualKeyCode::Left => {
+                            wrap_width = (wrap_width - WRAP_STEP).max(MIN_WRAP_WIDTH)
+                        }
+                        VirtualKeyCode::Right => {
+                            wrap_width = (wrap_width + WRAP_STEP)
+                                .min(WINDOW_WIDTH as f32 - MARGIN * 2.0)
+                        }
+                        VirtualKeyCode::Equals => {
+                            font_size = (font_size + FONT_SIZE_STEP).min(MAX_FONT_SIZE)
+                        }
+                        VirtualKeyCode::Minus => {
+                            font_size = (font_size - FONT_SIZE_STEP).max(MIN_FONT_SIZE)
+                        }
+                        VirtualKeyCode::Space => {
+                            if typewriter.is_finished() {
+                                typewriter.restart();
+                            } else {
+                                typewriter.finish();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let dt = clock.tick();
+
+        // laying out every frame is cheap for a paragraph, a game would keep the layout until
+        // the text or box changes
+        let layout = Layout::new().with_align(align).with_max_width(wrap_width).layout(&font, TEXT);
+        font.draw(&mut batch,
+                  &pages,
+                  &layout,
+                  [MARGIN, WINDOW_HEIGHT as f32 - MARGIN],
+                  TEXT_COLOR);
+
+        glyph_cache.begin_frame();
+        let truetype_layout = Layout::new()
+            .with_align(align)
+            .with_max_width(wrap_width)
+            .layout(&glyph_cache.font(truetype_font, font_size), &truetype_text);
+        glyph_cache.queue(truetype_font, font_size, &truetype_layout).unwrap();
+        if let Some(texture) = glyph_cache.update_texture(&mut factory).unwrap() {
+            renderer.set_texture(cache_texture, texture);
+        }
+        glyph_cache.draw(&mut batch,
+                         cache_texture,
+                         truetype_font,
+                         font_size,
+                         &truetype_layout,
+                         [MARGIN, WINDOW_HEIGHT as f32 / 2.0],
+                         TRUETYPE_COLOR);
+
+        // a game would play a sound for each character typed, this flashes the text instead
+        blip = (blip - dt).max(0.0);
+        typewriter.update(dt, |_, character| {
+            if !character.is_whitespace() {
+                blip = BLIP_TIME;
+            }
+        });
+        dialogue.set_color(if blip > 0.0 {
+            DIALOGUE_BLIP_COLOR
+        } else {
+            DIALOGUE_COLOR
+        });
+
+        let dialogue_layout = Layout::new()
+            .with_max_width(wrap_width)
+            .layout(&font, dialogue.text());
+        dialogue.draw(&mut batch,
+                      &dialogue_layout,
+                      [MARGIN, MARGIN + dialogue_layout.height],
+                      clock.elapsed() as f32,
+                      typewriter.revealed(),
+                      |character| font.region(&pages, character));
+
+        renderer.clear(&mut encoder, CLEAR_COLOR);
+        renderer.flush(&mut factory, &mut encoder, &mut batch);
+        encoder.flush(&mut device);
+        window.swap_buffers().unwrap();
+        device.cleanup();
+    }
+}
@@ -0,0 +1,156 @@
+extern crate gfx;
+extern crate glutin;
+extern crate learn_gfx_2d;
+
+use gfx::Device;
+use glutin::dpi::LogicalSize;
+use glutin::{Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+use learn_gfx_2d::{ColorFormat, DepthFormat};
+use learn_gfx_2d::camera::Camera2D;
+use learn_gfx_2d::clock::{FrameClock, SystemClock};
+use learn_gfx_2d::input::{Binding, InputMap};
+use learn_gfx_2d::rect::Rect;
+use learn_gfx_2d::texture::load_texture;
+use learn_gfx_2d::tilemap::map::TileMap;
+use learn_gfx_2d::tilemap::renderer::TileMapRenderer;
+use learn_gfx_2d::tilemap::tiled::{PropertyValue, Tile, TiledMap};
+use learn_gfx_2d::window;
+use std::path::Path;
+
+const WINDOW_WIDTH: u32 = 640;
+const WINDOW_HEIGHT: u32 = 480;
+
+const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.2, 1.0];
+
+// the map's 16 pixel tiles are drawn twice as big
+const ZOOM: f32 = 2.0;
+
+// in world pixels per second
+const SCROLL_SPEED: f32 = 240.0;
+
+// clicking a ground tile turns it into a wall, or a wall back into dirt
+const DIRT: Tile = Tile(2);
+const WALL: Tile = Tile(4);
+
+fn main() {
+    // the map keeps its tileset inside itself, so the directory is only needed for maps with
+    // external tilesets
+    let map = TiledMap::from_tmx(include_str!("../maps/yard.tmx"),
+                                 Path::new("examples/tilemap/maps"))
+        .unwrap();
+    let title = match map.properties.get("title") {
+        Some(PropertyValue::String(title)) => title.clone(),
+        _ => "Tilemap".to_string(),
+    };
+
+    let mut events_loop = glutin::EventsLoop::new();
+    let builder = glutin::WindowBuilder::new()
+        .with_title(title.clone())
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64));
+    let context = glutin::ContextBuilder::new()
+        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
+        .with_gl_profile(glutin::GlProfile::Core)
+        .with_vsync(true);
+
+    let (window, mut device, mut factory, main_color, _) =
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
+
+    // chunks are meshed and uploaded the first time they scroll into view, and again only
+    // after one of their tiles is clicked
+    let tiles = load_texture(&mut factory, &include_bytes!("../maps/tiles.png")[..]).unwrap();
+    let mut tile_map = TileMap::from_tiled(&map).unwrap();
+    let ground = tile_map.layer_index("ground").unwrap();
+    let mut renderer = TileMapRenderer::new(&mut factory,
+                                            main_color.clone(),
+                                            WINDOW_WIDTH as f32,
+                                            WINDOW_HEIGHT as f32,
+                                            &tile_map,
+                                            vec![tiles])
+        .unwrap();
+
+    // the camera starts on the spawn point placed in Tiled, whose y counts down from the top
+    // of the map while the world's counts up
+    let map_size = tile_map.grid().pixel_size();
+    let (map_width, map_height) = (map_size[0], map_size[1]);
+    // the camera and the mouse both work in physical pixels, which on a hidpi screen are more
+    // than the window's logical size
+    let hidpi_factor = window.get_hidpi_factor();
+    let viewport = LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64)
+        .to_physical(hidpi_factor);
+    let mut camera = Camera2D::new(viewport.width as f32, viewport.height as f32);
+    camera.zoom = ZOOM;
+    camera.bounds = Some(Rect::new(0.0, 0.0, map_width, map_height));
+    for layer in map.object_layers() {
+        for object in layer.objects.iter().filter(|object| object.name == "spawn") {
+            camera.center = [object.x, map_height - object.y];
+        }
+    }
+    camera.clamp_to_bounds();
+
+    let mut input = InputMap::new();
+    input.mouse_mut().set_hidpi_factor(hidpi_factor);
+    input.bind("left", Binding::Key(VirtualKeyCode::Left));
+    input.bind("right", Binding::Key(VirtualKeyCode::Right));
+    input.bind("down", Binding::Key(VirtualKeyCode::Down));
+    input.bind("up", Binding::Key(VirtualKeyCode::Up));
+    input.bind_axis("horizontal", "left", "right");
+    input.bind_axis("vertical", "down", "up");
+
+    let mut clock = FrameClock::new(SystemClock);
+    let mut last_stats = None;
+
+    'main: loop {
+        let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+
+        input.begin_frame();
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
+            match event {
+                Event::WindowEvent {
+                    event: WindowEvent::KeyboardInput {
+                        input: KeyboardInput { virtual_keycode: Some(VirtualKeyCode::Escape), .. },
+                        ..
+                    },
+                    ..
+                } |
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => break 'main,
+                _ => input.handle_event(&event),
+            }
+        }
+
+        let dt = clock.tick();
+        camera.center[0] += input.axis_value("horizontal") * SCROLL_SPEED * dt;
+        camera.center[1] += input.axis_value("vertical") * SCROLL_SPEED * dt;
+        camera.clamp_to_bounds();
+        renderer.set_projection(camera.view_projection());
+
+        if input.mouse_just_pressed(MouseButton::Left) {
+            let position = input.mouse().world_position(&camera);
+            if let Some(cell) = tile_map.cell_at(position) {
+                let tile = if tile_map.tile(ground, cell[0], cell[1]) == WALL {
+                    DIRT
+                } else {
+                    WALL
+                };
+                tile_map.set_tile(ground, cell[0], cell[1], tile);
+            }
+        }
+
+        renderer.clear(&mut encoder, CLEAR_COLOR);
+        let stats = renderer.draw(&mut factory, &mut encoder, &mut tile_map);
+        // the title shows how many chunks are drawn, and how many had to be built, whenever
+        // that changes
+        if last_stats != Some(stats) {
+            window.set_title(&format!("{} - {} of {} chunks visible, {} rebuilt",
+                                      title,
+                                      stats.visible,
+                                      stats.chunks,
+                                      stats.rebuilt));
+            last_stats = Some(stats);
+        }
+        encoder.flush(&mut device);
+        window.swap_buffers().unwrap();
+        device.cleanup();
+    }
+}
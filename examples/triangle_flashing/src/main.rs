@@ -1,14 +1,20 @@
 #[macro_use]
 extern crate gfx;
-extern crate gfx_window_glutin;
 extern crate glutin;
-extern crate time;
+extern crate learn_gfx_2d;
 
 pub type ColorFormat = gfx::format::Rgba8;
 pub type DepthFormat = gfx::format::DepthStencil;
 
 use gfx::traits::FactoryExt;
 use gfx::Device;
+use glutin::dpi::LogicalSize;
+use glutin::{ElementState, Event, VirtualKeyCode, WindowEvent};
+use learn_gfx_2d::clock::{FrameClock, SystemClock};
+// the triangle and how its color changes over time live in the library, where the golden image
+// check draws them too
+use learn_gfx_2d::scenes::triangle_flashing::{self, CLEAR_COLOR};
+use learn_gfx_2d::window;
 use std::f32;
 
 gfx_defines! {
@@ -28,25 +34,21 @@ gfx_defines! {
     }
 }
 
-const CLEAR_COLOR: [f32; 4] = [0.7, 0.85, 0.7, 1.0];
-
-const TRIANGLE: [Vertex; 3] = [Vertex { position: [-0.5, -0.5] },
-                               Vertex { position: [0.5, -0.5] },
-                               Vertex { position: [0.0, 0.5] }];
-
 const WINDOW_WIDTH: u32 = 640;
 const WINDOW_HEIGHT: u32 = 480;
 
 pub fn main() {
+    let mut events_loop = glutin::EventsLoop::new();
     let builder = glutin::WindowBuilder::new()
-        .with_dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64))
+        .with_title("Triangle Flashing");
+    let context = glutin::ContextBuilder::new()
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
         .with_gl_profile(glutin::GlProfile::Core)
-        .with_title("Triangle Flashing")
-        .with_vsync();
+        .with_vsync(true);
 
     let (window, mut device, mut factory, main_color, _) =
-        gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
 
     let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
@@ -55,7 +57,10 @@ pub fn main() {
                                 pipe::new())
         .unwrap();
 
-    let (vertex_buffer, slice) = factory.create_vertex_buffer_with_slice(&TRIANGLE, ());
+    let triangle: Vec<Vertex> = triangle_flashing::triangle().iter()
+        .map(|&position| Vertex { position: position })
+        .collect();
+    let (vertex_buffer, slice) = factory.create_vertex_buffer_with_slice(&triangle, ());
 
     // this time we add a constant buffer to pass color data through
     let data = pipe::Data {
@@ -67,19 +72,33 @@ pub fn main() {
     // this will hold the dynamic color value we will be flashing
     let mut color_data = ColorData { color: [1.0, 1.0, 1.0] };
 
+    // game time rather than the system's, so space can pause the flashing
+    let mut clock = FrameClock::new(SystemClock);
+
     'main: loop {
-        for event in window.poll_events() {
-            match event {
-                glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) |
-                glutin::Event::Closed => break 'main,
-                _ => {}
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
+            if let Event::WindowEvent { event, .. } = event {
+                match event {
+                    WindowEvent::CloseRequested => break 'main,
+                    WindowEvent::KeyboardInput { input, .. } => {
+                        match (input.state, input.virtual_keycode) {
+                            (_, Some(VirtualKeyCode::Escape)) => break 'main,
+                            (ElementState::Pressed, Some(VirtualKeyCode::Space)) => {
+                                clock.toggle_pause()
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
             }
         }
 
         // flash the triangle's color based on time
-        let seconds = time::precise_time_s() as f32;
-        let color_value = seconds.sin().abs();
-        color_data.color = [color_value, color_value, color_value];
+        clock.tick();
+        color_data.color = triangle_flashing::color(clock.elapsed() as f32);
 
         // modify the color data constant buffer with the new dynamic color
         encoder.update_constant_buffer(&data.color_data, &color_data);
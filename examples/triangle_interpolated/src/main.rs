@@ -1,13 +1,18 @@
 #[macro_use]
 extern crate gfx;
-extern crate gfx_window_glutin;
 extern crate glutin;
+extern crate learn_gfx_2d;
 
 pub type ColorFormat = gfx::format::Rgba8;
 pub type DepthFormat = gfx::format::DepthStencil;
 
 use gfx::traits::FactoryExt;
 use gfx::Device;
+use glutin::dpi::LogicalSize;
+// the triangle's points and colors live in the library, where the golden image check draws
+// them too
+use learn_gfx_2d::scenes::triangle_interpolated::{CLEAR_COLOR, triangle};
+use learn_gfx_2d::window;
 
 // this is a macro that allows use to describe the forms of data we will use
 gfx_defines! {
@@ -24,41 +29,25 @@ gfx_defines! {
     }
 }
 
-const CLEAR_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
-
-// here we define our triangle object in terms of points and colors
-// note that the position is using 'normalized device coordinates',
-// running from -1.0 to 1.0 in both the x and y axis
-const TRIANGLE: [Vertex; 3] = [Vertex {
-                                   position: [-0.5, -0.5],
-                                   color: [1.0, 0.0, 0.0],
-                               },
-                               Vertex {
-                                   position: [0.5, -0.5],
-                                   color: [0.0, 1.0, 0.0],
-                               },
-                               Vertex {
-                                   position: [0.0, 0.5],
-                                   color: [0.0, 0.0, 1.0],
-                               }];
-
 const WINDOW_WIDTH: u32 = 640;
 const WINDOW_HEIGHT: u32 = 480;
 
 pub fn main() {
     // set our application to have the attributes we want, such as title and size
+    let mut events_loop = glutin::EventsLoop::new();
     let builder = glutin::WindowBuilder::new()
-        .with_dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .with_dimensions(LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64))
+        .with_title("Triangle Interpolated");
+    let context = glutin::ContextBuilder::new()
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
         .with_gl_profile(glutin::GlProfile::Core)
-        .with_title("Triangle Interpolated")
-        .with_vsync();
+        .with_vsync(true);
 
-    // init everything provided by gfx_window_glutin
+    // open the window and init everything gfx needs to draw to it
     let (window, mut device, mut factory, main_color, _) =
-        gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
 
-    // build an encoder from the factory provided by gfx_window_glutin,
+    // build an encoder from the factory provided by window::init,
     // this encoder 'encodes' the commands we will be passing to the gpu
     let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
@@ -69,7 +58,17 @@ pub fn main() {
         .unwrap();
 
     // generate the actual vertex buffer and a slice of data will use
-    let (vertex_buffer, slice) = factory.create_vertex_buffer_with_slice(&TRIANGLE, ());
+    // copy the triangle into the vertex type our pipeline was defined with
+    let triangle: Vec<Vertex> = triangle().iter()
+        .map(|vertex| {
+            Vertex {
+                position: vertex.position,
+                color: vertex.color,
+            }
+        })
+        .collect();
+
+    let (vertex_buffer, slice) = factory.create_vertex_buffer_with_slice(&triangle, ());
 
     // supply the pipeline with actual data, remember this is using the
     // definition we supplied in the gfx_defines! macro near the beginning
@@ -80,15 +79,27 @@ pub fn main() {
 
     'main: loop {
         // quit our main loop if the window is closed or the escape key pressed
-        for event in window.poll_events() {
+        let mut events = Vec::new();
+        events_loop.poll_events(|event| events.push(event));
+        for event in events {
             match event {
-                glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) |
-                glutin::Event::Closed => break 'main,
+                glutin::Event::WindowEvent {
+                    event: glutin::WindowEvent::KeyboardInput {
+                        input: glutin::KeyboardInput {
+                            virtual_keycode: Some(glutin::VirtualKeyCode::Escape), ..
+                        },
+                        ..
+                    },
+                    ..
+                } |
+                glutin::Event::WindowEvent { event: glutin::WindowEvent::CloseRequested, .. } => {
+                    break 'main
+                }
                 _ => {}
             }
         }
 
-        // clear the screen using our CLEAR_COLOR defined before main
+        // clear the screen using the scene's CLEAR_COLOR
         encoder.clear(&data.out_color, CLEAR_COLOR);
 
         // use the encoder to queue up a draw command with all of our data and the pipeline object
@@ -0,0 +1,381 @@
+use atlas::AtlasTable;
+use rect::Rect;
+
+// frames shorter than this are stretched to it, so a zero duration can't stall update
+const MIN_FRAME_DURATION: f32 = 0.0001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    // normalized, with the origin at the sheet's top left like a Sprite's uv
+    pub uv: Rect,
+    // in seconds
+    pub duration: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaybackMode {
+    Loop,
+    // plays to the last frame and then back to the first, without repeating either end
+    PingPong,
+    // stops on the last frame
+    Once,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    FrameChanged(usize),
+    // a Loop or PingPong clip has come back around to its first frame
+    Looped,
+    // a Once clip has finished showing its last frame
+    Finished,
+}
+
+// slices a sprite sheet laid out as a grid, counting frames left to right and top to bottom
+pub fn grid_frames(sheet_size: [u32; 2],
+                   frame_size: [u32; 2],
+                   indices: &[usize],
+                   duration: f32)
+                   -> Vec<Frame> {
+    assert!(frame_size[0] > 0 && frame_size[1] > 0,
+            "grid frames need a size, not {:?}",
+            frame_size);
+    assert!(sheet_size[0] > 0 && sheet_size[1] > 0,
+            "a grid needs a sheet with a size, not {:?}",
+            sheet_size);
+    let columns = (sheet_size[0] / frame_size[0]).max(1) as usize;
+    let width = frame_size[0] as f32 / sheet_size[0] as f32;
+    let height = frame_size[1] as f32 / sheet_size[1] as f32;
+
+    indices.iter()
+        .map(|&index| {
+            let column = (index % columns) as f32;
+            let row = (index / columns) as f32;
+            Frame {
+                uv: Rect::new(column * width, row * height, width, height),
+                duration: duration,
+            }
+        })
+        .collect()
+}
+
+// looks frames up by name in a packed atlas, or returns the first name that is missing
+pub fn atlas_frames(table: &AtlasTable, names: &[&str], duration: f32) -> Result<Vec<Frame>, String> {
+    names.iter()
+        .map(|name| {
+            table.uv(name)
+                .map(|uv| {
+                    Frame {
+                        uv: uv,
+                        duration: duration,
+                    }
+                })
+                .ok_or_else(|| name.to_string())
+        })
+        .collect()
+}
+
+pub struct Animation {
+    frames: Vec<Frame>,
+    mode: PlaybackMode,
+    speed: f32,
+    current: usize,
+    elapsed: f32,
+    forward: bool,
+    playing: bool,
+    finished: bool,
+    events: Vec<AnimationEvent>,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<Frame>, mode: PlaybackMode) -> Self {
+        assert!(!frames.is_empty(), "an animation needs at least one frame");
+        Animation {
+            frames: frames,
+            mode: mode,
+            speed: 1.0,
+            current: 0,
+            elapsed: 0.0,
+            forward: true,
+            playing: true,
+            finished: false,
+            events: Vec::new(),
+        }
+    }
+
+    // advances the clip by dt seconds scaled by the speed. the events that happened during
+    // this step are available from events until the next update.
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+        if !self.playing || self.finished {
+            return;
+        }
+
+        self.elapsed += dt * self.speed;
+        loop {
+            let duration = self.frames[self.current].duration.max(MIN_FRAME_DURATION);
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            self.advance();
+            if self.finished {
+                self.elapsed = 0.0;
+                break;
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        let last = self.frames.len() - 1;
+        let previous = self.current;
+
+        match self.mode {
+            PlaybackMode::Loop => {
+                if self.current == last {
+                    self.current = 0;
+                    self.events.push(AnimationEvent::Looped);
+                } else {
+                    self.current += 1;
+                }
+            }
+            PlaybackMode::Once => {
+                if self.current == last {
+                    self.finished = true;
+                    self.events.push(AnimationEvent::Finished);
+                } else {
+                    self.current += 1;
+                }
+            }
+            PlaybackMode::PingPong => {
+                if last == 0 {
+                    self.events.push(AnimationEvent::Looped);
+                } else if self.forward {
+                    self.current += 1;
+                    if self.current == last {
+                        self.forward = false;
+                    }
+                } else {
+                    self.current -= 1;
+                    if self.current == 0 {
+                        self.forward = true;
+                        self.events.push(AnimationEvent::Looped);
+                    }
+                }
+            }
+        }
+
+        if self.current != previous {
+            self.events.push(AnimationEvent::FrameChanged(self.current));
+        }
+    }
+
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frames[self.current]
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    // the part of the sheet to draw right now
+    pub fn uv(&self) -> Rect {
+        self.frame().uv
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // scales how fast time passes for this clip, negative speeds are treated as paused
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing && !self.finished
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    // rewinds to the first frame and starts playing again
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.finished = false;
+        self.playing = true;
+        self.events.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // four frames a second, each frame's uv starting at its index so it's easy to tell apart
+    fn clip(count: usize, mode: PlaybackMode) -> Animation {
+        let frames = (0..count)
+            .map(|index| {
+                Frame {
+                    uv: Rect::new(index as f32, 0.0, 1.0, 1.0),
+                    duration: 0.25,
+                }
+            })
+            .collect();
+        Animation::new(frames, mode)
+    }
+
+    // the frame shown after each of a number of quarter second updates
+    fn steps(animation: &mut Animation, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                animation.update(0.25);
+                animation.frame_index()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_wraps_around() {
+        let mut animation = clip(3, PlaybackMode::Loop);
+        assert_eq!(steps(&mut animation, 7), vec![1, 2, 0, 1, 2, 0, 1]);
+        assert!(animation.is_playing());
+    }
+
+    #[test]
+    fn ping_pong_turns_at_each_end() {
+        let mut animation = clip(3, PlaybackMode::PingPong);
+        assert_eq!(steps(&mut animation, 8), vec![1, 2, 1, 0, 1, 2, 1, 0]);
+
+        let mut single = clip(1, PlaybackMode::PingPong);
+        assert_eq!(steps(&mut single, 2), vec![0, 0]);
+        assert_eq!(single.events(), &[AnimationEvent::Looped]);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let mut animation = clip(3, PlaybackMode::Once);
+        assert_eq!(steps(&mut animation, 2), vec![1, 2]);
+        assert!(!animation.is_finished());
+
+        animation.update(0.25);
+        assert_eq!(animation.frame_index(), 2);
+        assert!(animation.is_finished() && !animation.is_playing());
+        assert_eq!(animation.events(), &[AnimationEvent::Finished]);
+
+        // once it's finished nothing more happens until it's restarted
+        animation.update(10.0);
+        assert_eq!(animation.frame_index(), 2);
+        assert!(animation.events().is_empty());
+        animation.restart();
+        assert_eq!(animation.frame_index(), 0);
+        assert!(animation.is_playing());
+    }
+
+    #[test]
+    fn events_last_one_update() {
+        let mut animation = clip(2, PlaybackMode::Loop);
+        animation.update(0.1);
+        assert!(animation.events().is_empty());
+        animation.update(0.15);
+        assert_eq!(animation.events(), &[AnimationEvent::FrameChanged(1)]);
+        animation.update(0.25);
+        assert_eq!(animation.events(),
+                   &[AnimationEvent::Looped, AnimationEvent::FrameChanged(0)]);
+        animation.update(0.1);
+        assert!(animation.events().is_empty());
+
+        // a long update steps through several frames and reports each of them
+        animation.update(0.5);
+        assert_eq!(animation.events(),
+                   &[AnimationEvent::FrameChanged(1),
+                     AnimationEvent::Looped,
+                     AnimationEvent::FrameChanged(0)]);
+    }
+
+    #[test]
+    fn ping_pong_reports_coming_back_to_the_start() {
+        let mut animation = clip(2, PlaybackMode::PingPong);
+        animation.update(0.25);
+        assert_eq!(animation.events(), &[AnimationEvent::FrameChanged(1)]);
+        animation.update(0.25);
+        assert_eq!(animation.events(),
+                   &[AnimationEvent::Looped, AnimationEvent::FrameChanged(0)]);
+    }
+
+    #[test]
+    fn speed_scales_time() {
+        let mut animation = clip(4, PlaybackMode::Loop);
+        animation.set_speed(2.0);
+        animation.update(0.25);
+        assert_eq!(animation.frame_index(), 2);
+
+        animation.set_speed(0.5);
+        animation.update(0.25);
+        assert_eq!(animation.frame_index(), 2);
+        animation.update(0.25);
+        assert_eq!(animation.frame_index(), 3);
+
+        // negative speeds pause rather than run backwards
+        animation.set_speed(-1.0);
+        assert_eq!(animation.speed(), 0.0);
+        animation.update(1.0);
+        assert_eq!(animation.frame_index(), 3);
+    }
+
+    #[test]
+    fn paused_clips_hold_their_frame() {
+        let mut animation = clip(3, PlaybackMode::Loop);
+        animation.pause();
+        animation.update(1.0);
+        assert_eq!(animation.frame_index(), 0);
+        animation.play();
+        animation.update(0.25);
+        assert_eq!(animation.frame_index(), 1);
+    }
+
+    #[test]
+    fn grid_frames_count_across_then_down() {
+        // a 4 by 2 grid of 16 by 32 frames
+        let frames = grid_frames([64, 64], [16, 32], &[0, 3, 4, 6], 0.1);
+        let uvs: Vec<Rect> = frames.iter().map(|frame| frame.uv).collect();
+        assert_eq!(uvs,
+                   vec![Rect::new(0.0, 0.0, 0.25, 0.5),
+                        Rect::new(0.75, 0.0, 0.25, 0.5),
+                        Rect::new(0.0, 0.5, 0.25, 0.5),
+                        Rect::new(0.5, 0.5, 0.25, 0.5)]);
+        assert!(frames.iter().all(|frame| frame.duration == 0.1));
+    }
+
+    #[test]
+    #[should_panic(expected = "grid frames need a size")]
+    fn grid_frames_without_a_width_panic() {
+        grid_frames([64, 64], [0, 16], &[0], 0.1);
+    }
+}
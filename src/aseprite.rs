@@ -0,0 +1,469 @@
+use animation::{Animation, Frame, PlaybackMode};
+use atlas::PixelRect;
+use rect::Rect;
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+// the shapes below mirror Aseprite's sprite sheet export, only keeping what is used
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct AseRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct AseSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct AsePoint {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct AseFrame {
+    frame: AseRect,
+    #[serde(default)]
+    rotated: bool,
+    // where the frame's pixels were in the untrimmed sprite, and how big that was
+    #[serde(rename = "spriteSourceSize")]
+    sprite_source_size: Option<AseRect>,
+    #[serde(rename = "sourceSize")]
+    source_size: Option<AseSize>,
+    // milliseconds
+    duration: u32,
+}
+
+impl AseFrame {
+    // whether empty space was cut off around the frame, so it is smaller than the sprite
+    fn is_trimmed(&self) -> bool {
+        let source = match self.sprite_source_size {
+            Some(source) => source,
+            None => return false,
+        };
+        let size = self.source_size.unwrap_or(AseSize {
+            w: self.frame.w,
+            h: self.frame.h,
+        });
+        source.x != 0 || source.y != 0 || source.w != size.w || source.h != size.h
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AseTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AseSliceKey {
+    frame: usize,
+    bounds: AseRect,
+    pivot: Option<AsePoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AseSlice {
+    name: String,
+    keys: Vec<AseSliceKey>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AseMeta {
+    #[serde(default)]
+    image: String,
+    size: AseSize,
+    #[serde(default, rename = "frameTags")]
+    frame_tags: Vec<AseTag>,
+    #[serde(default)]
+    slices: Vec<AseSlice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AseSheet {
+    #[serde(deserialize_with = "deserialize_frames")]
+    frames: Vec<AseFrame>,
+    meta: AseMeta,
+}
+
+// Aseprite writes frames either as an array or as a map keyed by file name. the map's order
+// is the frame order, so it's read entry by entry instead of into a sorted map.
+fn deserialize_frames<'de, D>(deserializer: D) -> Result<Vec<AseFrame>, D::Error>
+    where D: Deserializer<'de>
+{
+    struct FramesVisitor;
+
+    impl<'de> Visitor<'de> for FramesVisitor {
+        type Value = Vec<AseFrame>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an array or map of frames")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where A: SeqAccess<'de>
+        {
+            let mut frames = Vec::new();
+            while let Some(frame) = seq.next_element()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where A: MapAccess<'de>
+        {
+            let mut frames = Vec::new();
+            while let Some((_, frame)) = map.next_entry::<String, AseFrame>()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+    }
+
+    deserializer.deserialize_any(FramesVisitor)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+impl Direction {
+    fn parse(direction: &str) -> Result<Self, AsepriteError> {
+        match direction {
+            "" | "forward" => Ok(Direction::Forward),
+            "reverse" => Ok(Direction::Reverse),
+            "pingpong" => Ok(Direction::PingPong),
+            "pingpong_reverse" => Ok(Direction::PingPongReverse),
+            other => Err(AsepriteError::Unsupported(format!("tag direction {}", other))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    // the first and last frame of the tag, both included
+    pub from: usize,
+    pub to: usize,
+    pub direction: Direction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SliceKey {
+    // the frame this key starts applying from
+    pub frame: usize,
+    // in pixels, relative to the top left of the sprite
+    pub bounds: PixelRect,
+    // in pixels, relative to the top left of the bounds
+    pub pivot: Option<[i32; 2]>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slice {
+    pub name: String,
+    pub keys: Vec<SliceKey>,
+}
+
+impl Slice {
+    // the key in effect on the given frame, which is the last one starting at or before it
+    pub fn key(&self, frame: usize) -> Option<&SliceKey> {
+        self.keys.iter().rev().find(|key| key.frame <= frame)
+    }
+}
+
+#[derive(Debug)]
+pub enum AsepriteError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    // something the export contains that this importer can't represent
+    Unsupported(String),
+    // a tag refers to frames the sheet doesn't have
+    InvalidTag(String),
+}
+
+impl fmt::Display for AsepriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AsepriteError::Io(ref err) => write!(f, "{}", err),
+            AsepriteError::Json(ref err) => write!(f, "{}", err),
+            AsepriteError::Unsupported(ref what) => write!(f, "unsupported {}", what),
+            AsepriteError::InvalidTag(ref name) => {
+                write!(f, "tag {} refers to frames outside the sheet", name)
+            }
+        }
+    }
+}
+
+impl Error for AsepriteError {}
+
+impl From<io::Error> for AsepriteError {
+    fn from(err: io::Error) -> Self {
+        AsepriteError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for AsepriteError {
+    fn from(err: serde_json::Error) -> Self {
+        AsepriteError::Json(err)
+    }
+}
+
+// a sprite sheet exported from Aseprite, with its frames already converted to uvs
+pub struct AsepriteSheet {
+    // the sheet's png, relative to the json file
+    pub image: String,
+    pub size: [u32; 2],
+    pub frames: Vec<Frame>,
+    pub tags: Vec<Tag>,
+    pub slices: Vec<Slice>,
+}
+
+impl AsepriteSheet {
+    pub fn from_json(json: &str) -> Result<Self, AsepriteError> {
+        let sheet: AseSheet = serde_json::from_str(json)?;
+        AsepriteSheet::from_export(sheet)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AsepriteError> {
+        let file = File::open(path)?;
+        let sheet: AseSheet = serde_json::from_reader(file)?;
+        AsepriteSheet::from_export(sheet)
+    }
+
+    fn from_export(sheet: AseSheet) -> Result<Self, AsepriteError> {
+        let width = sheet.meta.size.w as f32;
+        let height = sheet.meta.size.h as f32;
+
+        let mut frames = Vec::with_capacity(sheet.frames.len());
+        for frame in &sheet.frames {
+            if frame.rotated {
+                return Err(AsepriteError::Unsupported("rotated frames".to_string()));
+            }
+            // a Frame has nowhere to keep the offset a trimmed frame is drawn at, so it would
+            // be stretched over the whole sprite
+            if frame.is_trimmed() {
+                return Err(AsepriteError::Unsupported("trimmed frames".to_string()));
+            }
+            let rect = frame.frame;
+            frames.push(Frame {
+                uv: Rect::new(rect.x as f32 / width,
+                              rect.y as f32 / height,
+                              rect.w as f32 / width,
+                              rect.h as f32 / height),
+                duration: frame.duration as f32 / 1000.0,
+            });
+        }
+
+        let mut tags = Vec::with_capacity(sheet.meta.frame_tags.len());
+        for tag in sheet.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                return Err(AsepriteError::InvalidTag(tag.name));
+            }
+            tags.push(Tag {
+                direction: Direction::parse(&tag.direction)?,
+                name: tag.name,
+                from: tag.from,
+                to: tag.to,
+            });
+        }
+
+        let slices = sheet.meta
+            .slices
+            .into_iter()
+            .map(|slice| {
+                Slice {
+                    name: slice.name,
+                    keys: slice.keys
+                        .iter()
+                        .map(|key| {
+                            SliceKey {
+                                frame: key.frame,
+                                bounds: PixelRect {
+                                    x: key.bounds.x,
+                                    y: key.bounds.y,
+                                    width: key.bounds.w,
+                                    height: key.bounds.h,
+                                },
+                                pivot: key.pivot.map(|pivot| [pivot.x, pivot.y]),
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Ok(AsepriteSheet {
+            image: sheet.meta.image,
+            size: [sheet.meta.size.w, sheet.meta.size.h],
+            frames: frames,
+            tags: tags,
+            slices: slices,
+        })
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&Slice> {
+        self.slices.iter().find(|slice| slice.name == name)
+    }
+
+    // a looping clip of the tagged frames, played in the tag's direction
+    pub fn clip(&self, name: &str) -> Option<Animation> {
+        self.tag(name).map(|tag| {
+            let mut frames = self.frames[tag.from..tag.to + 1].to_vec();
+            match tag.direction {
+                Direction::Forward => Animation::new(frames, PlaybackMode::Loop),
+                Direction::Reverse => {
+                    frames.reverse();
+                    Animation::new(frames, PlaybackMode::Loop)
+                }
+                Direction::PingPong => Animation::new(frames, PlaybackMode::PingPong),
+                Direction::PingPongReverse => {
+                    frames.reverse();
+                    Animation::new(frames, PlaybackMode::PingPong)
+                }
+            }
+        })
+    }
+
+    // every tag's clip, by tag name. sheets without tags get one clip of all their frames
+    // under the empty name.
+    pub fn clips(&self) -> HashMap<String, Animation> {
+        let mut clips = HashMap::new();
+        if self.tags.is_empty() {
+            if !self.frames.is_empty() {
+                clips.insert(String::new(),
+                             Animation::new(self.frames.clone(), PlaybackMode::Loop));
+            }
+        } else {
+            for tag in &self.tags {
+                if let Some(clip) = self.clip(&tag.name) {
+                    clips.insert(tag.name.clone(), clip);
+                }
+            }
+        }
+        clips
+    }
+
+    // where the named slice's pivot is on the given frame, in pixels from the sprite's top left
+    pub fn pivot(&self, slice: &str, frame: usize) -> Option<[i32; 2]> {
+        self.slice(slice)
+            .and_then(|slice| slice.key(frame))
+            .and_then(|key| {
+                key.pivot.map(|pivot| {
+                    [key.bounds.x as i32 + pivot[0], key.bounds.y as i32 + pivot[1]]
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &'static str = include_str!("../tests/fixtures/aseprite/kitten_hash.json");
+    const ARRAY: &'static str = include_str!("../tests/fixtures/aseprite/kitten_array.json");
+    const TRIMMED: &'static str = include_str!("../tests/fixtures/aseprite/kitten_trimmed.json");
+
+    #[test]
+    fn hash_frames_keep_their_order() {
+        let sheet = AsepriteSheet::from_json(HASH).unwrap();
+        assert_eq!(sheet.image, "kitten.png");
+        assert_eq!(sheet.size, [128, 32]);
+        let durations: Vec<f32> = sheet.frames.iter().map(|frame| frame.duration).collect();
+        assert_eq!(durations, vec![0.1, 0.15, 0.05, 0.2]);
+        assert_eq!(sheet.frames[1].uv, Rect::new(0.25, 0.0, 0.25, 1.0));
+    }
+
+    #[test]
+    fn array_and_hash_agree() {
+        let hash = AsepriteSheet::from_json(HASH).unwrap();
+        let array = AsepriteSheet::from_json(ARRAY).unwrap();
+        assert_eq!(array.frames, hash.frames);
+        assert_eq!(array.tags, hash.tags);
+        assert_eq!(array.slices, hash.slices);
+    }
+
+    #[test]
+    fn tags_play_in_their_direction() {
+        let sheet = AsepriteSheet::from_json(ARRAY).unwrap();
+        assert_eq!(sheet.tag("idle").unwrap().direction, Direction::Forward);
+        assert_eq!(sheet.tag("blink").unwrap().direction, Direction::Reverse);
+        assert_eq!(sheet.tag("all").unwrap().direction, Direction::PingPong);
+
+        let idle = sheet.clip("idle").unwrap();
+        assert_eq!(idle.mode(), PlaybackMode::Loop);
+        assert_eq!(idle.frame_count(), 2);
+        assert_eq!(idle.uv(), sheet.frames[0].uv);
+
+        let blink = sheet.clip("blink").unwrap();
+        assert_eq!(blink.mode(), PlaybackMode::Loop);
+        assert_eq!(blink.uv(), sheet.frames[3].uv);
+
+        let all = sheet.clip("all").unwrap();
+        assert_eq!(all.mode(), PlaybackMode::PingPong);
+        assert_eq!(all.frame_count(), 4);
+
+        let clips = sheet.clips();
+        assert_eq!(clips.len(), 3);
+        assert!(sheet.clip("run").is_none());
+    }
+
+    #[test]
+    fn slices_follow_their_keys() {
+        let sheet = AsepriteSheet::from_json(HASH).unwrap();
+        let feet = sheet.slice("feet").unwrap();
+        assert_eq!(feet.key(1).unwrap().frame, 0);
+        assert_eq!(feet.key(3).unwrap().bounds,
+                   PixelRect {
+                       x: 6,
+                       y: 22,
+                       width: 20,
+                       height: 10,
+                   });
+        assert_eq!(sheet.pivot("feet", 0), Some([16, 32]));
+        assert_eq!(sheet.pivot("feet", 2), Some([16, 32]));
+        assert_eq!(sheet.pivot("hitbox", 0), None);
+        assert_eq!(sheet.pivot("tail", 0), None);
+    }
+
+    #[test]
+    fn trimmed_frames_are_refused() {
+        match AsepriteSheet::from_json(TRIMMED) {
+            Err(AsepriteError::Unsupported(what)) => assert_eq!(what, "trimmed frames"),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("a trimmed frame was accepted"),
+        }
+    }
+
+    #[test]
+    fn tags_past_the_last_frame_are_refused() {
+        let json = ARRAY.replace(r#""from": 0, "to": 3"#, r#""from": 0, "to": 4"#);
+        match AsepriteSheet::from_json(&json) {
+            Err(AsepriteError::InvalidTag(name)) => assert_eq!(name, "all"),
+            _ => panic!("the tag should have been refused"),
+        }
+    }
+}
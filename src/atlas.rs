@@ -0,0 +1,539 @@
+use image::{GenericImage, RgbaImage};
+use rect::Rect;
+use serde_json;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+const DEFAULT_PADDING: u32 = 1;
+const DEFAULT_MAX_SIZE: u32 = 4096;
+
+// a region of the atlas image in pixels, with the origin at the image's top left
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// one step of the skyline: a horizontal run of `width` pixels whose lowest free row is `y`
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+// places rectangles with the skyline bottom-left heuristic: each rectangle goes wherever the
+// top of the already packed rectangles is lowest, preferring the left most spot on ties
+pub struct SkylinePacker {
+    width: u32,
+    height: u32,
+    skyline: Vec<Segment>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        SkylinePacker {
+            width: width,
+            height: height,
+            skyline: vec![Segment {
+                              x: 0,
+                              y: 0,
+                              width: width,
+                          }],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // makes room to the right and below, leaving everything packed so far where it is
+    pub fn grow(&mut self, width: u32, height: u32) {
+        if width > self.width {
+            let extra = width - self.width;
+            let last = self.skyline.len() - 1;
+            if self.skyline[last].y == 0 {
+                self.skyline[last].width += extra;
+            } else {
+                self.skyline.push(Segment {
+                    x: self.width,
+                    y: 0,
+                    width: extra,
+                });
+            }
+            self.width = width;
+        }
+        self.height = self.height.max(height);
+    }
+
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<PixelRect> {
+        let mut best: Option<(usize, u32)> = None;
+
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fits(index, width, height) {
+                let better = match best {
+                    Some((_, best_y)) => y < best_y,
+                    None => true,
+                };
+                if better {
+                    best = Some((index, y));
+                }
+            }
+        }
+
+        best.map(|(index, y)| {
+            let rect = PixelRect {
+                x: self.skyline[index].x,
+                y: y,
+                width: width,
+                height: height,
+            };
+            self.place(index, &rect);
+            rect
+        })
+    }
+
+    // the row a rectangle starting at the given segment would sit on, if it fits at all
+    fn fits(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        let mut i = index;
+        while remaining > 0 {
+            let segment = self.skyline[i];
+            y = y.max(segment.y);
+            if y + height > self.height {
+                return None;
+            }
+            remaining -= segment.width as i64;
+            i += 1;
+        }
+        Some(y)
+    }
+
+    fn place(&mut self, index: usize, rect: &PixelRect) {
+        self.skyline.insert(index,
+                            Segment {
+                                x: rect.x,
+                                y: rect.y + rect.height,
+                                width: rect.width,
+                            });
+
+        // shrink or drop the segments the new one now covers
+        let right = rect.x + rect.width;
+        let i = index + 1;
+        while i < self.skyline.len() {
+            let segment = self.skyline[i];
+            if segment.x >= right {
+                break;
+            }
+            let overlap = right - segment.x;
+            if overlap >= segment.width {
+                self.skyline.remove(i);
+            } else {
+                self.skyline[i].x += overlap;
+                self.skyline[i].width -= overlap;
+                break;
+            }
+        }
+
+        // neighbours at the same height are one segment
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].y == self.skyline[i + 1].y {
+                self.skyline[i].width += self.skyline[i + 1].width;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+// where each named image ended up in the atlas. this is all a game needs at runtime besides
+// the atlas image itself, so it can be written out when the atlas is built offline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AtlasTable {
+    pub width: u32,
+    pub height: u32,
+    pub regions: BTreeMap<String, PixelRect>,
+}
+
+impl AtlasTable {
+    pub fn region(&self, name: &str) -> Option<PixelRect> {
+        self.regions.get(name).cloned()
+    }
+
+    // the region in normalized texture coordinates, ready for a Sprite's uv
+    pub fn uv(&self, name: &str) -> Option<Rect> {
+        self.region(name).map(|region| {
+            Rect::new(region.x as f32 / self.width as f32,
+                      region.y as f32 / self.height as f32,
+                      region.width as f32 / self.width as f32,
+                      region.height as f32 / self.height as f32)
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, AtlasError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, AtlasError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AtlasError> {
+        let file = File::create(path)?;
+        Ok(serde_json::to_writer_pretty(file, self)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AtlasError> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+}
+
+pub struct Atlas {
+    pub image: RgbaImage,
+    pub table: AtlasTable,
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+    // the named image could not be placed even in an atlas of the maximum size
+    TooLarge(String),
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AtlasError::TooLarge(ref name) => {
+                write!(f, "{} does not fit in the largest allowed atlas", name)
+            }
+            AtlasError::Io(ref err) => write!(f, "{}", err),
+            AtlasError::Json(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for AtlasError {}
+
+impl From<io::Error> for AtlasError {
+    fn from(err: io::Error) -> Self {
+        AtlasError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for AtlasError {
+    fn from(err: serde_json::Error) -> Self {
+        AtlasError::Json(err)
+    }
+}
+
+// gathers named images and packs them into the smallest power of two atlas they fit in
+pub struct AtlasBuilder {
+    images: Vec<(String, RgbaImage)>,
+    padding: u32,
+    max_size: u32,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        AtlasBuilder::new()
+    }
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        AtlasBuilder {
+            images: Vec::new(),
+            padding: DEFAULT_PADDING,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    // empty pixels kept between images so linear filtering doesn't bleed neighbours together
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    // the atlas is never wider or taller than this, even when it isn't a power of two
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size.max(1);
+        self
+    }
+
+    // adding a name that's already there replaces its image, and hands back the one replaced
+    pub fn add<S: Into<String>>(&mut self, name: S, image: RgbaImage) -> Option<RgbaImage> {
+        let name = name.into();
+        match self.images.iter_mut().find(|(added, _)| *added == name) {
+            Some(&mut (_, ref mut added)) => Some(::std::mem::replace(added, image)),
+            None => {
+                self.images.push((name, image));
+                None
+            }
+        }
+    }
+
+    pub fn build(mut self) -> Result<Atlas, AtlasError> {
+        // tall images first leaves a flatter skyline for the rest
+        self.images.sort_by(|a, b| {
+            (b.1.height(), b.1.width()).cmp(&(a.1.height(), a.1.width()))
+        });
+
+        let padding = self.padding;
+        let area: u32 = self.images
+            .iter()
+            .map(|(_, image)| (image.width() + padding) * (image.height() + padding))
+            .sum();
+
+        // the smallest power of two size with room for them all, as long as that's allowed
+        let mut size = [1, 1];
+        while size[0] * size[1] < area {
+            match grow(size, self.max_size) {
+                Some(grown) => size = grown,
+                None => break,
+            }
+        }
+
+        loop {
+            match self.pack(size[0], size[1]) {
+                Ok(regions) => return Ok(self.compose(size[0], size[1], regions)),
+                Err(name) => {
+                    match grow(size, self.max_size) {
+                        Some(grown) => size = grown,
+                        None => return Err(AtlasError::TooLarge(name)),
+                    }
+                }
+            }
+        }
+    }
+
+    // packs every image at the given size, or names the first one that didn't fit
+    fn pack(&self, width: u32, height: u32) -> Result<Vec<PixelRect>, String> {
+        let mut packer = SkylinePacker::new(width, height);
+        let mut regions = Vec::with_capacity(self.images.len());
+
+        for (name, image) in &self.images {
+            match packer.pack(image.width() + self.padding, image.height() + self.padding) {
+                Some(rect) => {
+                    regions.push(PixelRect {
+                        x: rect.x,
+                        y: rect.y,
+                        width: image.width(),
+                        height: image.height(),
+                    })
+                }
+                None => return Err(name.clone()),
+            }
+        }
+
+        Ok(regions)
+    }
+
+    fn compose(self, width: u32, height: u32, regions: Vec<PixelRect>) -> Atlas {
+        let mut atlas = RgbaImage::new(width, height);
+        let mut table = AtlasTable {
+            width: width,
+            height: height,
+            regions: BTreeMap::new(),
+        };
+
+        for ((name, image), region) in self.images.into_iter().zip(regions) {
+            // the packer only hands out regions inside the atlas
+            atlas.copy_from(&image, region.x, region.y).unwrap();
+            table.regions.insert(name, region);
+        }
+
+        Atlas {
+            image: atlas,
+            table: table,
+        }
+    }
+}
+
+// doubles the shorter side of an atlas size, or the other once the shorter is as large as
+// allowed. None when neither can grow.
+fn grow(size: [u32; 2], max_size: u32) -> Option<[u32; 2]> {
+    let (width, height) = (size[0], size[1]);
+    if width < max_size && (width <= height || height >= max_size) {
+        Some([(width * 2).min(max_size), height])
+    } else if height < max_size {
+        Some([width, (height * 2).min(max_size)])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use std::{env, fs, process};
+
+    fn builder(max_size: u32, sizes: &[[u32; 2]]) -> AtlasBuilder {
+        let mut builder = AtlasBuilder::new().with_max_size(max_size);
+        for (index, size) in sizes.iter().enumerate() {
+            builder.add(format!("image{}", index), RgbaImage::new(size[0], size[1]));
+        }
+        builder
+    }
+
+    fn overlaps(a: &PixelRect, b: &PixelRect) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height &&
+        b.y < a.y + a.height
+    }
+
+    // every region inside the atlas and apart from the rest
+    fn check_regions(atlas: &Atlas) {
+        let table = &atlas.table;
+        assert_eq!(atlas.image.dimensions(), (table.width, table.height));
+        let regions: Vec<&PixelRect> = table.regions.values().collect();
+        for (index, region) in regions.iter().enumerate() {
+            assert!(region.x + region.width <= table.width);
+            assert!(region.y + region.height <= table.height);
+            for other in &regions[index + 1..] {
+                assert!(!overlaps(region, other), "{:?} overlaps {:?}", region, other);
+            }
+        }
+    }
+
+    #[test]
+    fn builds_the_smallest_power_of_two_that_fits() {
+        let atlas = builder(4096, &[[15, 15]; 4]).build().unwrap();
+        assert_eq!((atlas.table.width, atlas.table.height), (32, 32));
+        assert_eq!(atlas.table.regions.len(), 4);
+        check_regions(&atlas);
+    }
+
+    #[test]
+    fn never_grows_past_the_max_size() {
+        // their area alone asks for 64 by 64
+        let atlas = builder(48, &[[20, 20]; 4]).build().unwrap();
+        assert!(atlas.table.width <= 48 && atlas.table.height <= 48);
+        check_regions(&atlas);
+    }
+
+    #[test]
+    fn images_that_dont_fit_are_an_error() {
+        // five fit in 64 by 32, but not in the 32 by 32 allowed
+        match builder(32, &[[15, 15]; 5]).build() {
+            Err(AtlasError::TooLarge(_)) => {}
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(atlas) => panic!("built a {}x{} atlas", atlas.table.width, atlas.table.height),
+        }
+        match builder(64, &[[10, 10], [100, 10]]).build() {
+            Err(AtlasError::TooLarge(name)) => assert_eq!(name, "image1"),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("an image wider than the max size was packed"),
+        }
+    }
+
+    #[test]
+    fn an_empty_builder_makes_an_empty_atlas() {
+        let atlas = AtlasBuilder::new().build().unwrap();
+        assert!(atlas.table.regions.is_empty());
+        assert_eq!((atlas.table.width, atlas.table.height), (1, 1));
+    }
+
+    // a square of one color, so where it lands in the atlas can be checked
+    fn filled(size: u32, color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(size, size, Rgba(color))
+    }
+
+    #[test]
+    fn images_are_copied_into_their_regions() {
+        let mut builder = AtlasBuilder::new();
+        builder.add("red", filled(8, [255, 0, 0, 255]));
+        builder.add("green", filled(4, [0, 255, 0, 255]));
+        let atlas = builder.build().unwrap();
+        check_regions(&atlas);
+
+        for &(name, color) in &[("red", [255, 0, 0, 255]), ("green", [0, 255, 0, 255])] {
+            let region = atlas.table.region(name).unwrap();
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    assert_eq!(atlas.image.get_pixel(x, y).0, color, "{} at {},{}", name, x, y);
+                }
+            }
+            // the padding after each image is left empty
+            assert_eq!(atlas.image.get_pixel(region.x + region.width, region.y).0, [0; 4]);
+
+            let uv = atlas.table.uv(name).unwrap();
+            let (width, height) = (atlas.table.width as f32, atlas.table.height as f32);
+            assert_eq!(uv,
+                       Rect::new(region.x as f32 / width,
+                                 region.y as f32 / height,
+                                 region.width as f32 / width,
+                                 region.height as f32 / height));
+        }
+        assert!(atlas.table.uv("blue").is_none());
+    }
+
+    #[test]
+    fn uv_is_normalized() {
+        let mut regions = BTreeMap::new();
+        regions.insert("kitten".to_string(),
+                       PixelRect {
+                           x: 16,
+                           y: 8,
+                           width: 32,
+                           height: 16,
+                       });
+        let table = AtlasTable {
+            width: 64,
+            height: 32,
+            regions: regions,
+        };
+        assert_eq!(table.uv("kitten"), Some(Rect::new(0.25, 0.25, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn adding_a_name_again_replaces_it() {
+        let mut builder = AtlasBuilder::new();
+        assert!(builder.add("kitten", filled(4, [255, 0, 0, 255])).is_none());
+        let replaced = builder.add("kitten", filled(8, [0, 0, 255, 255])).unwrap();
+        assert_eq!(replaced.dimensions(), (4, 4));
+
+        let atlas = builder.build().unwrap();
+        assert_eq!(atlas.table.regions.len(), 1);
+        let region = atlas.table.region("kitten").unwrap();
+        assert_eq!((region.width, region.height), (8, 8));
+        assert_eq!(atlas.image.get_pixel(region.x, region.y).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn tables_round_trip_through_json() {
+        let atlas = builder(4096, &[[15, 15], [7, 30], [3, 3]]).build().unwrap();
+        let json = atlas.table.to_json().unwrap();
+        assert_eq!(AtlasTable::from_json(&json).unwrap(), atlas.table);
+
+        let path = env::temp_dir().join(format!("learn_gfx_2d_atlas_{}.json", process::id()));
+        atlas.table.save(&path).unwrap();
+        let loaded = AtlasTable::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), atlas.table);
+
+        match AtlasTable::from_json("{\"width\": 4}") {
+            Err(AtlasError::Json(_)) => {}
+            other => panic!("expected a json error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use cgmath;
+use gfx;
+use gfx::handle::{Buffer, RenderTargetView, ShaderResourceView};
+use gfx::traits::FactoryExt;
+use rect::Rect;
+use render_target::RenderTarget2D;
+use sprite::{full_scissor, pixel_projection, viewport_scissor};
+use std::cell::Cell;
+use std::rc::Rc;
+use texture::blank_texture;
+use viewport::Viewport;
+use ColorFormat;
+
+gfx_defines!{
+    constant BatchView {
+        projection: [[f32; 4]; 4] = "projection",
+    }
+
+    // batched vertices are already in world space, so they carry everything the sprite
+    // renderer would have put in the model matrix
+    vertex BatchVertex {
+        position: [f32; 2] = "position",
+        tex_coord: [f32; 2] = "texture_coord",
+        color: [f32; 4] = "color",
+    }
+
+    pipeline batch_pipe {
+        vbuf: gfx::VertexBuffer<BatchVertex> = (),
+        view: gfx::ConstantBuffer<BatchView> = "View",
+        texture_sampler: gfx::TextureSampler<[f32; 4]> = "texture_sampler",
+        out: gfx::BlendTarget<ColorFormat> = ("color", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+        scissor: gfx::Scissor = (),
+    }
+}
+
+const VERTICES_PER_SPRITE: usize = 4;
+const INDICES_PER_SPRITE: usize = 6;
+
+// how many sprites the renderer's buffers hold before they have to grow
+const DEFAULT_CAPACITY: usize = 1024;
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// identifies a texture registered with a SpriteBatchRenderer, sprites are sorted by it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextureId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    // the center of the sprite in world space
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    // counter clockwise, in radians, around the sprite's center
+    pub rotation: f32,
+    // the region of the texture to draw, normalized with the origin at the image's top left
+    pub uv: Rect,
+    pub tint: [f32; 4],
+    // sprites on lower layers are drawn first, textures are only sorted within a layer
+    pub layer: i32,
+}
+
+impl Sprite {
+    pub fn new(position: [f32; 2], size: [f32; 2]) -> Self {
+        Sprite {
+            position: position,
+            size: size,
+            rotation: 0.0,
+            uv: Rect::unit(),
+            tint: WHITE,
+            layer: 0,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_uv(mut self, uv: Rect) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+// a run of indices that can be drawn with a single texture bound
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawCall {
+    pub texture: TextureId,
+    pub start: u32,
+    pub count: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FlushStats {
+    pub sprites: usize,
+    pub draw_calls: usize,
+    pub vertices: usize,
+    pub indices: usize,
+}
+
+// collects sprites over a frame and turns them into one vertex and index array. nothing
+// here touches the gpu, so the output of build can be inspected directly.
+pub struct SpriteBatch {
+    // how many textures the renderer the batch came from has, shared with it so textures
+    // added later count too. batches made on their own take any id.
+    textures: Option<Rc<Cell<usize>>>,
+    queued: Vec<(TextureId, Sprite)>,
+    vertices: Vec<BatchVertex>,
+    indices: Vec<u32>,
+    draw_calls: Vec<DrawCall>,
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        SpriteBatch::new()
+    }
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        SpriteBatch::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(sprites: usize) -> Self {
+        SpriteBatch {
+            textures: None,
+            queued: Vec::with_capacity(sprites),
+            vertices: Vec::with_capacity(sprites * VERTICES_PER_SPRITE),
+            indices: Vec::with_capacity(sprites * INDICES_PER_SPRITE),
+            draw_calls: Vec::new(),
+        }
+    }
+
+    fn checked(textures: Rc<Cell<usize>>) -> Self {
+        let mut batch = SpriteBatch::new();
+        batch.textures = Some(textures);
+        batch
+    }
+
+    // panics when the batch came from a SpriteBatchRenderer that never returned this id from
+    // add_texture, so the mistake shows up where the sprite is pushed rather than at flush
+    pub fn push(&mut self, texture: TextureId, sprite: Sprite) {
+        if let Some(ref textures) = self.textures {
+            if texture.0 >= textures.get() {
+                panic!("{:?} was never added to the SpriteBatchRenderer this batch is for",
+                       texture);
+            }
+        }
+        self.queued.push((texture, sprite));
+    }
+
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    // sorts the queued sprites and writes their quads, emptying the queue. the stable sort
+    // keeps submission order for sprites that share a layer and texture.
+    pub fn build(&mut self) -> FlushStats {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draw_calls.clear();
+
+        self.queued.sort_by_key(|entry| (entry.1.layer, entry.0));
+
+        for &(texture, ref sprite) in &self.queued {
+            let start = self.indices.len() as u32;
+            let base = self.vertices.len() as u32;
+
+            push_quad(&mut self.vertices, sprite);
+            self.indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+
+            let extends_last = match self.draw_calls.last() {
+                Some(call) => call.texture == texture,
+                None => false,
+            };
+
+            if extends_last {
+                self.draw_calls.last_mut().unwrap().count += INDICES_PER_SPRITE as u32;
+            } else {
+                self.draw_calls.push(DrawCall {
+                    texture: texture,
+                    start: start,
+                    count: INDICES_PER_SPRITE as u32,
+                });
+            }
+        }
+
+        let stats = FlushStats {
+            sprites: self.queued.len(),
+            draw_calls: self.draw_calls.len(),
+            vertices: self.vertices.len(),
+            indices: self.indices.len(),
+        };
+        self.queued.clear();
+        stats
+    }
+
+    pub fn vertices(&self) -> &[BatchVertex] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn draw_calls(&self) -> &[DrawCall] {
+        &self.draw_calls
+    }
+}
+
+// writes the bottom left, bottom right, top right and top left corners of a sprite
+fn push_quad(vertices: &mut Vec<BatchVertex>, sprite: &Sprite) {
+    let half_width = sprite.size[0] / 2.0;
+    let half_height = sprite.size[1] / 2.0;
+    let (sin, cos) = sprite.rotation.sin_cos();
+    let uv = sprite.uv;
+
+    let corners = [([-half_width, -half_height], [uv.x, uv.bottom()]),
+                   ([half_width, -half_height], [uv.right(), uv.bottom()]),
+                   ([half_width, half_height], [uv.right(), uv.y]),
+                   ([-half_width, half_height], [uv.x, uv.y])];
+
+    for &(corner, tex_coord) in &corners {
+        vertices.push(BatchVertex {
+            position: [corner[0] * cos - corner[1] * sin + sprite.position[0],
+                       corner[0] * sin + corner[1] * cos + sprite.position[1]],
+            tex_coord: tex_coord,
+            color: sprite.tint,
+        });
+    }
+}
+
+// draws a whole SpriteBatch from one dynamic vertex and index buffer, binding each
+// registered texture once per draw call
+pub struct SpriteBatchRenderer<R: gfx::Resources> {
+    pso: gfx::PipelineState<R, batch_pipe::Meta>,
+    data: batch_pipe::Data<R>,
+    index_buffer: Buffer<R, u32>,
+    capacity: usize,
+    textures: Vec<ShaderResourceView<R, [f32; 4]>>,
+    // the number of textures, shared with the batches this renderer has made
+    texture_count: Rc<Cell<usize>>,
+    view: BatchView,
+}
+
+impl<R: gfx::Resources> SpriteBatchRenderer<R> {
+    pub fn new<F>(factory: &mut F,
+                  out: RenderTargetView<R, ColorFormat>,
+                  width: f32,
+                  height: f32)
+                  -> Self
+        where F: gfx::Factory<R>
+    {
+        let pso = factory.create_pipeline_simple(include_bytes!("shaders/batch_vert.glsl"),
+                                    include_bytes!("shaders/batch_frag.glsl"),
+                                    batch_pipe::new())
+            .unwrap();
+
+        let (vertex_buffer, index_buffer) = create_buffers(factory, DEFAULT_CAPACITY);
+        let placeholder = blank_texture(factory);
+        let sampler = factory.create_sampler_linear();
+
+        let data = batch_pipe::Data {
+            vbuf: vertex_buffer,
+            view: factory.create_constant_buffer(1),
+            texture_sampler: (placeholder, sampler),
+            out: out,
+            scissor: full_scissor(width, height),
+        };
+
+        SpriteBatchRenderer {
+            pso: pso,
+            data: data,
+            index_buffer: index_buffer,
+            capacity: DEFAULT_CAPACITY,
+            textures: Vec::new(),
+            texture_count: Rc::new(Cell::new(0)),
+            view: BatchView { projection: pixel_projection(width, height).into() },
+        }
+    }
+
+    pub fn add_texture(&mut self, texture: ShaderResourceView<R, [f32; 4]>) -> TextureId {
+        self.textures.push(texture);
+        self.texture_count.set(self.textures.len());
+        TextureId(self.textures.len() - 1)
+    }
+
+    // a batch that checks each sprite's texture was added to this renderer as it's pushed
+    pub fn batch(&self) -> SpriteBatch {
+        SpriteBatch::checked(self.texture_count.clone())
+    }
+
+    // swaps the texture behind an id, such as a render target's after it has been resized.
+    // panics for an id that add_texture didn't return.
+    pub fn set_texture(&mut self, id: TextureId, texture: ShaderResourceView<R, [f32; 4]>) {
+        let registered = self.registered(id);
+        self.textures[registered] = texture;
+    }
+
+    fn registered(&self, id: TextureId) -> usize {
+        if id.0 >= self.textures.len() {
+            panic!("{:?} was never added to this SpriteBatchRenderer", id);
+        }
+        id.0
+    }
+
+    pub fn set_projection(&mut self, projection: cgmath::Matrix4<f32>) {
+        self.view.projection = projection.into();
+    }
+
+    // limits drawing to the viewport's part of the window and shows its world area
+    pub fn set_viewport(&mut self, viewport: &Viewport) {
+        self.set_projection(viewport.projection());
+        self.data.scissor = viewport_scissor(viewport);
+    }
+
+    pub fn set_target(&mut self, out: RenderTargetView<R, ColorFormat>) {
+        self.data.out = out;
+    }
+
+    // draws into a render target instead of the window, with a pixel projection covering it.
+    // switching back to the window means setting its target, projection or viewport again.
+    pub fn set_render_target(&mut self, target: &RenderTarget2D<R>) {
+        self.data.out = target.target().clone();
+        self.data.scissor = target.scissor();
+        self.set_projection(target.projection());
+    }
+
+    pub fn clear<C>(&self, encoder: &mut gfx::Encoder<R, C>, color: [f32; 4])
+        where C: gfx::CommandBuffer<R>
+    {
+        encoder.clear(&self.data.out, color);
+    }
+
+    // builds the batch, uploads it and issues one draw per texture run. the factory is only
+    // used when the batch no longer fits in the current buffers. every sprite's texture has to
+    // have come from this renderer's add_texture. batches from batch have checked that as
+    // sprites were pushed, any other batch holding another id panics here.
+    pub fn flush<F, C>(&mut self,
+                       factory: &mut F,
+                       encoder: &mut gfx::Encoder<R, C>,
+                       batch: &mut SpriteBatch)
+                       -> FlushStats
+        where F: gfx::Factory<R>,
+              C: gfx::CommandBuffer<R>
+    {
+        let stats = batch.build();
+        if stats.sprites == 0 {
+            return stats;
+        }
+
+        if stats.sprites > self.capacity {
+            let capacity = stats.sprites.next_power_of_two();
+            let (vertex_buffer, index_buffer) = create_buffers(factory, capacity);
+            self.data.vbuf = vertex_buffer;
+            self.index_buffer = index_buffer;
+            self.capacity = capacity;
+        }
+
+        encoder.update_buffer(&self.data.vbuf, batch.vertices(), 0).unwrap();
+        encoder.update_buffer(&self.index_buffer, batch.indices(), 0).unwrap();
+        encoder.update_constant_buffer(&self.data.view, &self.view);
+
+        for call in batch.draw_calls() {
+            let slice = gfx::Slice {
+                start: call.start,
+                end: call.start + call.count,
+                base_vertex: 0,
+                instances: None,
+                buffer: gfx::IndexBuffer::Index32(self.index_buffer.clone()),
+            };
+            self.data.texture_sampler.0 = self.textures[self.registered(call.texture)].clone();
+            encoder.draw(&slice, &self.pso, &self.data);
+        }
+
+        stats
+    }
+}
+
+fn create_buffers<R, F>(factory: &mut F, sprites: usize) -> (Buffer<R, BatchVertex>, Buffer<R, u32>)
+    where R: gfx::Resources,
+          F: gfx::Factory<R>
+{
+    let vertex_buffer = factory.create_buffer(sprites * VERTICES_PER_SPRITE,
+                       gfx::buffer::Role::Vertex,
+                       gfx::memory::Usage::Dynamic,
+                       gfx::memory::Bind::empty())
+        .unwrap();
+    let index_buffer = factory.create_buffer(sprites * INDICES_PER_SPRITE,
+                       gfx::buffer::Role::Index,
+                       gfx::memory::Usage::Dynamic,
+                       gfx::memory::Bind::empty())
+        .unwrap();
+    (vertex_buffer, index_buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sprites two wide, told apart by where they are
+    fn sprite(x: f32, layer: i32) -> Sprite {
+        Sprite::new([x, 0.0], [2.0, 2.0]).with_layer(layer)
+    }
+
+    // the x of each quad in the order it was built
+    fn built_order(batch: &SpriteBatch) -> Vec<f32> {
+        batch.vertices()
+            .chunks(VERTICES_PER_SPRITE)
+            .map(|quad| quad[0].position[0] + 1.0)
+            .collect()
+    }
+
+    fn calls(batch: &SpriteBatch) -> Vec<(usize, u32, u32)> {
+        batch.draw_calls().iter().map(|call| (call.texture.0, call.start, call.count)).collect()
+    }
+
+    #[test]
+    fn sprites_are_sorted_by_layer_then_texture() {
+        let mut batch = SpriteBatch::new();
+        batch.push(TextureId(1), sprite(0.0, 1));
+        batch.push(TextureId(1), sprite(10.0, 0));
+        batch.push(TextureId(0), sprite(20.0, 0));
+        batch.push(TextureId(0), sprite(30.0, -1));
+        batch.push(TextureId(0), sprite(40.0, 0));
+        batch.build();
+        // sprites sharing a layer and texture keep the order they were pushed in
+        assert_eq!(built_order(&batch), vec![30.0, 20.0, 40.0, 10.0, 0.0]);
+    }
+
+    #[test]
+    fn runs_of_one_texture_share_a_draw_call() {
+        let mut batch = SpriteBatch::new();
+        batch.push(TextureId(1), sprite(0.0, 1));
+        batch.push(TextureId(1), sprite(10.0, 0));
+        batch.push(TextureId(0), sprite(20.0, 0));
+        batch.push(TextureId(0), sprite(30.0, 0));
+        let stats = batch.build();
+        // the texture 1 sprites on layers 0 and 1 end up next to each other, so they merge
+        assert_eq!(calls(&batch), vec![(0, 0, 12), (1, 12, 12)]);
+        assert_eq!(stats,
+                   FlushStats {
+                       sprites: 4,
+                       draw_calls: 2,
+                       vertices: 16,
+                       indices: 24,
+                   });
+    }
+
+    #[test]
+    fn layers_can_split_a_texture_into_several_calls() {
+        let mut batch = SpriteBatch::new();
+        batch.push(TextureId(0), sprite(0.0, 0));
+        batch.push(TextureId(1), sprite(10.0, 1));
+        batch.push(TextureId(0), sprite(20.0, 2));
+        batch.push(TextureId(0), sprite(30.0, 2));
+        assert_eq!(batch.build().draw_calls, 3);
+        assert_eq!(calls(&batch), vec![(0, 0, 6), (1, 6, 6), (0, 12, 12)]);
+    }
+
+    #[test]
+    fn each_quad_indexes_its_own_corners() {
+        let mut batch = SpriteBatch::new();
+        batch.push(TextureId(0), sprite(0.0, 0));
+        batch.push(TextureId(0), sprite(10.0, 0));
+        batch.build();
+        assert_eq!(batch.indices(), &[0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+    }
+
+    #[test]
+    fn building_empties_the_queue() {
+        let mut batch = SpriteBatch::new();
+        batch.push(TextureId(0), sprite(0.0, 0));
+        assert_eq!(batch.len(), 1);
+        batch.build();
+        assert!(batch.is_empty());
+        assert_eq!(batch.build(), FlushStats::default());
+        assert!(batch.draw_calls().is_empty());
+    }
+
+    #[test]
+    fn quads_are_rotated_around_their_center() {
+        let uv = Rect::new(0.25, 0.5, 0.25, 0.5);
+        let tint = [1.0, 0.5, 0.25, 0.75];
+        let mut batch = SpriteBatch::new();
+        batch.push(TextureId(0),
+                   Sprite::new([10.0, 20.0], [4.0, 2.0])
+                       .with_rotation(::std::f32::consts::FRAC_PI_2)
+                       .with_uv(uv)
+                       .with_tint(tint));
+        batch.build();
+
+        // a quarter turn counter clockwise takes the bottom left corner, 2 left of and 1 below
+        // the center, to 1 right of and 2 below it
+        let expected = [([11.0, 18.0], [0.25, 1.0]),
+                        ([11.0, 22.0], [0.5, 1.0]),
+                        ([9.0, 22.0], [0.5, 0.5]),
+                        ([9.0, 18.0], [0.25, 0.5])];
+        let vertices = batch.vertices();
+        assert_eq!(vertices.len(), 4);
+        for (vertex, &(position, tex_coord)) in vertices.iter().zip(expected.iter()) {
+            assert!((vertex.position[0] - position[0]).abs() < 1e-5 &&
+                    (vertex.position[1] - position[1]).abs() < 1e-5,
+                    "{:?} isn't at {:?}",
+                    vertex.position,
+                    position);
+            assert_eq!(vertex.tex_coord, tex_coord);
+            assert_eq!(vertex.color, tint);
+        }
+        assert_eq!(batch.indices(), &[0, 1, 2, 2, 3, 0]);
+    }
+
+    #[test]
+    fn renderer_batches_take_the_textures_added_so_far() {
+        let textures = Rc::new(Cell::new(1));
+        let mut batch = SpriteBatch::checked(textures.clone());
+        batch.push(TextureId(0), sprite(0.0, 0));
+        // added to the renderer after the batch was made
+        textures.set(2);
+        batch.push(TextureId(1), sprite(10.0, 0));
+        assert_eq!(batch.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "TextureId(2) was never added")]
+    fn renderer_batches_refuse_unknown_textures_when_pushed() {
+        let mut batch = SpriteBatch::checked(Rc::new(Cell::new(2)));
+        batch.push(TextureId(2), sprite(0.0, 0));
+    }
+}
@@ -0,0 +1,389 @@
+use glutin::{MouseButton, VirtualKeyCode};
+use input::{Binding, InputMap};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use toml;
+
+// every key, named as in VirtualKeyCode so the names in a bindings file match the code
+macro_rules! key_names {
+    ($($key:ident),*) => {
+        const KEY_NAMES: &'static [(&'static str, VirtualKeyCode)] = &[
+     $((stringify!($key), VirtualKeyCode::$key)),*
+        ];
+    }
+}
+
+key_names!(
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0, A, B, C, D, E, F, G, H, I,
+    J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Escape, F1, F2, F3, F4, F5, F6, F7,
+    F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+    Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp, Left, Up, Right,
+    Down, Back, Return, Space, Compose, Caret, Numlock, Numpad0, Numpad1, Numpad2, Numpad3,
+    Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9, AbntC1, AbntC2, Add, Apostrophe,
+    Apps, At, Ax, Backslash, Calculator, Capital, Colon, Comma, Convert, Decimal, Divide,
+    Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl, LShift, LWin, Mail, MediaSelect,
+    MediaStop, Minus, Multiply, Mute, MyComputer, NavigateForward, NavigateBackward,
+    NextTrack, NoConvert, NumpadComma, NumpadEnter, NumpadEquals, OEM102, Period, PlayPause,
+    Power, PrevTrack, RAlt, RBracket, RControl, RShift, RWin, Semicolon, Slash, Sleep, Stop,
+    Subtract, Sysrq, Tab, Underline, Unlabeled, VolumeDown, VolumeUp, Wake, WebBack,
+    WebFavorites, WebForward, WebHome, WebRefresh, WebSearch, WebStop, Yen, Copy, Paste, Cut);
+
+const MOUSE_PREFIX: &'static str = "Mouse";
+
+// None for any key glutin has added since the list above was written
+pub fn key_name(key: VirtualKeyCode) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|&&(_, named)| named == key).map(|&(name, _)| name)
+}
+
+// names are matched ignoring case, so "space" and "SPACE" both find Space
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES.iter()
+        .find(|&&(named, _)| named.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+pub fn binding_name(binding: Binding) -> String {
+    match binding {
+        Binding::Key(key) => {
+            key_name(key).map(str::to_string).unwrap_or_else(|| format!("{:?}", key))
+        }
+        Binding::Mouse(MouseButton::Left) => format!("{}Left", MOUSE_PREFIX),
+        Binding::Mouse(MouseButton::Right) => format!("{}Right", MOUSE_PREFIX),
+        Binding::Mouse(MouseButton::Middle) => format!("{}Middle", MOUSE_PREFIX),
+        Binding::Mouse(MouseButton::Other(button)) => format!("{}{}", MOUSE_PREFIX, button),
+    }
+}
+
+pub fn binding_from_name(name: &str) -> Option<Binding> {
+    if let Some(key) = key_from_name(name) {
+        return Some(Binding::Key(key));
+    }
+
+    // get rather than slicing, since the prefix's length may not fall on a char boundary
+    let mouse = name.get(..MOUSE_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(MOUSE_PREFIX));
+    if !mouse || name.len() == MOUSE_PREFIX.len() {
+        return None;
+    }
+    let button = &name[MOUSE_PREFIX.len()..];
+    if button.eq_ignore_ascii_case("left") {
+        Some(Binding::Mouse(MouseButton::Left))
+    } else if button.eq_ignore_ascii_case("right") {
+        Some(Binding::Mouse(MouseButton::Right))
+    } else if button.eq_ignore_ascii_case("middle") {
+        Some(Binding::Mouse(MouseButton::Middle))
+    } else {
+        button.parse().ok().map(|button| Binding::Mouse(MouseButton::Other(button)))
+    }
+}
+
+// the layout of a bindings file:
+//
+// [actions]
+// jump = ["Space", "MouseLeft"]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BindingsFile {
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<String>>,
+}
+
+// one binding claimed by more than one action
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub binding: Binding,
+    pub actions: Vec<String>,
+}
+
+// problems in a bindings file that were worked around while loading it
+#[derive(Clone, Debug, PartialEq)]
+pub enum BindingWarning {
+    // the name isn't a key or mouse button, so it was skipped
+    UnknownBinding { action: String, name: String },
+    // the game doesn't have this action, so its bindings were ignored
+    UnknownAction(String),
+    // none of the action's bindings could be used, so it kept its defaults
+    KeptDefaults(String),
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    // the file binds the same key to several actions. nothing was changed.
+    Conflicts(Vec<Conflict>),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindingsError::Io(ref err) => write!(f, "{}", err),
+            BindingsError::Parse(ref err) => write!(f, "{}", err),
+            BindingsError::Serialize(ref err) => write!(f, "{}", err),
+            BindingsError::Conflicts(ref conflicts) => {
+                write!(f, "conflicting bindings:")?;
+                for conflict in conflicts {
+                    write!(f,
+                           " {} is bound to {};",
+                           binding_name(conflict.binding),
+                           conflict.actions.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for BindingsError {}
+
+impl From<io::Error> for BindingsError {
+    fn from(err: io::Error) -> Self {
+        BindingsError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for BindingsError {
+    fn from(err: toml::de::Error) -> Self {
+        BindingsError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for BindingsError {
+    fn from(err: toml::ser::Error) -> Self {
+        BindingsError::Serialize(err)
+    }
+}
+
+// every binding shared by two or more actions, in a stable order
+pub fn find_conflicts(map: &InputMap) -> Vec<Conflict> {
+    let mut actions = map.actions();
+    actions.sort();
+
+    let mut bound: HashMap<Binding, Vec<String>> = HashMap::new();
+    for action in actions {
+        for &binding in map.bindings(action) {
+            bound.entry(binding).or_default().push(action.to_string());
+        }
+    }
+
+    let mut conflicts: Vec<Conflict> = bound.into_iter()
+        .filter(|(_, actions)| actions.len() > 1)
+        .map(|(binding, actions)| {
+            Conflict {
+                binding: binding,
+                actions: actions,
+            }
+        })
+        .collect();
+    conflicts.sort_by_key(|conflict| binding_name(conflict.binding));
+    conflicts
+}
+
+pub fn bindings_to_toml(map: &InputMap) -> Result<String, BindingsError> {
+    let mut file = BindingsFile::default();
+    for action in map.actions() {
+        let names = map.bindings(action).iter().map(|&binding| binding_name(binding)).collect();
+        file.actions.insert(action.to_string(), names);
+    }
+    Ok(toml::to_string(&file)?)
+}
+
+// rebinds the actions named in the file, leaving the others with their defaults. the map is
+// only changed when the result has no conflicts.
+pub fn apply_toml(map: &mut InputMap, contents: &str) -> Result<Vec<BindingWarning>, BindingsError> {
+    let file: BindingsFile = toml::from_str(contents)?;
+    let mut rebound = map.clone();
+    let mut warnings = Vec::new();
+
+    for (action, names) in file.actions {
+        if !map.has_action(&action) {
+            warnings.push(BindingWarning::UnknownAction(action));
+            continue;
+        }
+
+        let mut bindings = Vec::with_capacity(names.len());
+        let mut skipped = false;
+        for name in names {
+            match binding_from_name(&name) {
+                Some(binding) => {
+                    if !bindings.contains(&binding) {
+                        bindings.push(binding);
+                    }
+                }
+                None => {
+                    skipped = true;
+                    warnings.push(BindingWarning::UnknownBinding {
+                        action: action.clone(),
+                        name: name,
+                    })
+                }
+            }
+        }
+
+        // an action the file deliberately leaves unbound is respected, but one whose names
+        // all failed to parse would otherwise become unusable
+        if bindings.is_empty() && skipped {
+            warnings.push(BindingWarning::KeptDefaults(action));
+        } else {
+            rebound.set_bindings(action, bindings);
+        }
+    }
+
+    let conflicts = find_conflicts(&rebound);
+    if !conflicts.is_empty() {
+        return Err(BindingsError::Conflicts(conflicts));
+    }
+
+    *map = rebound;
+    Ok(warnings)
+}
+
+pub fn save_bindings<P: AsRef<Path>>(map: &InputMap, path: P) -> Result<(), BindingsError> {
+    let contents = bindings_to_toml(map)?;
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+pub fn load_bindings<P: AsRef<Path>>(map: &mut InputMap,
+                                     path: P)
+                                     -> Result<Vec<BindingWarning>, BindingsError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    apply_toml(map, &contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_names_round_trip() {
+        for &(name, key) in KEY_NAMES {
+            assert_eq!(key_name(key), Some(name));
+            assert_eq!(binding_from_name(&binding_name(Binding::Key(key))),
+                       Some(Binding::Key(key)));
+        }
+        for &button in &[MouseButton::Left,
+                         MouseButton::Right,
+                         MouseButton::Middle,
+                         MouseButton::Other(4)] {
+            let binding = Binding::Mouse(button);
+            assert_eq!(binding_from_name(&binding_name(binding)), Some(binding));
+        }
+        assert_eq!(binding_from_name("space"), Some(Binding::Key(VirtualKeyCode::Space)));
+        assert_eq!(binding_from_name("Mouse"), None);
+    }
+
+    #[test]
+    fn non_ascii_names_are_unknown() {
+        assert_eq!(binding_from_name("Mouséleft"), None);
+        assert_eq!(binding_from_name("Mous\u{e9}"), None);
+        assert_eq!(binding_from_name("\u{1f600}\u{1f600}"), None);
+    }
+
+    fn defaults() -> InputMap {
+        let mut map = InputMap::new();
+        map.bind("jump", Binding::Key(VirtualKeyCode::Space));
+        map.bind("fire", Binding::Mouse(MouseButton::Left));
+        map.bind("left", Binding::Key(VirtualKeyCode::A));
+        map
+    }
+
+    #[test]
+    fn apply_toml_rebinds_named_actions() {
+        let mut map = defaults();
+        let warnings = apply_toml(&mut map,
+                                  "[actions]\njump = [\"W\", \"MouseRight\", \"w\"]\nfire = []\n")
+            .unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(map.bindings("jump"),
+                   &[Binding::Key(VirtualKeyCode::W), Binding::Mouse(MouseButton::Right)]);
+        // left unbound on purpose
+        assert!(map.bindings("fire").is_empty());
+        // not in the file, so it keeps its default
+        assert_eq!(map.bindings("left"), &[Binding::Key(VirtualKeyCode::A)]);
+    }
+
+    #[test]
+    fn apply_toml_round_trips_saved_bindings() {
+        let mut map = defaults();
+        map.bind("jump", Binding::Mouse(MouseButton::Other(4)));
+        let saved = bindings_to_toml(&map).unwrap();
+
+        let mut loaded = defaults();
+        loaded.clear_bindings("jump");
+        assert!(apply_toml(&mut loaded, &saved).unwrap().is_empty());
+        for action in map.actions() {
+            assert_eq!(loaded.bindings(action), map.bindings(action));
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_skipped() {
+        let mut map = defaults();
+        let warnings = apply_toml(&mut map,
+                                  "[actions]\njump = [\"Nope\", \"W\"]\ndance = [\"D\"]\n")
+            .unwrap();
+
+        assert_eq!(warnings,
+                   vec![BindingWarning::UnknownAction("dance".to_string()),
+                        BindingWarning::UnknownBinding {
+                            action: "jump".to_string(),
+                            name: "Nope".to_string(),
+                        }]);
+        assert_eq!(map.bindings("jump"), &[Binding::Key(VirtualKeyCode::W)]);
+        assert!(!map.has_action("dance"));
+    }
+
+    #[test]
+    fn unusable_actions_keep_their_defaults() {
+        let mut map = defaults();
+        let warnings = apply_toml(&mut map, "[actions]\njump = [\"Nope\"]\n").unwrap();
+
+        assert_eq!(warnings,
+                   vec![BindingWarning::UnknownBinding {
+                            action: "jump".to_string(),
+                            name: "Nope".to_string(),
+                        },
+                        BindingWarning::KeptDefaults("jump".to_string())]);
+        assert_eq!(map.bindings("jump"), &[Binding::Key(VirtualKeyCode::Space)]);
+    }
+
+    #[test]
+    fn conflicts_are_reported_and_nothing_changes() {
+        let mut map = defaults();
+        let result = apply_toml(&mut map,
+                                "[actions]\njump = [\"A\", \"MouseLeft\"]\n");
+
+        match result {
+            Err(BindingsError::Conflicts(conflicts)) => {
+                assert_eq!(conflicts,
+                           vec![Conflict {
+                                    binding: Binding::Key(VirtualKeyCode::A),
+                                    actions: vec!["jump".to_string(), "left".to_string()],
+                                },
+                                Conflict {
+                                    binding: Binding::Mouse(MouseButton::Left),
+                                    actions: vec!["fire".to_string(), "jump".to_string()],
+                                }]);
+            }
+            other => panic!("expected conflicts, got {:?}", other),
+        }
+        assert_eq!(map.bindings("jump"), &[Binding::Key(VirtualKeyCode::Space)]);
+    }
+
+    #[test]
+    fn malformed_files_are_parse_errors() {
+        let mut map = defaults();
+        match apply_toml(&mut map, "[actions]\njump = \"Space\"\n") {
+            Err(BindingsError::Parse(_)) => {}
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use cgmath;
+use cgmath::{Matrix4, Rad, Vector3};
+use rect::Rect;
+use sprite::{FAR_PLANE, NEAR_PLANE};
+
+// a camera looking at a y up world. screen coordinates are the window's, in pixels with the
+// origin at the top left and y pointing down, which is how glutin reports the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera2D {
+    // the world position shown in the middle of the viewport
+    pub center: [f32; 2],
+    // how many screen pixels one world unit covers
+    pub zoom: f32,
+    // counter clockwise, in radians
+    pub rotation: f32,
+    // half the size of the area the follow target can move in without the camera moving
+    pub dead_zone: [f32; 2],
+    // how quickly follow catches up, as an exponential rate: each second the distance left is
+    // multiplied by e^-follow_speed, so 1 covers about 63% of it and 3 about 95%. zero snaps
+    // straight to the target.
+    pub follow_speed: f32,
+    // the part of the world the camera may show, with x and y at its bottom left
+    pub bounds: Option<Rect>,
+    viewport: [f32; 2],
+}
+
+impl Camera2D {
+    // starts out showing the same pixel space as sprite::pixel_projection
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Camera2D {
+            center: [viewport_width / 2.0, viewport_height / 2.0],
+            zoom: 1.0,
+            rotation: 0.0,
+            dead_zone: [0.0, 0.0],
+            follow_speed: 0.0,
+            bounds: None,
+            viewport: [viewport_width, viewport_height],
+        }
+    }
+
+    pub fn viewport(&self) -> [f32; 2] {
+        self.viewport
+    }
+
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport = [width, height];
+        self.clamp_to_bounds();
+    }
+
+    // the matrix to put in a View's projection: world to clip space in one step
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        let half_width = self.viewport[0] / 2.0 / self.zoom;
+        let half_height = self.viewport[1] / 2.0 / self.zoom;
+        let projection = cgmath::ortho(-half_width,
+                                       half_width,
+                                       -half_height,
+                                       half_height,
+                                       NEAR_PLANE,
+                                       FAR_PLANE);
+        let rotation = Matrix4::from_angle_z(Rad(-self.rotation));
+        let translation = Matrix4::from_translation(Vector3::new(-self.center[0],
+                                                                 -self.center[1],
+                                                                 0.0));
+        projection * rotation * translation
+    }
+
+    pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+        let x = (screen[0] - self.viewport[0] / 2.0) / self.zoom;
+        let y = (self.viewport[1] / 2.0 - screen[1]) / self.zoom;
+        let (sin, cos) = self.rotation.sin_cos();
+        [x * cos - y * sin + self.center[0], x * sin + y * cos + self.center[1]]
+    }
+
+    pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+        let x = world[0] - self.center[0];
+        let y = world[1] - self.center[1];
+        let (sin, cos) = self.rotation.sin_cos();
+        let local_x = x * cos + y * sin;
+        let local_y = -x * sin + y * cos;
+        [local_x * self.zoom + self.viewport[0] / 2.0,
+         self.viewport[1] / 2.0 - local_y * self.zoom]
+    }
+
+    // the world space box around everything the camera can currently see
+    pub fn visible_rect(&self) -> Rect {
+        let extents = self.half_extents();
+        Rect::new(self.center[0] - extents[0],
+                  self.center[1] - extents[1],
+                  extents[0] * 2.0,
+                  extents[1] * 2.0)
+    }
+
+    // moves towards a target, once it leaves the dead zone, over dt seconds
+    pub fn follow(&mut self, target: [f32; 2], dt: f32) {
+        let mut desired = self.center;
+        for axis in 0..2 {
+            let offset = target[axis] - self.center[axis];
+            if offset > self.dead_zone[axis] {
+                desired[axis] = target[axis] - self.dead_zone[axis];
+            } else if offset < -self.dead_zone[axis] {
+                desired[axis] = target[axis] + self.dead_zone[axis];
+            }
+        }
+
+        if self.follow_speed <= 0.0 {
+            self.center = desired;
+        } else {
+            let t = 1.0 - (-self.follow_speed * dt).exp();
+            self.center[0] += (desired[0] - self.center[0]) * t;
+            self.center[1] += (desired[1] - self.center[1]) * t;
+        }
+
+        self.clamp_to_bounds();
+    }
+
+    // keeps the visible area inside the bounds, centering on them when they are too small
+    pub fn clamp_to_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let extents = self.half_extents();
+        let minimum = [bounds.x, bounds.y];
+        let size = [bounds.width, bounds.height];
+
+        for axis in 0..2 {
+            if extents[axis] * 2.0 >= size[axis] {
+                self.center[axis] = minimum[axis] + size[axis] / 2.0;
+            } else {
+                let low = minimum[axis] + extents[axis];
+                let high = minimum[axis] + size[axis] - extents[axis];
+                self.center[axis] = self.center[axis].max(low).min(high);
+            }
+        }
+    }
+
+    // half the size of the visible area's bounding box, which grows when rotated
+    fn half_extents(&self) -> [f32; 2] {
+        let half_width = self.viewport[0] / 2.0 / self.zoom;
+        let half_height = self.viewport[1] / 2.0 / self.zoom;
+        let (sin, cos) = self.rotation.sin_cos();
+        [half_width * cos.abs() + half_height * sin.abs(),
+         half_width * sin.abs() + half_height * cos.abs()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let mut camera = Camera2D::new(640.0, 480.0);
+        // unmoved, the world is the window's pixels flipped upside down
+        assert!(close(camera.screen_to_world([0.0, 0.0]), [0.0, 480.0]));
+        assert!(close(camera.world_to_screen([320.0, 240.0]), [320.0, 240.0]));
+
+        camera.center = [100.0, -50.0];
+        camera.zoom = 2.0;
+        camera.rotation = 0.7;
+        for &screen in &[[0.0, 0.0], [640.0, 480.0], [13.0, 377.0], [320.0, 240.0]] {
+            assert!(close(camera.world_to_screen(camera.screen_to_world(screen)), screen));
+        }
+        assert!(close(camera.screen_to_world([320.0, 240.0]), [100.0, -50.0]));
+
+        // turning the camera a quarter counter clockwise turns the world the other way, so +x
+        // points down the screen
+        camera.rotation = FRAC_PI_2;
+        camera.zoom = 1.0;
+        assert!(close(camera.world_to_screen([110.0, -50.0]), [320.0, 250.0]));
+    }
+
+    #[test]
+    fn following_waits_for_the_dead_zone() {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.center = [0.0, 0.0];
+        camera.dead_zone = [20.0, 10.0];
+
+        camera.follow([15.0, -8.0], 0.1);
+        assert_eq!(camera.center, [0.0, 0.0]);
+
+        // only far enough to bring the target back to the dead zone's edge
+        camera.follow([50.0, -30.0], 0.1);
+        assert_eq!(camera.center, [30.0, -20.0]);
+    }
+
+    #[test]
+    fn following_eases_in_at_the_follow_speed() {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.center = [0.0, 0.0];
+        camera.follow_speed = 2.0;
+
+        camera.follow([100.0, 0.0], 0.5);
+        let covered = 1.0 - (-1.0f32).exp();
+        assert!(close(camera.center, [100.0 * covered, 0.0]));
+
+        // the same time in smaller steps ends up in the same place
+        let mut stepped = Camera2D::new(200.0, 100.0);
+        stepped.center = [0.0, 0.0];
+        stepped.follow_speed = 2.0;
+        for _ in 0..10 {
+            stepped.follow([100.0, 0.0], 0.05);
+        }
+        assert!(close(stepped.center, camera.center));
+    }
+
+    #[test]
+    fn bounds_keep_the_view_inside() {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.bounds = Some(Rect::new(0.0, 0.0, 1000.0, 500.0));
+
+        camera.follow([-300.0, 900.0], 0.1);
+        assert_eq!(camera.center, [100.0, 450.0]);
+        camera.follow([500.0, 200.0], 0.1);
+        assert_eq!(camera.center, [500.0, 200.0]);
+
+        // zooming out shows more of the world, so the center has to come further in
+        camera.zoom = 0.5;
+        camera.follow([0.0, 0.0], 0.1);
+        assert_eq!(camera.center, [200.0, 100.0]);
+    }
+
+    #[test]
+    fn bounds_smaller_than_the_view_center_it() {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.bounds = Some(Rect::new(10.0, 20.0, 50.0, 400.0));
+        camera.follow([500.0, 0.0], 0.1);
+        // too narrow, so centered across, but tall enough to clamp up and down
+        assert_eq!(camera.center, [35.0, 70.0]);
+
+        camera.set_viewport(200.0, 1000.0);
+        assert_eq!(camera.center, [35.0, 220.0]);
+    }
+
+    #[test]
+    fn the_visible_rect_grows_when_rotated() {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.center = [0.0, 0.0];
+        assert_eq!(camera.visible_rect(), Rect::new(-100.0, -50.0, 200.0, 100.0));
+        camera.rotation = FRAC_PI_2;
+        let rect = camera.visible_rect();
+        assert!(close([rect.x, rect.y], [-50.0, -100.0]));
+        assert!(close([rect.width, rect.height], [100.0, 200.0]));
+    }
+}
@@ -0,0 +1,272 @@
+use gfx;
+use gfx::format::ChannelType;
+use gfx::handle::{Buffer, RenderTargetView, Sampler};
+use gfx::memory::Typed;
+use gfx::traits::FactoryExt;
+use image::{Delay, Frame, ImageFormat, RgbaImage};
+use image::codecs::gif::{GifEncoder, Repeat};
+use post_process::{PostVertex, SCREEN_INDICES, SCREEN_QUAD, copy_pipe};
+use render_target::RenderTarget2D;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use ColorFormat;
+
+// gif frame delays are counted in hundredths of a second, so this divides evenly
+pub const DEFAULT_GIF_FPS: u32 = 25;
+
+// the window's own color target can't be read back, so a game that wants to capture frames
+// draws into this instead and presents it to the window at the end of the frame
+pub struct Capture<R: gfx::Resources> {
+    target: RenderTarget2D<R>,
+    download: Buffer<R, [u8; 4]>,
+    vbuf: Buffer<R, PostVertex>,
+    slice: gfx::Slice<R>,
+    sampler: Sampler<R>,
+    pso: gfx::PipelineState<R, copy_pipe::Meta>,
+}
+
+impl<R: gfx::Resources> Capture<R> {
+    pub fn new<F>(factory: &mut F, width: u32, height: u32) -> Result<Self, String>
+        where F: gfx::Factory<R>
+    {
+        let pso = factory.create_pipeline_simple(include_bytes!("shaders/post_vert.glsl"),
+                                    include_bytes!("shaders/post_copy_frag.glsl"),
+                                    copy_pipe::new())
+            .map_err(|e| format!("{:?}", e))?;
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&SCREEN_QUAD,
+                                                                    &SCREEN_INDICES[..]);
+        let target = RenderTarget2D::new(factory, width, height)?;
+        let download = factory.create_download_buffer((target.width() * target.height()) as usize)
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(Capture {
+            target: target,
+            download: download,
+            vbuf: vbuf,
+            slice: slice,
+            sampler: factory.create_sampler_linear(),
+            pso: pso,
+        })
+    }
+
+    pub fn resize<F>(&mut self, factory: &mut F, width: u32, height: u32) -> Result<(), String>
+        where F: gfx::Factory<R>
+    {
+        if width.max(1) == self.target.width() && height.max(1) == self.target.height() {
+            return Ok(());
+        }
+        *self = Capture::new(factory, width, height)?;
+        Ok(())
+    }
+
+    // where the frame should be drawn, with renderers' set_render_target or as the out of a
+    // PostProcessChain
+    pub fn target(&self) -> &RenderTarget2D<R> {
+        &self.target
+    }
+
+    // copies the frame to the window
+    pub fn present<C>(&self,
+                      encoder: &mut gfx::Encoder<R, C>,
+                      out: &RenderTargetView<R, ColorFormat>)
+        where C: gfx::CommandBuffer<R>
+    {
+        let data = copy_pipe::Data {
+            vbuf: self.vbuf.clone(),
+            source: (self.target.texture().clone(), self.sampler.clone()),
+            out: out.clone(),
+        };
+        encoder.draw(&self.slice, &self.pso, &data);
+    }
+
+    // asks for the frame to be copied somewhere the cpu can get at it. read only sees it once
+    // the encoder has been flushed, so only ask on frames that are actually being captured.
+    pub fn request<C>(&self, encoder: &mut gfx::Encoder<R, C>) -> Result<(), String>
+        where C: gfx::CommandBuffer<R>
+    {
+        let texture = self.target.raw_texture();
+        let info = texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+        encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, self.download.raw(), 0)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    // the frame copied by the last request, the right way up
+    pub fn read<F>(&self, factory: &mut F) -> Result<RgbaImage, String>
+        where F: gfx::Factory<R>
+    {
+        let width = self.target.width();
+        let height = self.target.height();
+        let reader = factory.read_mapping(&self.download).map_err(|e| format!("{:?}", e))?;
+
+        // gl hands rows back bottom first
+        let mut image = RgbaImage::new(width, height);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            pixel.0 = reader[((height - 1 - y) * width + x) as usize];
+        }
+        Ok(image)
+    }
+}
+
+// frames collected for an animated gif, which is only encoded once they are all in
+pub struct GifRecording {
+    frames: Vec<RgbaImage>,
+    limit: Option<usize>,
+    // in hundredths of a second
+    delay: u16,
+}
+
+impl GifRecording {
+    pub fn new(fps: u32) -> Self {
+        GifRecording {
+            frames: Vec::new(),
+            limit: None,
+            delay: (100 / fps.max(1)).max(1) as u16,
+        }
+    }
+
+    // stops taking frames once it has this many
+    pub fn with_limit(mut self, frames: usize) -> Self {
+        self.limit = Some(frames);
+        self
+    }
+
+    pub fn add_frame(&mut self, frame: RgbaImage) {
+        if !self.is_full() {
+            self.frames.push(frame);
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.frames.len() >= limit)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // writes a gif that loops forever. every frame gets its own palette, which is slow to work
+    // out for large frames but keeps the colors close.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if self.frames.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no frames recorded"));
+        }
+
+        let mut encoder = GifEncoder::new(File::create(path)?);
+        encoder.set_repeat(Repeat::Infinite).map_err(io::Error::other)?;
+        let delay = Delay::from_numer_denom_ms(self.delay as u32 * 10, 1);
+        let frames = self.frames.iter().map(|frame| Frame::from_parts(frame.clone(), 0, 0, delay));
+        encoder.encode_frames(frames).map_err(io::Error::other)
+    }
+}
+
+// writes a png whatever the path's extension, where RgbaImage::save would go by the extension
+pub fn save_png<P: AsRef<Path>>(image: &RgbaImage, path: P) -> io::Result<()> {
+    image.save_with_format(path, ImageFormat::Png).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::{AnimationDecoder, Rgba};
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    // a small frame filled with one color, standing in for one read back from a Capture
+    fn frame(color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(4, 3, Rgba(color))
+    }
+
+    fn temp_path(name: &str) -> ::std::path::PathBuf {
+        env::temp_dir().join(format!("learn_gfx_2d_capture_{}_{}", process::id(), name))
+    }
+
+    #[test]
+    fn recordings_collect_frames_until_their_limit() {
+        let mut recording = GifRecording::new(DEFAULT_GIF_FPS).with_limit(2);
+        assert!(recording.is_empty());
+        assert!(!recording.is_full());
+
+        recording.add_frame(frame([255, 0, 0, 255]));
+        assert_eq!(recording.len(), 1);
+        assert!(!recording.is_full());
+
+        recording.add_frame(frame([0, 255, 0, 255]));
+        recording.add_frame(frame([0, 0, 255, 255]));
+        assert_eq!(recording.len(), 2);
+        assert!(recording.is_full());
+        assert_eq!(recording.frames[1], frame([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn recordings_without_a_limit_are_never_full() {
+        let mut recording = GifRecording::new(DEFAULT_GIF_FPS);
+        for _ in 0..100 {
+            recording.add_frame(frame([0, 0, 0, 255]));
+        }
+        assert_eq!(recording.len(), 100);
+        assert!(!recording.is_full());
+    }
+
+    #[test]
+    fn delays_round_down_to_whole_hundredths() {
+        assert_eq!(GifRecording::new(25).delay, 4);
+        assert_eq!(GifRecording::new(30).delay, 3);
+        assert_eq!(GifRecording::new(60).delay, 1);
+        // faster than gifs can go still gets the shortest delay rather than none
+        assert_eq!(GifRecording::new(200).delay, 1);
+        assert_eq!(GifRecording::new(0).delay, 100);
+    }
+
+    #[test]
+    fn recordings_encode_every_frame_with_their_delay() {
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+        let mut recording = GifRecording::new(DEFAULT_GIF_FPS);
+        for &color in &colors {
+            recording.add_frame(frame(color));
+        }
+
+        let path = temp_path("recording.gif");
+        recording.save(&path).unwrap();
+        let decoded = GifDecoder::new(fs::File::open(&path).unwrap())
+            .and_then(|decoder| decoder.into_frames().collect_frames());
+        fs::remove_file(&path).unwrap();
+
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.len(), colors.len());
+        for (decoded, &color) in decoded.iter().zip(colors.iter()) {
+            assert_eq!(decoded.delay().numer_denom_ms(), (40, 1));
+            assert_eq!(*decoded.buffer(), frame(color));
+        }
+    }
+
+    #[test]
+    fn empty_recordings_are_not_saved() {
+        let path = temp_path("empty.gif");
+        let err = GifRecording::new(DEFAULT_GIF_FPS).save(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn save_png_writes_a_png_whatever_the_extension() {
+        let mut image = frame([10, 20, 30, 255]);
+        image.put_pixel(3, 2, Rgba([200, 100, 50, 128]));
+
+        let path = temp_path("screenshot.frame");
+        save_png(&image, &path).unwrap();
+        let bytes = fs::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        let bytes = bytes.unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+        let loaded = image::load_from_memory_with_format(&bytes, ImageFormat::Png).unwrap();
+        assert_eq!(loaded.to_rgba8(), image);
+    }
+}
@@ -0,0 +1,253 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use time;
+
+// a source of time in seconds. only differences between readings matter, so where zero is
+// is up to each clock.
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+}
+
+// the time as the operating system reports it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        time::precise_time_s()
+    }
+}
+
+// a clock that only moves when told to. clones share the same time, so a test can keep one
+// to advance while another is owned by whatever is being tested.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    seconds: Rc<Cell<f64>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        self.seconds.set(self.seconds.get() + seconds);
+    }
+
+    pub fn set(&self, seconds: f64) {
+        self.seconds.set(seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.seconds.get()
+    }
+}
+
+// real time deltas longer than this are cut short, so a stall such as dragging the window
+// doesn't make everything animated jump ahead
+pub const DEFAULT_MAX_DELTA: f64 = 0.25;
+
+// game time read from a clock once per frame. it can be slowed down, sped up or paused
+// without the code animating against it knowing, since all it sees is how far game time moved.
+pub struct FrameClock<C: Clock> {
+    clock: C,
+    last: Option<f64>,
+    elapsed: f64,
+    delta: f32,
+    max_delta: f64,
+    time_scale: f32,
+    paused: bool,
+    frames: u64,
+}
+
+impl<C: Clock> FrameClock<C> {
+    pub fn new(clock: C) -> Self {
+        FrameClock {
+            clock: clock,
+            last: None,
+            elapsed: 0.0,
+            delta: 0.0,
+            max_delta: DEFAULT_MAX_DELTA,
+            time_scale: 1.0,
+            paused: false,
+            frames: 0,
+        }
+    }
+
+    pub fn with_max_delta(mut self, seconds: f64) -> Self {
+        self.max_delta = seconds;
+        self
+    }
+
+    // reads the clock, call once at the start of each frame. returns the frame's delta. the
+    // first tick only starts the clock, so its delta is zero.
+    pub fn tick(&mut self) -> f32 {
+        let now = self.clock.now();
+        let real = match self.last {
+            Some(last) => (now - last).max(0.0).min(self.max_delta),
+            None => 0.0,
+        };
+        self.last = Some(now);
+        self.frames += 1;
+
+        let scaled = if self.paused {
+            0.0
+        } else {
+            real * self.time_scale as f64
+        };
+        self.elapsed += scaled;
+        self.delta = scaled as f32;
+        self.delta
+    }
+
+    // how far game time moved over the last tick, in seconds
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // game time in seconds since the first tick, not counting time spent paused
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    // how many times tick has been called
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // 0.5 runs at half speed and 2 at double. negative scales are treated as zero, game time
+    // never runs backwards.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_clock() -> (ManualClock, FrameClock<ManualClock>) {
+        let clock = ManualClock::new();
+        (clock.clone(), FrameClock::new(clock))
+    }
+
+    #[test]
+    fn the_first_tick_starts_the_clock() {
+        let (clock, mut frame_clock) = frame_clock();
+        clock.set(50.0);
+        assert_eq!(frame_clock.tick(), 0.0);
+        clock.advance(0.125);
+        assert_eq!(frame_clock.tick(), 0.125);
+        assert_eq!(frame_clock.elapsed(), 0.125);
+        assert_eq!(frame_clock.frames(), 2);
+    }
+
+    #[test]
+    fn pausing_stops_game_time() {
+        let (clock, mut frame_clock) = frame_clock();
+        frame_clock.tick();
+        clock.advance(0.125);
+        frame_clock.tick();
+
+        frame_clock.pause();
+        assert!(frame_clock.is_paused());
+        clock.advance(0.125);
+        assert_eq!(frame_clock.tick(), 0.0);
+        assert_eq!(frame_clock.elapsed(), 0.125);
+
+        // the time spent paused isn't made up for once it resumes
+        frame_clock.resume();
+        clock.advance(0.0625);
+        assert_eq!(frame_clock.tick(), 0.0625);
+        assert_eq!(frame_clock.elapsed(), 0.1875);
+
+        frame_clock.toggle_pause();
+        assert!(frame_clock.is_paused());
+        frame_clock.toggle_pause();
+        assert!(!frame_clock.is_paused());
+    }
+
+    #[test]
+    fn time_scale_stretches_deltas() {
+        let (clock, mut frame_clock) = frame_clock();
+        frame_clock.tick();
+
+        frame_clock.set_time_scale(0.5);
+        clock.advance(0.125);
+        assert_eq!(frame_clock.tick(), 0.0625);
+        frame_clock.set_time_scale(2.0);
+        clock.advance(0.125);
+        assert_eq!(frame_clock.tick(), 0.25);
+        assert_eq!(frame_clock.elapsed(), 0.3125);
+
+        frame_clock.set_time_scale(-1.0);
+        assert_eq!(frame_clock.time_scale(), 0.0);
+        clock.advance(0.125);
+        assert_eq!(frame_clock.tick(), 0.0);
+    }
+
+    #[test]
+    fn long_deltas_are_clamped() {
+        let (clock, frame_clock) = frame_clock();
+        let mut frame_clock = frame_clock.with_max_delta(0.1);
+        frame_clock.tick();
+
+        clock.advance(5.0);
+        assert_eq!(frame_clock.tick(), 0.1);
+        // the clamp is on real time, before the scale
+        frame_clock.set_time_scale(2.0);
+        clock.advance(5.0);
+        assert_eq!(frame_clock.tick(), 0.2);
+
+        // a clock going backwards doesn't run game time backwards
+        clock.advance(-1.0);
+        assert_eq!(frame_clock.tick(), 0.0);
+        assert!((frame_clock.elapsed() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn deltas_are_clamped_by_default() {
+        let (clock, mut frame_clock) = frame_clock();
+        frame_clock.tick();
+        clock.advance(10.0);
+        assert_eq!(frame_clock.tick(), DEFAULT_MAX_DELTA as f32);
+    }
+}
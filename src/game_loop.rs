@@ -0,0 +1,197 @@
+use clock::Clock;
+
+// frames longer than this are cut short, so a stall such as dragging the window doesn't leave
+// a backlog of updates that takes even longer to work through
+pub const DEFAULT_MAX_FRAME_TIME: f64 = 0.25;
+
+// what one frame of the loop works out to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    // how many fixed updates to run this frame
+    pub updates: u32,
+    // how far between the last update and the next one the frame falls, from 0 to 1. renderers
+    // blend the previous and current state by it so motion stays smooth between updates.
+    pub alpha: f32,
+    // seconds thrown away because the frame took longer than the maximum frame time
+    pub dropped: f64,
+}
+
+// runs game logic at a fixed rate no matter how fast frames are drawn, so movement doesn't
+// depend on the monitor's refresh rate
+pub struct GameLoop<C: Clock> {
+    clock: C,
+    timestep: f64,
+    max_frame_time: f64,
+    accumulator: f64,
+    last: Option<f64>,
+    updates: u64,
+}
+
+impl<C: Clock> GameLoop<C> {
+    // panics unless updates_per_second is above zero, since a timestep of zero or infinity would
+    // keep step from ever finishing
+    pub fn new(clock: C, updates_per_second: f64) -> Self {
+        assert!(updates_per_second > 0.0 && updates_per_second.is_finite(),
+                "a GameLoop needs a positive number of updates per second, not {}",
+                updates_per_second);
+        GameLoop {
+            clock: clock,
+            timestep: 1.0 / updates_per_second,
+            max_frame_time: DEFAULT_MAX_FRAME_TIME,
+            accumulator: 0.0,
+            last: None,
+            updates: 0,
+        }
+    }
+
+    pub fn with_max_frame_time(mut self, seconds: f64) -> Self {
+        self.max_frame_time = seconds;
+        self
+    }
+
+    // the dt every update is given, in seconds
+    pub fn timestep(&self) -> f32 {
+        self.timestep as f32
+    }
+
+    // how many updates have been run in total
+    pub fn updates(&self) -> u64 {
+        self.updates
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    // reads the clock and works out how many updates this frame owes. the first frame only
+    // starts the clock and runs none.
+    pub fn step(&mut self) -> Step {
+        let now = self.clock.now();
+        let mut elapsed = match self.last {
+            Some(last) => (now - last).max(0.0),
+            None => 0.0,
+        };
+        self.last = Some(now);
+
+        let mut dropped = 0.0;
+        if elapsed > self.max_frame_time {
+            dropped = elapsed - self.max_frame_time;
+            elapsed = self.max_frame_time;
+        }
+
+        self.accumulator += elapsed;
+        let mut updates = 0;
+        while self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+            updates += 1;
+        }
+        self.updates += updates as u64;
+
+        Step {
+            updates: updates,
+            alpha: (self.accumulator / self.timestep) as f32,
+            dropped: dropped,
+        }
+    }
+
+    // runs one frame: the updates it owes with the fixed dt, then a render with the alpha
+    pub fn frame<U, R>(&mut self, mut update: U, mut render: R) -> Step
+        where U: FnMut(f32),
+              R: FnMut(f32)
+    {
+        let step = self.step();
+        for _ in 0..step.updates {
+            update(self.timestep as f32);
+        }
+        render(step.alpha);
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::ManualClock;
+    use std::cell::RefCell;
+
+    fn game_loop(updates_per_second: f64) -> (ManualClock, GameLoop<ManualClock>) {
+        let clock = ManualClock::new();
+        (clock.clone(), GameLoop::new(clock, updates_per_second))
+    }
+
+    #[test]
+    fn the_first_step_runs_no_updates() {
+        let (clock, mut game_loop) = game_loop(10.0);
+        clock.set(100.0);
+        assert_eq!(game_loop.step(),
+                   Step {
+                       updates: 0,
+                       alpha: 0.0,
+                       dropped: 0.0,
+                   });
+    }
+
+    #[test]
+    fn updates_are_owed_per_timestep() {
+        let (clock, mut game_loop) = game_loop(10.0);
+        game_loop.step();
+
+        clock.advance(0.25);
+        let step = game_loop.step();
+        assert_eq!(step.updates, 2);
+        assert!((step.alpha - 0.5).abs() < 1e-4);
+
+        // the half update left over carries on into the next frame
+        clock.advance(0.06);
+        let step = game_loop.step();
+        assert_eq!(step.updates, 1);
+        assert!((step.alpha - 0.1).abs() < 1e-4);
+
+        clock.advance(0.01);
+        assert_eq!(game_loop.step().updates, 0);
+        assert_eq!(game_loop.updates(), 3);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let (clock, game_loop) = game_loop(10.0);
+        let mut game_loop = game_loop.with_max_frame_time(0.5);
+        game_loop.step();
+
+        clock.advance(3.0);
+        let step = game_loop.step();
+        assert_eq!(step.updates, 5);
+        assert!((step.dropped - 2.5).abs() < 1e-9);
+        assert!(step.alpha < 1e-4);
+    }
+
+    #[test]
+    fn frame_runs_updates_then_renders() {
+        let (clock, mut game_loop) = game_loop(16.0);
+        game_loop.step();
+        clock.advance(0.15625);
+
+        let calls = RefCell::new(Vec::new());
+        game_loop.frame(|dt| calls.borrow_mut().push(("update", dt)),
+                        |alpha| calls.borrow_mut().push(("render", alpha)));
+        assert_eq!(calls.into_inner(), vec![("update", 0.0625), ("update", 0.0625), ("render", 0.5)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive number of updates")]
+    fn zero_updates_per_second_panics() {
+        game_loop(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive number of updates")]
+    fn infinite_updates_per_second_panics() {
+        game_loop(f64::INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive number of updates")]
+    fn nan_updates_per_second_panics() {
+        game_loop(f64::NAN);
+    }
+}
@@ -0,0 +1,179 @@
+use image::{self, Rgba, RgbaImage};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// how far apart a channel may be from the reference before the pixel counts as different,
+// which leaves room for rounding to differ a little between machines
+pub const DEFAULT_TOLERANCE: u8 = 2;
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(io::Error),
+    Image(image::ImageError),
+    // there is no reference yet, blessing the output creates it
+    MissingReference(PathBuf),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    // how many pixels were out of tolerance, and where the diff image was written
+    Mismatch {
+        pixels: usize,
+        diff: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GoldenError::Io(ref err) => write!(f, "{}", err),
+            GoldenError::Image(ref err) => write!(f, "{}", err),
+            GoldenError::MissingReference(ref path) => {
+                write!(f, "no reference image at {}", path.display())
+            }
+            GoldenError::SizeMismatch { expected, actual } => {
+                write!(f,
+                       "expected a {}x{} image but got {}x{}",
+                       expected.0,
+                       expected.1,
+                       actual.0,
+                       actual.1)
+            }
+            GoldenError::Mismatch { pixels, ref diff } => {
+                write!(f, "{} pixels differ, see {}", pixels, diff.display())
+            }
+        }
+    }
+}
+
+impl Error for GoldenError {}
+
+impl From<io::Error> for GoldenError {
+    fn from(err: io::Error) -> Self {
+        GoldenError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for GoldenError {
+    fn from(err: image::ImageError) -> Self {
+        GoldenError::Image(err)
+    }
+}
+
+pub struct Difference {
+    // how many pixels were out of tolerance
+    pub pixels: usize,
+    // the largest difference of any one channel
+    pub largest: u8,
+    // the reference faded out, with every pixel out of tolerance in red
+    pub image: RgbaImage,
+}
+
+// compares two images the same size pixel by pixel
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Difference {
+    let (width, height) = expected.dimensions();
+    let mut difference = Difference {
+        pixels: 0,
+        largest: 0,
+        image: RgbaImage::new(width, height),
+    };
+
+    for (x, y, expected_pixel) in expected.enumerate_pixels() {
+        let actual_pixel = actual.get_pixel(x, y);
+        let mut largest = 0;
+        for channel in 0..4 {
+            let a = expected_pixel.0[channel] as i16;
+            let b = actual_pixel.0[channel] as i16;
+            largest = largest.max((a - b).unsigned_abs() as u8);
+        }
+        difference.largest = difference.largest.max(largest);
+
+        let pixel = if largest > tolerance {
+            difference.pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let data = expected_pixel.0;
+            let luma = (data[0] as u32 * 3 + data[1] as u32 * 6 + data[2] as u32) / 10;
+            let faded = (luma / 4) as u8;
+            Rgba([faded, faded, faded, 255])
+        };
+        difference.image.put_pixel(x, y, pixel);
+    }
+
+    difference
+}
+
+// checks rendered images against references stored as pngs, one per name. failures leave the
+// rendered image and a diff next to each other in the output directory.
+pub struct GoldenImages {
+    references: PathBuf,
+    output: PathBuf,
+    tolerance: u8,
+    bless: bool,
+}
+
+impl GoldenImages {
+    pub fn new<P, Q>(references: P, output: Q) -> Self
+        where P: Into<PathBuf>,
+              Q: Into<PathBuf>
+    {
+        GoldenImages {
+            references: references.into(),
+            output: output.into(),
+            tolerance: DEFAULT_TOLERANCE,
+            bless: false,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    // when blessing, whatever is checked becomes the new reference instead
+    pub fn with_bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    pub fn reference_path(&self, name: &str) -> PathBuf {
+        self.references.join(format!("{}.png", name))
+    }
+
+    pub fn check(&self, name: &str, actual: &RgbaImage) -> Result<(), GoldenError> {
+        let reference = self.reference_path(name);
+        if self.bless {
+            fs::create_dir_all(&self.references)?;
+            actual.save(&reference)?;
+            return Ok(());
+        }
+        if !reference.exists() {
+            return Err(GoldenError::MissingReference(reference));
+        }
+
+        let expected = image::open(&reference)?.to_rgba8();
+        if expected.dimensions() != actual.dimensions() {
+            return Err(GoldenError::SizeMismatch {
+                expected: expected.dimensions(),
+                actual: actual.dimensions(),
+            });
+        }
+
+        let difference = compare(&expected, actual, self.tolerance);
+        if difference.pixels == 0 {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.output)?;
+        let diff = self.output.join(format!("{}.diff.png", name));
+        actual.save(self.output.join(format!("{}.png", name)))?;
+        difference.image.save(&diff)?;
+        Err(GoldenError::Mismatch {
+            pixels: difference.pixels,
+            diff: diff,
+        })
+    }
+}
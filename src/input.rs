@@ -0,0 +1,357 @@
+use glutin::{ElementState, Event, MouseButton, VirtualKeyCode, WindowEvent};
+use mouse::Mouse;
+use std::collections::{HashMap, HashSet};
+
+// something physical that can be held down
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+// an axis is driven by two actions, one pulling it towards -1 and the other towards 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Axis {
+    negative: String,
+    positive: String,
+}
+
+// maps named actions such as "jump" to any number of keys and mouse buttons, so game code
+// asks about what the player wants to do rather than which key they pressed.
+//
+// call begin_frame once per frame before handing it that frame's events.
+#[derive(Clone)]
+pub struct InputMap {
+    actions: HashMap<String, Vec<Binding>>,
+    axes: HashMap<String, Axis>,
+    down: HashSet<Binding>,
+    pressed: HashSet<Binding>,
+    released: HashSet<Binding>,
+    mouse: Mouse,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap::new()
+    }
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        InputMap {
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+            down: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            mouse: Mouse::new(),
+        }
+    }
+
+    pub fn bind<S: Into<String>>(&mut self, action: S, binding: Binding) {
+        let bindings = self.actions.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|&bound| bound != binding);
+        }
+    }
+
+    // replaces everything bound to an action
+    pub fn set_bindings<S: Into<String>>(&mut self, action: S, bindings: Vec<Binding>) {
+        self.actions.insert(action.into(), bindings);
+    }
+
+    pub fn clear_bindings(&mut self, action: &str) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.clear();
+        }
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(|bindings| &bindings[..]).unwrap_or(&[])
+    }
+
+    pub fn has_action(&self, action: &str) -> bool {
+        self.actions.contains_key(action)
+    }
+
+    pub fn actions(&self) -> Vec<&str> {
+        self.actions.keys().map(|action| &action[..]).collect()
+    }
+
+    pub fn bind_axis<A, N, P>(&mut self, axis: A, negative: N, positive: P)
+        where A: Into<String>,
+              N: Into<String>,
+              P: Into<String>
+    {
+        self.axes.insert(axis.into(),
+                         Axis {
+                             negative: negative.into(),
+                             positive: positive.into(),
+                         });
+    }
+
+    // forgets which bindings were pressed or released during the previous frame
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse.begin_frame();
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Event::WindowEvent { ref event, .. } = *event {
+            self.handle_window_event(event);
+        }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    match input.state {
+                        ElementState::Pressed => self.press(Binding::Key(key)),
+                        ElementState::Released => self.release(Binding::Key(key)),
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => {
+                self.press(Binding::Mouse(button))
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
+                self.release(Binding::Mouse(button))
+            }
+            WindowEvent::CursorMoved { position, .. } => self.mouse.moved_logical(position),
+            WindowEvent::HiDpiFactorChanged(factor) => self.mouse.set_hidpi_factor(factor),
+            WindowEvent::MouseWheel { delta, .. } => self.mouse.scrolled(delta),
+            WindowEvent::CursorLeft { .. } => self.mouse.left_window(),
+            // releases never arrive for keys let go of while another window has focus
+            WindowEvent::Focused(false) => self.release_all(),
+            _ => {}
+        }
+    }
+
+    pub fn press(&mut self, binding: Binding) {
+        // held keys repeat their pressed event, which shouldn't count as a new press
+        if self.down.insert(binding) {
+            self.pressed.insert(binding);
+            if let Binding::Mouse(button) = binding {
+                self.mouse.pressed(button);
+            }
+        }
+    }
+
+    pub fn release(&mut self, binding: Binding) {
+        if self.down.remove(&binding) {
+            self.released.insert(binding);
+            if let Binding::Mouse(button) = binding {
+                self.mouse.released(button);
+            }
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        for binding in self.down.drain() {
+            self.released.insert(binding);
+        }
+        self.mouse.release_all();
+    }
+
+    pub fn mouse(&self) -> &Mouse {
+        &self.mouse
+    }
+
+    pub fn mouse_mut(&mut self) -> &mut Mouse {
+        &mut self.mouse
+    }
+
+    pub fn is_down(&self, binding: Binding) -> bool {
+        self.down.contains(&binding)
+    }
+
+    pub fn was_pressed(&self, binding: Binding) -> bool {
+        self.pressed.contains(&binding)
+    }
+
+    pub fn was_released(&self, binding: Binding) -> bool {
+        self.released.contains(&binding)
+    }
+
+    pub fn mouse_down(&self, button: MouseButton) -> bool {
+        self.is_down(Binding::Mouse(button))
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.was_pressed(Binding::Mouse(button))
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.was_released(Binding::Mouse(button))
+    }
+
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| self.down.contains(binding))
+    }
+
+    // whether any of the action's bindings went down this frame
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| self.pressed.contains(binding))
+    }
+
+    // whether the action stopped being held this frame
+    pub fn just_released(&self, action: &str) -> bool {
+        !self.is_pressed(action) &&
+        self.bindings(action).iter().any(|binding| self.released.contains(binding))
+    }
+
+    // -1, 0 or 1, with opposite actions held together cancelling out
+    pub fn axis_value(&self, axis: &str) -> f32 {
+        match self.axes.get(axis) {
+            Some(axis) => {
+                let mut value = 0.0;
+                if self.is_pressed(&axis.negative) {
+                    value -= 1.0;
+                }
+                if self.is_pressed(&axis.positive) {
+                    value += 1.0;
+                }
+                value
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glutin::{DeviceId, KeyboardInput, ModifiersState};
+
+    fn key(state: ElementState, key: VirtualKeyCode) -> WindowEvent {
+        WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state: state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::default(),
+            },
+        }
+    }
+
+    fn click(state: ElementState, button: MouseButton) -> WindowEvent {
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: state,
+            button: button,
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    fn input_map() -> InputMap {
+        let mut input = InputMap::new();
+        input.bind("jump", Binding::Key(VirtualKeyCode::Space));
+        input.bind("jump", Binding::Mouse(MouseButton::Right));
+        input.bind("left", Binding::Key(VirtualKeyCode::Left));
+        input.bind("left", Binding::Key(VirtualKeyCode::A));
+        input.bind("right", Binding::Key(VirtualKeyCode::Right));
+        // the axis and its actions can be named with different kinds of string
+        input.bind_axis("horizontal", "left", String::from("right"));
+        input
+    }
+
+    // hands the map one frame's worth of events
+    fn frame(input: &mut InputMap, events: &[WindowEvent]) {
+        input.begin_frame();
+        for event in events {
+            input.handle_window_event(event);
+        }
+    }
+
+    #[test]
+    fn pressing_and_releasing_are_edges() {
+        let mut input = input_map();
+        frame(&mut input, &[key(ElementState::Pressed, VirtualKeyCode::Space)]);
+        assert!(input.just_pressed("jump") && input.is_pressed("jump"));
+        assert!(!input.just_released("jump"));
+
+        // held, including through the repeats the os sends
+        frame(&mut input, &[key(ElementState::Pressed, VirtualKeyCode::Space)]);
+        assert!(!input.just_pressed("jump") && input.is_pressed("jump"));
+        frame(&mut input, &[]);
+        assert!(!input.just_pressed("jump") && input.is_pressed("jump"));
+
+        frame(&mut input, &[key(ElementState::Released, VirtualKeyCode::Space)]);
+        assert!(input.just_released("jump") && !input.is_pressed("jump"));
+        frame(&mut input, &[]);
+        assert!(!input.just_released("jump"));
+    }
+
+    #[test]
+    fn a_tap_within_one_frame_is_pressed_and_released() {
+        let mut input = input_map();
+        frame(&mut input,
+              &[key(ElementState::Pressed, VirtualKeyCode::Space),
+                key(ElementState::Released, VirtualKeyCode::Space)]);
+        assert!(input.just_pressed("jump") && input.just_released("jump"));
+        assert!(!input.is_pressed("jump"));
+    }
+
+    #[test]
+    fn an_action_is_held_while_any_binding_is() {
+        let mut input = input_map();
+        frame(&mut input,
+              &[key(ElementState::Pressed, VirtualKeyCode::Space),
+                click(ElementState::Pressed, MouseButton::Right)]);
+        assert!(input.mouse_just_pressed(MouseButton::Right));
+
+        frame(&mut input, &[key(ElementState::Released, VirtualKeyCode::Space)]);
+        assert!(input.is_pressed("jump") && !input.just_released("jump"));
+        assert!(input.was_released(Binding::Key(VirtualKeyCode::Space)));
+
+        frame(&mut input, &[click(ElementState::Released, MouseButton::Right)]);
+        assert!(input.just_released("jump"));
+        assert!(input.mouse_just_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = input_map();
+        frame(&mut input,
+              &[key(ElementState::Pressed, VirtualKeyCode::Left),
+                click(ElementState::Pressed, MouseButton::Right)]);
+        frame(&mut input, &[WindowEvent::Focused(false)]);
+        assert!(input.just_released("left") && input.just_released("jump"));
+        assert!(!input.mouse_down(MouseButton::Right));
+    }
+
+    #[test]
+    fn axes_follow_their_actions() {
+        let mut input = input_map();
+        assert_eq!(input.axis_value("horizontal"), 0.0);
+        assert_eq!(input.axis_value("vertical"), 0.0);
+
+        frame(&mut input, &[key(ElementState::Pressed, VirtualKeyCode::A)]);
+        assert_eq!(input.axis_value("horizontal"), -1.0);
+        frame(&mut input, &[key(ElementState::Pressed, VirtualKeyCode::Right)]);
+        assert_eq!(input.axis_value("horizontal"), 0.0);
+        frame(&mut input, &[key(ElementState::Released, VirtualKeyCode::A)]);
+        assert_eq!(input.axis_value("horizontal"), 1.0);
+        frame(&mut input, &[key(ElementState::Released, VirtualKeyCode::Right)]);
+        assert_eq!(input.axis_value("horizontal"), 0.0);
+    }
+
+    #[test]
+    fn unbinding_stops_an_action() {
+        let mut input = input_map();
+        input.unbind("left", Binding::Key(VirtualKeyCode::A));
+        frame(&mut input, &[key(ElementState::Pressed, VirtualKeyCode::A)]);
+        assert!(!input.is_pressed("left"));
+        assert!(input.is_down(Binding::Key(VirtualKeyCode::A)));
+        assert_eq!(input.bindings("left"), &[Binding::Key(VirtualKeyCode::Left)]);
+    }
+}
@@ -0,0 +1,45 @@
+extern crate base64;
+extern crate cgmath;
+extern crate flate2;
+#[macro_use]
+extern crate gfx;
+extern crate gfx_device_gl;
+extern crate glutin;
+extern crate image;
+extern crate rusttype;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate time;
+extern crate toml;
+extern crate unicode_normalization;
+extern crate xml;
+
+pub mod animation;
+pub mod aseprite;
+pub mod atlas;
+pub mod batch;
+pub mod bindings;
+pub mod camera;
+pub mod capture;
+pub mod clock;
+pub mod game_loop;
+pub mod golden;
+pub mod input;
+pub mod mouse;
+pub mod post_process;
+pub mod raster;
+pub mod rect;
+pub mod render_target;
+pub mod replay;
+pub mod scenes;
+pub mod sprite;
+pub mod text;
+pub mod texture;
+pub mod tilemap;
+pub mod viewport;
+pub mod window;
+
+pub type ColorFormat = gfx::format::Rgba8;
+pub type DepthFormat = gfx::format::Depth;
@@ -1,4 +0,0 @@
-fn main() {
-    println!("Hello, and thank you for checking out learn_gfx_2d.");
-    println!("Please checkout the various examples in the example directory.");
-}
@@ -0,0 +1,324 @@
+use camera::Camera2D;
+use glutin::dpi::LogicalPosition;
+use glutin::{MouseButton, MouseScrollDelta};
+
+// how far, in physical pixels, the cursor has to move with a button held before it's a drag
+pub const DEFAULT_DRAG_THRESHOLD: f32 = 4.0;
+
+// touchpads scroll in pixels while wheels scroll in lines, this evens the two out
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drag {
+    pub button: MouseButton,
+    // where the button went down, in physical pixels
+    pub start: [f32; 2],
+    pub current: [f32; 2],
+}
+
+impl Drag {
+    pub fn delta(&self) -> [f32; 2] {
+        [self.current[0] - self.start[0], self.current[1] - self.start[1]]
+    }
+}
+
+// the cursor as of the events seen so far. positions are in the window's physical pixels with
+// the origin at the top left, the same pixels Viewport and Camera2D work in. glutin reports the
+// cursor in logical pixels, so they're scaled by the window's hidpi factor, which has to be
+// given with set_hidpi_factor. InputMap keeps one of these up to date, and tracks the buttons
+// themselves along with the keys.
+#[derive(Clone, Debug)]
+pub struct Mouse {
+    position: [f32; 2],
+    frame_start: [f32; 2],
+    inside: bool,
+    wheel: [f32; 2],
+    drag_threshold: f32,
+    hidpi_factor: f64,
+    // buttons being held and where they went down
+    held: Vec<(MouseButton, [f32; 2])>,
+    drags: Vec<Drag>,
+    started: Vec<Drag>,
+    ended: Vec<Drag>,
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Mouse::new()
+    }
+}
+
+impl Mouse {
+    pub fn new() -> Self {
+        Mouse {
+            position: [0.0, 0.0],
+            frame_start: [0.0, 0.0],
+            inside: false,
+            wheel: [0.0, 0.0],
+            drag_threshold: DEFAULT_DRAG_THRESHOLD,
+            hidpi_factor: 1.0,
+            held: Vec::new(),
+            drags: Vec::new(),
+            started: Vec::new(),
+            ended: Vec::new(),
+        }
+    }
+
+    pub fn set_drag_threshold(&mut self, threshold: f32) {
+        self.drag_threshold = threshold;
+    }
+
+    pub fn hidpi_factor(&self) -> f64 {
+        self.hidpi_factor
+    }
+
+    // the window's, from get_hidpi_factor and then each HiDpiFactorChanged event
+    pub fn set_hidpi_factor(&mut self, factor: f64) {
+        self.hidpi_factor = factor;
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_start = self.position;
+        self.wheel = [0.0, 0.0];
+        self.started.clear();
+        self.ended.clear();
+    }
+
+    // a position as glutin reports it, in logical pixels
+    pub fn moved_logical(&mut self, position: LogicalPosition) {
+        let physical = position.to_physical(self.hidpi_factor);
+        self.moved(physical.x as f32, physical.y as f32)
+    }
+
+    // a position in physical pixels
+    pub fn moved(&mut self, x: f32, y: f32) {
+        self.position = [x, y];
+        self.inside = true;
+
+        for drag in &mut self.drags {
+            drag.current = self.position;
+        }
+
+        let threshold = self.drag_threshold;
+        for &(button, start) in &self.held {
+            let dragging = self.drags.iter().any(|drag| drag.button == button);
+            let dx = x - start[0];
+            let dy = y - start[1];
+            if !dragging && (dx * dx + dy * dy).sqrt() >= threshold {
+                let drag = Drag {
+                    button: button,
+                    start: start,
+                    current: self.position,
+                };
+                self.drags.push(drag);
+                self.started.push(drag);
+            }
+        }
+    }
+
+    pub fn pressed(&mut self, button: MouseButton) {
+        if !self.held.iter().any(|&(held, _)| held == button) {
+            self.held.push((button, self.position));
+        }
+    }
+
+    pub fn released(&mut self, button: MouseButton) {
+        self.held.retain(|&(held, _)| held != button);
+        if let Some(index) = self.drags.iter().position(|drag| drag.button == button) {
+            let mut drag = self.drags.remove(index);
+            drag.current = self.position;
+            self.ended.push(drag);
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        let buttons: Vec<MouseButton> = self.held.iter().map(|&(button, _)| button).collect();
+        for button in buttons {
+            self.released(button);
+        }
+    }
+
+    pub fn scrolled(&mut self, delta: MouseScrollDelta) {
+        let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x, y),
+            MouseScrollDelta::PixelDelta(position) => {
+                (position.x as f32 / PIXELS_PER_LINE, position.y as f32 / PIXELS_PER_LINE)
+            }
+        };
+        self.wheel[0] += x;
+        self.wheel[1] += y;
+    }
+
+    pub fn left_window(&mut self) {
+        self.inside = false;
+    }
+
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    pub fn world_position(&self, camera: &Camera2D) -> [f32; 2] {
+        camera.screen_to_world(self.position)
+    }
+
+    // how far the cursor moved this frame
+    pub fn motion(&self) -> [f32; 2] {
+        [self.position[0] - self.frame_start[0], self.position[1] - self.frame_start[1]]
+    }
+
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+
+    // lines scrolled this frame, with positive y scrolling up
+    pub fn wheel(&self) -> [f32; 2] {
+        self.wheel
+    }
+
+    // the drag in progress with this button, if any
+    pub fn drag(&self, button: MouseButton) -> Option<Drag> {
+        self.drags.iter().find(|drag| drag.button == button).cloned()
+    }
+
+    // the drag that passed the threshold this frame
+    pub fn drag_started(&self, button: MouseButton) -> Option<Drag> {
+        self.started.iter().find(|drag| drag.button == button).cloned()
+    }
+
+    // the drag whose button was let go of this frame
+    pub fn drag_ended(&self, button: MouseButton) -> Option<Drag> {
+        self.ended.iter().find(|drag| drag.button == button).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glutin::{DeviceId, ElementState, ModifiersState, TouchPhase, WindowEvent};
+    use input::InputMap;
+
+    fn cursor(x: f64, y: f64) -> WindowEvent {
+        WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: LogicalPosition::new(x, y),
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    fn click(state: ElementState) -> WindowEvent {
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    fn wheel(delta: MouseScrollDelta) -> WindowEvent {
+        WindowEvent::MouseWheel {
+            device_id: unsafe { DeviceId::dummy() },
+            delta: delta,
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    fn frame(input: &mut InputMap, events: &[WindowEvent]) {
+        input.begin_frame();
+        for event in events {
+            input.handle_window_event(event);
+        }
+    }
+
+    #[test]
+    fn buttons_have_edges() {
+        let mut input = InputMap::new();
+        frame(&mut input, &[click(ElementState::Pressed)]);
+        assert!(input.mouse_just_pressed(MouseButton::Left));
+        assert!(input.mouse_down(MouseButton::Left));
+
+        frame(&mut input, &[]);
+        assert!(!input.mouse_just_pressed(MouseButton::Left));
+        assert!(input.mouse_down(MouseButton::Left));
+
+        frame(&mut input, &[click(ElementState::Released)]);
+        assert!(input.mouse_just_released(MouseButton::Left));
+        assert!(!input.mouse_down(MouseButton::Left));
+        assert!(!input.mouse_down(MouseButton::Right));
+    }
+
+    #[test]
+    fn positions_are_scaled_to_physical_pixels() {
+        let mut input = InputMap::new();
+        input.mouse_mut().set_hidpi_factor(2.0);
+        frame(&mut input, &[cursor(10.0, 20.5)]);
+        assert_eq!(input.mouse().position(), [20.0, 41.0]);
+        assert!(input.mouse().is_inside());
+
+        frame(&mut input, &[WindowEvent::HiDpiFactorChanged(1.5), cursor(10.0, 20.0)]);
+        assert_eq!(input.mouse().position(), [15.0, 30.0]);
+        assert_eq!(input.mouse().motion(), [-5.0, -11.0]);
+
+        frame(&mut input, &[WindowEvent::CursorLeft { device_id: unsafe { DeviceId::dummy() } }]);
+        assert!(!input.mouse().is_inside());
+        assert_eq!(input.mouse().motion(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn the_wheel_adds_up_over_a_frame() {
+        // a touchpad scrolling a line's worth of pixels back down
+        let pixels = LogicalPosition::new(0.0, -PIXELS_PER_LINE as f64);
+        let mut input = InputMap::new();
+        frame(&mut input,
+              &[wheel(MouseScrollDelta::LineDelta(0.0, 1.0)),
+                wheel(MouseScrollDelta::LineDelta(0.5, 2.0)),
+                wheel(MouseScrollDelta::PixelDelta(pixels))]);
+        assert_eq!(input.mouse().wheel(), [0.5, 2.0]);
+
+        frame(&mut input, &[]);
+        assert_eq!(input.mouse().wheel(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn drags_start_past_the_threshold() {
+        let mut mouse = Mouse::new();
+        mouse.set_drag_threshold(5.0);
+        mouse.moved(10.0, 10.0);
+        mouse.pressed(MouseButton::Left);
+
+        mouse.begin_frame();
+        mouse.moved(13.0, 13.0);
+        assert!(mouse.drag(MouseButton::Left).is_none());
+
+        mouse.begin_frame();
+        mouse.moved(13.0, 14.0);
+        let started = mouse.drag_started(MouseButton::Left).unwrap();
+        assert_eq!(started.start, [10.0, 10.0]);
+        assert_eq!(started.delta(), [3.0, 4.0]);
+
+        // still dragging, but it only started once
+        mouse.begin_frame();
+        mouse.moved(30.0, 2.0);
+        assert!(mouse.drag_started(MouseButton::Left).is_none());
+        assert_eq!(mouse.drag(MouseButton::Left).unwrap().delta(), [20.0, -8.0]);
+        assert!(mouse.drag(MouseButton::Right).is_none());
+
+        mouse.begin_frame();
+        mouse.released(MouseButton::Left);
+        assert_eq!(mouse.drag_ended(MouseButton::Left).unwrap().delta(), [20.0, -8.0]);
+        assert!(mouse.drag(MouseButton::Left).is_none());
+        mouse.begin_frame();
+        assert!(mouse.drag_ended(MouseButton::Left).is_none());
+    }
+
+    #[test]
+    fn clicks_without_moving_arent_drags() {
+        let mut mouse = Mouse::new();
+        mouse.moved(10.0, 10.0);
+        mouse.pressed(MouseButton::Left);
+        mouse.moved(11.0, 10.0);
+        mouse.released(MouseButton::Left);
+        assert!(mouse.drag_started(MouseButton::Left).is_none());
+        assert!(mouse.drag_ended(MouseButton::Left).is_none());
+    }
+}
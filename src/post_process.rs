@@ -0,0 +1,616 @@
+use gfx;
+use gfx::handle::{Buffer, RenderTargetView, Sampler, ShaderResourceView};
+use gfx::traits::FactoryExt;
+use image::{Rgba, RgbaImage};
+use render_target::RenderTarget2D;
+use texture::texture_from_image;
+use ColorFormat;
+
+gfx_defines!{
+    constant GrayscaleParams {
+        amount: f32 = "amount",
+    }
+
+    constant VignetteParams {
+        radius: f32 = "radius",
+        softness: f32 = "softness",
+        strength: f32 = "strength",
+    }
+
+    constant ScanlineParams {
+        resolution: [f32; 2] = "resolution",
+        intensity: f32 = "intensity",
+        curvature: f32 = "curvature",
+    }
+
+    constant BrightParams {
+        threshold: f32 = "threshold",
+    }
+
+    constant BlurParams {
+        offset: [f32; 2] = "offset",
+    }
+
+    constant BloomParams {
+        intensity: f32 = "intensity",
+    }
+
+    constant ColorGradeParams {
+        size: f32 = "size",
+        amount: f32 = "amount",
+    }
+
+    // a corner of the quad covering the whole target, already in clip space
+    vertex PostVertex {
+        position: [f32; 2] = "position",
+        tex_coord: [f32; 2] = "texture_coord",
+    }
+
+    pipeline copy_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    pipeline grayscale_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<GrayscaleParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    pipeline vignette_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<VignetteParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    pipeline scanline_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<ScanlineParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    pipeline bright_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<BrightParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    pipeline blur_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<BlurParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    // adds the blurred bright parts back onto the scene
+    pipeline bloom_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<BloomParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        glow: gfx::TextureSampler<[f32; 4]> = "glow",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+
+    pipeline color_grade_pipe {
+        vbuf: gfx::VertexBuffer<PostVertex> = (),
+        params: gfx::ConstantBuffer<ColorGradeParams> = "Params",
+        source: gfx::TextureSampler<[f32; 4]> = "source",
+        lut: gfx::TextureSampler<[f32; 4]> = "lut",
+        out: gfx::RenderTarget<ColorFormat> = "color",
+    }
+}
+
+// render targets are sampled the way gl stores them, bottom row first, so unlike TEX_QUAD
+// these coordinates need no flipping
+pub const SCREEN_QUAD: [PostVertex; 4] = [PostVertex {
+                                              position: [-1.0, -1.0],
+                                              tex_coord: [0.0, 0.0],
+                                          },
+                                          PostVertex {
+                                              position: [1.0, -1.0],
+                                              tex_coord: [1.0, 0.0],
+                                          },
+                                          PostVertex {
+                                              position: [1.0, 1.0],
+                                              tex_coord: [1.0, 1.0],
+                                          },
+                                          PostVertex {
+                                              position: [-1.0, 1.0],
+                                              tex_coord: [0.0, 1.0],
+                                          }];
+
+pub const SCREEN_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+// the neutral lookup table bound until set_lut is called
+const DEFAULT_LUT_SIZE: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    // 0 leaves colors alone and 1 is fully gray
+    Grayscale { amount: f32 },
+    // darkens towards the edges. radius and softness are measured from 0 in the middle of the
+    // screen to 1 in its corners, and strength is how dark the edges get.
+    Vignette {
+        radius: f32,
+        softness: f32,
+        strength: f32,
+    },
+    // the dark gaps between an old monitor's rows and the bulge of its glass
+    Scanlines { intensity: f32, curvature: f32 },
+    // lets whatever is brighter than the threshold glow onto its surroundings. radius is the
+    // number of pixels between blur samples, so larger glows reach further but look blockier.
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: f32,
+    },
+    // remaps every color through the chain's lookup table, see set_lut
+    ColorGrade { amount: f32 },
+}
+
+impl Effect {
+    pub fn grayscale() -> Self {
+        Effect::Grayscale { amount: 1.0 }
+    }
+
+    pub fn vignette() -> Self {
+        Effect::Vignette {
+            radius: 0.9,
+            softness: 0.5,
+            strength: 0.8,
+        }
+    }
+
+    pub fn scanlines() -> Self {
+        Effect::Scanlines {
+            intensity: 0.4,
+            curvature: 0.05,
+        }
+    }
+
+    pub fn bloom() -> Self {
+        Effect::Bloom {
+            threshold: 0.7,
+            intensity: 1.0,
+            radius: 2.0,
+        }
+    }
+
+    pub fn color_grade() -> Self {
+        Effect::ColorGrade { amount: 1.0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pass {
+    pub effect: Effect,
+    // disabled passes are skipped, so effects can be switched on and off while running
+    pub enabled: bool,
+}
+
+// a stack of full screen effects applied one after the other. the scene is drawn into scene()
+// instead of the window, then apply runs each enabled pass on the previous one's output, with
+// the last writing to the window.
+pub struct PostProcessChain<R: gfx::Resources> {
+    vbuf: Buffer<R, PostVertex>,
+    slice: gfx::Slice<R>,
+    sampler: Sampler<R>,
+    // the scene is drawn into the first, and passes then bounce between the two
+    targets: [RenderTarget2D<R>; 2],
+    // bloom's bright pass writes to the first, its horizontal blur to the second and its
+    // vertical blur back to the first, which is then added onto the scene
+    bloom_targets: [RenderTarget2D<R>; 2],
+    copy_pso: gfx::PipelineState<R, copy_pipe::Meta>,
+    grayscale_pso: gfx::PipelineState<R, grayscale_pipe::Meta>,
+    grayscale_params: Buffer<R, GrayscaleParams>,
+    vignette_pso: gfx::PipelineState<R, vignette_pipe::Meta>,
+    vignette_params: Buffer<R, VignetteParams>,
+    scanline_pso: gfx::PipelineState<R, scanline_pipe::Meta>,
+    scanline_params: Buffer<R, ScanlineParams>,
+    bright_pso: gfx::PipelineState<R, bright_pipe::Meta>,
+    bright_params: Buffer<R, BrightParams>,
+    blur_pso: gfx::PipelineState<R, blur_pipe::Meta>,
+    horizontal_blur_params: Buffer<R, BlurParams>,
+    vertical_blur_params: Buffer<R, BlurParams>,
+    bloom_pso: gfx::PipelineState<R, bloom_pipe::Meta>,
+    bloom_params: Buffer<R, BloomParams>,
+    color_grade_pso: gfx::PipelineState<R, color_grade_pipe::Meta>,
+    color_grade_params: Buffer<R, ColorGradeParams>,
+    lut: ShaderResourceView<R, [f32; 4]>,
+    lut_size: u32,
+    passes: Vec<Pass>,
+}
+
+impl<R: gfx::Resources> PostProcessChain<R> {
+    pub fn new<F>(factory: &mut F, width: u32, height: u32) -> Result<Self, String>
+        where F: gfx::Factory<R>
+    {
+        let vertex_shader = include_bytes!("shaders/post_vert.glsl");
+        let copy_pso = factory.create_pipeline_simple(vertex_shader,
+                                    include_bytes!("shaders/post_copy_frag.glsl"),
+                                    copy_pipe::new())
+            .map_err(|e| format!("{:?}", e))?;
+        let grayscale_pso =
+            factory.create_pipeline_simple(vertex_shader,
+                                        include_bytes!("shaders/post_grayscale_frag.glsl"),
+                                        grayscale_pipe::new())
+                .map_err(|e| format!("{:?}", e))?;
+        let vignette_pso =
+            factory.create_pipeline_simple(vertex_shader,
+                                        include_bytes!("shaders/post_vignette_frag.glsl"),
+                                        vignette_pipe::new())
+                .map_err(|e| format!("{:?}", e))?;
+        let scanline_pso =
+            factory.create_pipeline_simple(vertex_shader,
+                                        include_bytes!("shaders/post_scanlines_frag.glsl"),
+                                        scanline_pipe::new())
+                .map_err(|e| format!("{:?}", e))?;
+        let bright_pso = factory.create_pipeline_simple(vertex_shader,
+                                    include_bytes!("shaders/post_bright_frag.glsl"),
+                                    bright_pipe::new())
+            .map_err(|e| format!("{:?}", e))?;
+        let blur_pso = factory.create_pipeline_simple(vertex_shader,
+                                    include_bytes!("shaders/post_blur_frag.glsl"),
+                                    blur_pipe::new())
+            .map_err(|e| format!("{:?}", e))?;
+        let bloom_pso = factory.create_pipeline_simple(vertex_shader,
+                                    include_bytes!("shaders/post_bloom_frag.glsl"),
+                                    bloom_pipe::new())
+            .map_err(|e| format!("{:?}", e))?;
+        let color_grade_pso =
+            factory.create_pipeline_simple(vertex_shader,
+                                        include_bytes!("shaders/post_color_grade_frag.glsl"),
+                                        color_grade_pipe::new())
+                .map_err(|e| format!("{:?}", e))?;
+
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&SCREEN_QUAD,
+                                                                    &SCREEN_INDICES[..]);
+        let lut = texture_from_image(factory, &identity_lut(DEFAULT_LUT_SIZE))?;
+
+        Ok(PostProcessChain {
+            vbuf: vbuf,
+            slice: slice,
+            sampler: factory.create_sampler_linear(),
+            targets: [RenderTarget2D::new(factory, width, height)?,
+                      RenderTarget2D::new(factory, width, height)?],
+            bloom_targets: [RenderTarget2D::new(factory, width, height)?,
+                            RenderTarget2D::new(factory, width, height)?],
+            copy_pso: copy_pso,
+            grayscale_pso: grayscale_pso,
+            grayscale_params: factory.create_constant_buffer(1),
+            vignette_pso: vignette_pso,
+            vignette_params: factory.create_constant_buffer(1),
+            scanline_pso: scanline_pso,
+            scanline_params: factory.create_constant_buffer(1),
+            bright_pso: bright_pso,
+            bright_params: factory.create_constant_buffer(1),
+            blur_pso: blur_pso,
+            horizontal_blur_params: factory.create_constant_buffer(1),
+            vertical_blur_params: factory.create_constant_buffer(1),
+            bloom_pso: bloom_pso,
+            bloom_params: factory.create_constant_buffer(1),
+            color_grade_pso: color_grade_pso,
+            color_grade_params: factory.create_constant_buffer(1),
+            lut: lut,
+            lut_size: DEFAULT_LUT_SIZE,
+            passes: Vec::new(),
+        })
+    }
+
+    // call when the window is resized. the scene target is replaced, so renderers drawing into
+    // it need set_render_target again.
+    pub fn resize<F>(&mut self, factory: &mut F, width: u32, height: u32) -> Result<(), String>
+        where F: gfx::Factory<R>
+    {
+        for target in self.targets.iter_mut().chain(self.bloom_targets.iter_mut()) {
+            target.resize(factory, width, height)?;
+        }
+        Ok(())
+    }
+
+    // where the scene should be drawn each frame, before apply
+    pub fn scene(&self) -> &RenderTarget2D<R> {
+        &self.targets[0]
+    }
+
+    // the table ColorGrade looks colors up in, laid out as identity_lut describes
+    pub fn set_lut<F>(&mut self, factory: &mut F, lut: &RgbaImage) -> Result<(), String>
+        where F: gfx::Factory<R>
+    {
+        let size = lut.height();
+        if lut.width() != size * size {
+            return Err(format!("a lookup table {} pixels high should be {} wide, not {}",
+                               size,
+                               size * size,
+                               lut.width()));
+        }
+        self.lut = texture_from_image(factory, lut)?;
+        self.lut_size = size;
+        Ok(())
+    }
+
+    // adds an enabled pass to the end of the chain, returning its index
+    pub fn push(&mut self, effect: Effect) -> usize {
+        self.passes.push(Pass {
+            effect: effect,
+            enabled: true,
+        });
+        self.passes.len() - 1
+    }
+
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    // for reordering, removing or tweaking passes
+    pub fn passes_mut(&mut self) -> &mut Vec<Pass> {
+        &mut self.passes
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(pass) = self.passes.get_mut(index) {
+            pass.enabled = enabled;
+        }
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(pass) = self.passes.get_mut(index) {
+            pass.enabled = !pass.enabled;
+        }
+    }
+
+    // runs every enabled pass over the scene, the last one drawing into out. with nothing
+    // enabled the scene is copied across as is.
+    pub fn apply<C>(&mut self,
+                    encoder: &mut gfx::Encoder<R, C>,
+                    out: &RenderTargetView<R, ColorFormat>)
+        where C: gfx::CommandBuffer<R>
+    {
+        let effects: Vec<Effect> = self.passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .map(|pass| pass.effect)
+            .collect();
+
+        if effects.is_empty() {
+            let data = copy_pipe::Data {
+                vbuf: self.vbuf.clone(),
+                source: (self.targets[0].texture().clone(), self.sampler.clone()),
+                out: out.clone(),
+            };
+            encoder.draw(&self.slice, &self.copy_pso, &data);
+            return;
+        }
+
+        let mut current = 0;
+        for (index, &effect) in effects.iter().enumerate() {
+            let source = self.targets[current].texture().clone();
+            let destination = if index + 1 == effects.len() {
+                out.clone()
+            } else {
+                self.targets[1 - current].target().clone()
+            };
+            self.draw_pass(encoder, effect, source, destination);
+            current = 1 - current;
+        }
+    }
+
+    fn draw_pass<C>(&self,
+                    encoder: &mut gfx::Encoder<R, C>,
+                    effect: Effect,
+                    source: ShaderResourceView<R, [f32; 4]>,
+                    out: RenderTargetView<R, ColorFormat>)
+        where C: gfx::CommandBuffer<R>
+    {
+        let source = (source, self.sampler.clone());
+        let width = self.targets[0].width() as f32;
+        let height = self.targets[0].height() as f32;
+
+        match effect {
+            Effect::Grayscale { amount } => {
+                encoder.update_constant_buffer(&self.grayscale_params,
+                                               &GrayscaleParams { amount: amount });
+                let data = grayscale_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.grayscale_params.clone(),
+                    source: source,
+                    out: out,
+                };
+                encoder.draw(&self.slice, &self.grayscale_pso, &data);
+            }
+            Effect::Vignette { radius, softness, strength } => {
+                encoder.update_constant_buffer(&self.vignette_params,
+                                               &VignetteParams {
+                                                   radius: radius,
+                                                   softness: softness,
+                                                   strength: strength,
+                                               });
+                let data = vignette_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.vignette_params.clone(),
+                    source: source,
+                    out: out,
+                };
+                encoder.draw(&self.slice, &self.vignette_pso, &data);
+            }
+            Effect::Scanlines { intensity, curvature } => {
+                encoder.update_constant_buffer(&self.scanline_params,
+                                               &ScanlineParams {
+                                                   resolution: [width, height],
+                                                   intensity: intensity,
+                                                   curvature: curvature,
+                                               });
+                let data = scanline_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.scanline_params.clone(),
+                    source: source,
+                    out: out,
+                };
+                encoder.draw(&self.slice, &self.scanline_pso, &data);
+            }
+            Effect::Bloom { threshold, intensity, radius } => {
+                let bright = &self.bloom_targets[0];
+                let blurred = &self.bloom_targets[1];
+
+                encoder.update_constant_buffer(&self.bright_params,
+                                               &BrightParams { threshold: threshold });
+                let data = bright_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.bright_params.clone(),
+                    source: source.clone(),
+                    out: bright.target().clone(),
+                };
+                encoder.draw(&self.slice, &self.bright_pso, &data);
+
+                // a gaussian blur is separable, so blurring rows and then columns takes 9 + 9
+                // samples a pixel where blurring both at once would take 9 * 9
+                encoder.update_constant_buffer(&self.horizontal_blur_params,
+                                               &BlurParams { offset: [radius / width, 0.0] });
+                let data = blur_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.horizontal_blur_params.clone(),
+                    source: (bright.texture().clone(), self.sampler.clone()),
+                    out: blurred.target().clone(),
+                };
+                encoder.draw(&self.slice, &self.blur_pso, &data);
+
+                encoder.update_constant_buffer(&self.vertical_blur_params,
+                                               &BlurParams { offset: [0.0, radius / height] });
+                let data = blur_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.vertical_blur_params.clone(),
+                    source: (blurred.texture().clone(), self.sampler.clone()),
+                    out: bright.target().clone(),
+                };
+                encoder.draw(&self.slice, &self.blur_pso, &data);
+
+                encoder.update_constant_buffer(&self.bloom_params,
+                                               &BloomParams { intensity: intensity });
+                let data = bloom_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.bloom_params.clone(),
+                    source: source,
+                    glow: (bright.texture().clone(), self.sampler.clone()),
+                    out: out,
+                };
+                encoder.draw(&self.slice, &self.bloom_pso, &data);
+            }
+            Effect::ColorGrade { amount } => {
+                encoder.update_constant_buffer(&self.color_grade_params,
+                                               &ColorGradeParams {
+                                                   size: self.lut_size as f32,
+                                                   amount: amount,
+                                               });
+                let data = color_grade_pipe::Data {
+                    vbuf: self.vbuf.clone(),
+                    params: self.color_grade_params.clone(),
+                    source: source,
+                    lut: (self.lut.clone(), self.sampler.clone()),
+                    out: out,
+                };
+                encoder.draw(&self.slice, &self.color_grade_pso, &data);
+            }
+        }
+    }
+}
+
+// a lookup table that leaves colors as they are, a starting point to edit in a paint program.
+// it is size slices of size x size pixels side by side: red increases left to right within a
+// slice, green top to bottom, and blue from one slice to the next.
+pub fn identity_lut(size: u32) -> RgbaImage {
+    lut_from_fn(size, |color| color)
+}
+
+// a lookup table made by passing every color it covers through grade
+pub fn lut_from_fn<F>(size: u32, grade: F) -> RgbaImage
+    where F: Fn([f32; 3]) -> [f32; 3]
+{
+    let size = size.max(2);
+    let step = (size - 1) as f32;
+    let mut lut = RgbaImage::new(size * size, size);
+    for (x, y, pixel) in lut.enumerate_pixels_mut() {
+        let color = [(x % size) as f32 / step, y as f32 / step, (x / size) as f32 / step];
+        let graded = grade(color);
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        *pixel = Rgba([channel(graded[0]), channel(graded[1]), channel(graded[2]), 255]);
+    }
+    lut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the color a lut's pixel stands for, as identity_lut lays them out
+    fn color_at(size: u32, x: u32, y: u32) -> [u8; 3] {
+        let channel = |value: u32| (value as f32 / (size - 1) as f32 * 255.0).round() as u8;
+        [channel(x % size), channel(y), channel(x / size)]
+    }
+
+    #[test]
+    fn identity_lut_is_size_slices_of_size_by_size() {
+        let lut = identity_lut(16);
+        assert_eq!((lut.width(), lut.height()), (256, 16));
+    }
+
+    #[test]
+    fn identity_lut_holds_the_color_each_pixel_stands_for() {
+        let lut = identity_lut(4);
+        for (x, y, pixel) in lut.enumerate_pixels() {
+            let [r, g, b] = color_at(4, x, y);
+            assert_eq!(*pixel, Rgba([r, g, b, 255]), "at {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn identity_lut_covers_black_to_white() {
+        let lut = identity_lut(8);
+        assert_eq!(*lut.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+        assert_eq!(*lut.get_pixel(7, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*lut.get_pixel(0, 7), Rgba([0, 255, 0, 255]));
+        assert_eq!(*lut.get_pixel(56, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(*lut.get_pixel(63, 7), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn luts_are_at_least_two_colors_a_side() {
+        for &size in &[0, 1] {
+            let lut = identity_lut(size);
+            assert_eq!((lut.width(), lut.height()), (4, 2));
+            assert_eq!(*lut.get_pixel(3, 1), Rgba([255, 255, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn lut_from_fn_grades_every_color() {
+        let inverted = lut_from_fn(4, |color| [1.0 - color[0], 1.0 - color[1], 1.0 - color[2]]);
+        let identity = identity_lut(4);
+        for (x, y, pixel) in inverted.enumerate_pixels() {
+            let original = identity.get_pixel(x, y);
+            let expected = Rgba([255 - original[0], 255 - original[1], 255 - original[2], 255]);
+            assert_eq!(*pixel, expected, "at {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn lut_from_fn_passes_the_colors_in_red_green_blue_order() {
+        let swapped = lut_from_fn(4, |color| [color[2], color[0], color[1]]);
+        for (x, y, pixel) in swapped.enumerate_pixels() {
+            let [r, g, b] = color_at(4, x, y);
+            assert_eq!(*pixel, Rgba([b, r, g, 255]), "at {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn lut_from_fn_clamps_what_grade_returns() {
+        let lut = lut_from_fn(2, |_| [-1.0, 0.5, 2.0]);
+        for pixel in lut.pixels() {
+            assert_eq!(*pixel, Rgba([0, 128, 255, 255]));
+        }
+    }
+}
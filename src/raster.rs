@@ -0,0 +1,423 @@
+use batch::BatchVertex;
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use image::{Rgba, RgbaImage};
+use rect::Rect;
+use sprite::{self, TEX_INDICES, View, sprite_model, tex_quad};
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// the vertex the colored examples draw with, position in clip space and an rgb color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 3],
+}
+
+// what the rasterizer needs from a vertex: what its vertex shader would pass along
+pub trait RasterVertex {
+    fn position(&self) -> [f32; 2];
+
+    // multiplied with the texture sample, or used as is when nothing is textured
+    fn color(&self) -> [f32; 4] {
+        WHITE
+    }
+
+    // normalized, with the origin at the texture image's top left
+    fn tex_coord(&self) -> [f32; 2] {
+        [0.0, 0.0]
+    }
+}
+
+impl RasterVertex for ColorVertex {
+    fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    fn color(&self) -> [f32; 4] {
+        [self.color[0], self.color[1], self.color[2], 1.0]
+    }
+}
+
+impl RasterVertex for sprite::Vertex {
+    fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    // the sprite vertex shader flips y before sampling
+    fn tex_coord(&self) -> [f32; 2] {
+        [self.tex_coord[0], 1.0 - self.tex_coord[1]]
+    }
+}
+
+impl RasterVertex for BatchVertex {
+    fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    fn tex_coord(&self) -> [f32; 2] {
+        self.tex_coord
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    // overwrites the target, like a plain RenderTarget
+    Replace,
+    // gfx::preset::blend::ALPHA, as the sprite and batch pipelines use
+    Alpha,
+}
+
+// the vertex after the view has been applied, in target pixels with y pointing down
+#[derive(Clone, Copy)]
+struct Projected {
+    x: f32,
+    y: f32,
+    color: [f32; 4],
+    tex_coord: [f32; 2],
+}
+
+// a software stand in for the gfx pipelines, for places without a GPU such as CI. it draws
+// triangles the way the shaders in this crate do, so what it produces can be compared against
+// what's expected pixel by pixel.
+pub struct Rasterizer {
+    image: RgbaImage,
+    model: Matrix4<f32>,
+    projection: Matrix4<f32>,
+    blend: Blend,
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Rasterizer {
+            image: RgbaImage::new(width, height),
+            model: Matrix4::identity(),
+            projection: Matrix4::identity(),
+            blend: Blend::Replace,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    // the same view the sprite pipeline is given. until this is called positions are taken
+    // to be in clip space already, as the colored examples' are.
+    pub fn set_view(&mut self, view: &View) {
+        self.model = Matrix4::from(view.model);
+        self.projection = Matrix4::from(view.projection);
+    }
+
+    pub fn set_projection(&mut self, projection: Matrix4<f32>) {
+        self.projection = projection;
+    }
+
+    pub fn set_blend(&mut self, blend: Blend) {
+        self.blend = blend;
+    }
+
+    pub fn clear(&mut self, color: [f32; 4]) {
+        let pixel = to_pixel(color);
+        for texel in self.image.pixels_mut() {
+            *texel = pixel;
+        }
+    }
+
+    // every three vertices make a triangle, like a slice without an index buffer
+    pub fn draw<V>(&mut self, vertices: &[V], texture: Option<&RgbaImage>)
+        where V: RasterVertex
+    {
+        for triangle in vertices.chunks(3) {
+            if triangle.len() == 3 {
+                self.draw_triangle(&triangle[0], &triangle[1], &triangle[2], texture);
+            }
+        }
+    }
+
+    // indexed triangles, taking either the u16 indices the examples use or the batch's u32s
+    pub fn draw_indexed<V, I>(&mut self,
+                              vertices: &[V],
+                              indices: &[I],
+                              texture: Option<&RgbaImage>)
+        where V: RasterVertex,
+              I: Copy + Into<u32>
+    {
+        for triangle in indices.chunks(3) {
+            if triangle.len() == 3 {
+                let a = &vertices[triangle[0].into() as usize];
+                let b = &vertices[triangle[1].into() as usize];
+                let c = &vertices[triangle[2].into() as usize];
+                self.draw_triangle(a, b, c, texture);
+            }
+        }
+    }
+
+    // what SpriteRenderer::draw_sprite draws, given the same projection
+    pub fn draw_sprite(&mut self, texture: &RgbaImage, position: [f32; 2], size: [f32; 2]) {
+        self.draw_sprite_region(texture, Rect::unit(), position, size);
+    }
+
+    pub fn draw_sprite_region(&mut self,
+                              texture: &RgbaImage,
+                              uv: Rect,
+                              position: [f32; 2],
+                              size: [f32; 2]) {
+        let model = self.model;
+        let blend = self.blend;
+        self.model = sprite_model(position, size);
+        self.blend = Blend::Alpha;
+        self.draw_indexed(&tex_quad(uv), &TEX_INDICES, Some(texture));
+        self.model = model;
+        self.blend = blend;
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+
+    fn project<V: RasterVertex>(&self, vertex: &V) -> Projected {
+        let position = vertex.position();
+        let local = Vector4::new(position[0], position[1], 0.0, 1.0);
+        let clip = self.projection * self.model * local;
+        Projected {
+            x: (clip.x / clip.w + 1.0) / 2.0 * self.width() as f32,
+            y: (1.0 - clip.y / clip.w) / 2.0 * self.height() as f32,
+            color: vertex.color(),
+            tex_coord: vertex.tex_coord(),
+        }
+    }
+
+    fn draw_triangle<V>(&mut self, a: &V, b: &V, c: &V, texture: Option<&RgbaImage>)
+        where V: RasterVertex
+    {
+        let a = self.project(a);
+        let mut b = self.project(b);
+        let mut c = self.project(c);
+
+        // nothing is culled, so both windings are turned the same way round
+        let mut area = edge(&a, &b, c.x, c.y);
+        if area < 0.0 {
+            ::std::mem::swap(&mut b, &mut c);
+            area = -area;
+        }
+        if area == 0.0 {
+            return;
+        }
+
+        let width = self.width() as f32;
+        let height = self.height() as f32;
+        let min_x = a.x.min(b.x).min(c.x).max(0.0).floor() as u32;
+        let min_y = a.y.min(b.y).min(c.y).max(0.0).floor() as u32;
+        let max_x = a.x.max(b.x).max(c.x).min(width).ceil() as u32;
+        let max_y = a.y.max(b.y).max(c.y).min(height).ceil() as u32;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // pixels are covered when their centers are
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let weight_a = edge(&b, &c, px, py);
+                let weight_b = edge(&c, &a, px, py);
+                let weight_c = edge(&a, &b, px, py);
+                if !covers(weight_a, &b, &c) || !covers(weight_b, &c, &a) ||
+                   !covers(weight_c, &a, &b) {
+                    continue;
+                }
+
+                let weights = [weight_a / area, weight_b / area, weight_c / area];
+                let mut color = [0.0; 4];
+                for (channel, value) in color.iter_mut().enumerate() {
+                    *value = a.color[channel] * weights[0] +
+                             b.color[channel] * weights[1] +
+                             c.color[channel] * weights[2];
+                }
+                if let Some(texture) = texture {
+                    let u = a.tex_coord[0] * weights[0] + b.tex_coord[0] * weights[1] +
+                            c.tex_coord[0] * weights[2];
+                    let v = a.tex_coord[1] * weights[0] + b.tex_coord[1] * weights[1] +
+                            c.tex_coord[1] * weights[2];
+                    let sample = sample_linear(texture, u, v);
+                    for channel in 0..4 {
+                        color[channel] *= sample[channel];
+                    }
+                }
+
+                self.write(x, y, color);
+            }
+        }
+    }
+
+    fn write(&mut self, x: u32, y: u32, color: [f32; 4]) {
+        let color = match self.blend {
+            Blend::Replace => color,
+            Blend::Alpha => {
+                let target = from_pixel(self.image.get_pixel(x, y));
+                let alpha = color[3];
+                [color[0] * alpha + target[0] * (1.0 - alpha),
+                 color[1] * alpha + target[1] * (1.0 - alpha),
+                 color[2] * alpha + target[2] * (1.0 - alpha),
+                 alpha + target[3]]
+            }
+        };
+        self.image.put_pixel(x, y, to_pixel(color));
+    }
+}
+
+// twice the signed area of the triangle from, to, p. positive when p is to the right of the
+// edge in y down coordinates.
+fn edge(from: &Projected, to: &Projected, x: f32, y: f32) -> f32 {
+    (x - from.x) * (to.y - from.y) - (y - from.y) * (to.x - from.x)
+}
+
+// a pixel center exactly on an edge shared by two triangles belongs to only one of them, the
+// one that walks the edge in the direction picked out here, so it isn't blended twice
+fn covers(weight: f32, from: &Projected, to: &Projected) -> bool {
+    if weight != 0.0 {
+        return weight > 0.0;
+    }
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    dy > 0.0 || (dy == 0.0 && dx < 0.0)
+}
+
+// what create_sampler_linear does: bilinear filtering, clamped at the edges
+fn sample_linear(texture: &RgbaImage, u: f32, v: f32) -> [f32; 4] {
+    let (width, height) = texture.dimensions();
+    let x = u * width as f32 - 0.5;
+    let y = v * height as f32 - 0.5;
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let texel = |x: f32, y: f32| {
+        let x = x.max(0.0).min(width as f32 - 1.0) as u32;
+        let y = y.max(0.0).min(height as f32 - 1.0) as u32;
+        from_pixel(texture.get_pixel(x, y))
+    };
+    let top_left = texel(x0, y0);
+    let top_right = texel(x0 + 1.0, y0);
+    let bottom_left = texel(x0, y0 + 1.0);
+    let bottom_right = texel(x0 + 1.0, y0 + 1.0);
+
+    let mut color = [0.0; 4];
+    for channel in 0..4 {
+        let top = top_left[channel] + (top_right[channel] - top_left[channel]) * fx;
+        let bottom = bottom_left[channel] + (bottom_right[channel] - bottom_left[channel]) * fx;
+        color[channel] = top + (bottom - top) * fy;
+    }
+    color
+}
+
+fn from_pixel(pixel: &Rgba<u8>) -> [f32; 4] {
+    let data = pixel.0;
+    [data[0] as f32 / 255.0, data[1] as f32 / 255.0, data[2] as f32 / 255.0, data[3] as f32 / 255.0]
+}
+
+fn to_pixel(color: [f32; 4]) -> Rgba<u8> {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgba([channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(position: [f32; 2], color: [f32; 4]) -> BatchVertex {
+        BatchVertex {
+            position: position,
+            tex_coord: [0.0, 0.0],
+            color: color,
+        }
+    }
+
+    // two triangles covering all of clip space, split along the diagonal from the top left to
+    // the bottom right, which runs through the center of every pixel on a square target
+    fn split_square(color: [f32; 4], flip_second: bool) -> Vec<BatchVertex> {
+        let top_left = vertex([-1.0, 1.0], color);
+        let top_right = vertex([1.0, 1.0], color);
+        let bottom_left = vertex([-1.0, -1.0], color);
+        let bottom_right = vertex([1.0, -1.0], color);
+        if flip_second {
+            vec![top_left, top_right, bottom_right, bottom_left, top_left, bottom_right]
+        } else {
+            vec![top_left, top_right, bottom_right, top_left, bottom_right, bottom_left]
+        }
+    }
+
+    fn close(a: [f32; 4], b: [f32; 4]) -> bool {
+        (0..4).all(|channel| (a[channel] - b[channel]).abs() < 0.0001)
+    }
+
+    #[test]
+    fn shared_edges_are_only_drawn_once() {
+        for &flip_second in &[false, true] {
+            let mut target = Rasterizer::new(4, 4);
+            target.clear([0.0, 0.0, 0.0, 1.0]);
+            target.set_blend(Blend::Alpha);
+            target.draw(&split_square([1.0, 1.0, 1.0, 0.5], flip_second), None);
+            // covered twice, the diagonal would come out at 192 rather than 128
+            for (x, y, pixel) in target.image().enumerate_pixels() {
+                assert_eq!(pixel.0[0], 128, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn alpha_blends_over_what_is_there() {
+        let mut target = Rasterizer::new(2, 2);
+        target.clear([0.0, 0.8, 1.0, 1.0]);
+        target.set_blend(Blend::Alpha);
+        target.draw(&split_square([1.0, 0.0, 0.0, 0.25], false), None);
+        assert_eq!(target.image().get_pixel(0, 0).0, [64, 153, 191, 255]);
+        assert_eq!(target.image().get_pixel(1, 1).0, [64, 153, 191, 255]);
+    }
+
+    #[test]
+    fn replace_ignores_what_is_there() {
+        let mut target = Rasterizer::new(2, 2);
+        target.clear([0.0, 0.8, 1.0, 1.0]);
+        target.draw(&split_square([1.0, 0.0, 0.0, 0.25], false), None);
+        assert_eq!(target.image().get_pixel(0, 1).0, [255, 0, 0, 64]);
+    }
+
+    #[test]
+    fn linear_samples_are_clamped_to_the_edge_texels() {
+        let mut texture = RgbaImage::new(2, 2);
+        texture.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        texture.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+        texture.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        texture.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let green = [0.0, 1.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        let white = [1.0, 1.0, 1.0, 1.0];
+
+        // the corners and anything past them are the corner texels, not blended with the
+        // opposite side as wrapping would
+        assert!(close(sample_linear(&texture, 0.0, 0.0), red));
+        assert!(close(sample_linear(&texture, -3.0, -3.0), red));
+        assert!(close(sample_linear(&texture, 1.0, 0.0), green));
+        assert!(close(sample_linear(&texture, 0.0, 1.0), blue));
+        assert!(close(sample_linear(&texture, 1.0, 1.0), white));
+        assert!(close(sample_linear(&texture, 4.0, 2.0), white));
+
+        // along an edge only the two texels on it are blended
+        assert!(close(sample_linear(&texture, 0.5, 0.0), [0.5, 0.5, 0.0, 1.0]));
+        assert!(close(sample_linear(&texture, 0.5, -1.0), [0.5, 0.5, 0.0, 1.0]));
+        assert!(close(sample_linear(&texture, 0.5, 0.5), [0.5, 0.5, 0.5, 1.0]));
+        // texel centers are exact
+        assert!(close(sample_linear(&texture, 0.75, 0.25), green));
+    }
+}
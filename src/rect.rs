@@ -0,0 +1,32 @@
+// an axis aligned rectangle, used both for pixel regions and normalized texture coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        }
+    }
+
+    // the whole of a texture in normalized coordinates
+    pub fn unit() -> Self {
+        Rect::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+}
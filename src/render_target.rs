@@ -0,0 +1,105 @@
+use cgmath;
+use gfx;
+use gfx::format::{ChannelTyped, Formatted, Swizzle};
+use gfx::handle::{RenderTargetView, ShaderResourceView, Texture};
+use gfx::memory::Bind;
+use gfx::texture;
+use rect::Rect;
+use sprite::{full_scissor, pixel_projection};
+use ColorFormat;
+
+type Surface = <ColorFormat as Formatted>::Surface;
+
+// a color texture that can be drawn into like the window and then drawn like any other texture,
+// for things such as minimaps, caching ui that rarely changes, or post-processing
+pub struct RenderTarget2D<R: gfx::Resources> {
+    texture: Texture<R, Surface>,
+    view: ShaderResourceView<R, [f32; 4]>,
+    target: RenderTargetView<R, ColorFormat>,
+    width: u32,
+    height: u32,
+}
+
+impl<R: gfx::Resources> RenderTarget2D<R> {
+    pub fn new<F>(factory: &mut F, width: u32, height: u32) -> Result<Self, String>
+        where F: gfx::Factory<R>
+    {
+        let width = width.max(1);
+        let height = height.max(1);
+        let kind = texture::Kind::D2(width as texture::Size,
+                                     height as texture::Size,
+                                     texture::AaMode::Single);
+        let channel = <<ColorFormat as Formatted>::Channel as ChannelTyped>::get_channel_type();
+        // like factory.create_render_target, but able to be copied from so it can be read back
+        let bind = Bind::SHADER_RESOURCE | Bind::RENDER_TARGET | Bind::TRANSFER_SRC;
+        let texture = factory.create_texture(kind, 1, bind, gfx::memory::Usage::Data, Some(channel))
+            .map_err(|e| format!("{:?}", e))?;
+        let view = factory.view_texture_as_shader_resource::<ColorFormat>(&texture,
+                                                                         (0, 0),
+                                                                         Swizzle::new())
+            .map_err(|e| format!("{:?}", e))?;
+        let target = factory.view_texture_as_render_target(&texture, 0, None)
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(RenderTarget2D {
+            texture: texture,
+            view: view,
+            target: target,
+            width: width,
+            height: height,
+        })
+    }
+
+    // replaces the texture with one of a new size. anything drawn so far is lost, and renderers
+    // or batches holding on to the old views have to be given the new ones.
+    pub fn resize<F>(&mut self, factory: &mut F, width: u32, height: u32) -> Result<(), String>
+        where F: gfx::Factory<R>
+    {
+        if width.max(1) != self.width || height.max(1) != self.height {
+            *self = RenderTarget2D::new(factory, width, height)?;
+        }
+        Ok(())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // what to hand a pipeline's out, or a renderer's set_target, to draw into this
+    pub fn target(&self) -> &RenderTargetView<R, ColorFormat> {
+        &self.target
+    }
+
+    // what to sample to draw the contents, like a texture from load_texture
+    pub fn texture(&self) -> &ShaderResourceView<R, [f32; 4]> {
+        &self.view
+    }
+
+    pub fn raw_texture(&self) -> &Texture<R, Surface> {
+        &self.texture
+    }
+
+    // a pixel space projection covering the whole target, origin at the bottom left
+    pub fn projection(&self) -> cgmath::Matrix4<f32> {
+        pixel_projection(self.width as f32, self.height as f32)
+    }
+
+    pub fn scissor(&self) -> gfx::Rect {
+        full_scissor(self.width as f32, self.height as f32)
+    }
+
+    // gl keeps what's drawn bottom row first while loaded images are top row first, so the
+    // contents come out upside down unless drawn with this region instead of Rect::unit
+    pub fn uv(&self) -> Rect {
+        Rect::new(0.0, 1.0, 1.0, -1.0)
+    }
+
+    pub fn clear<C>(&self, encoder: &mut gfx::Encoder<R, C>, color: [f32; 4])
+        where C: gfx::CommandBuffer<R>
+    {
+        encoder.clear(&self.target, color);
+    }
+}
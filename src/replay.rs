@@ -0,0 +1,493 @@
+use bindings::{binding_from_name, binding_name};
+use glutin::dpi::LogicalPosition;
+use glutin::{ElementState, Event, MouseScrollDelta, WindowEvent};
+use input::{Binding, InputMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const HEADER: &'static str = "# learn_gfx_2d input recording v2";
+
+// the part of a glutin event InputMap looks at, in a form that can be written to a file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    Pressed(Binding),
+    Released(Binding),
+    // in logical pixels, as glutin reports it, so a replay on a screen with another hidpi factor
+    // puts the cursor in the same place in the window
+    MouseMoved(f32, f32),
+    // in lines
+    Wheel(f32, f32),
+    // in pixels
+    PixelWheel(f32, f32),
+    MouseLeft,
+    FocusLost,
+}
+
+impl InputEvent {
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match *event {
+            Event::WindowEvent { ref event, .. } => InputEvent::from_window_event(event),
+            _ => None,
+        }
+    }
+
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                input.virtual_keycode.map(|key| match input.state {
+                    ElementState::Pressed => InputEvent::Pressed(Binding::Key(key)),
+                    ElementState::Released => InputEvent::Released(Binding::Key(key)),
+                })
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => {
+                Some(InputEvent::Pressed(Binding::Mouse(button)))
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
+                Some(InputEvent::Released(Binding::Mouse(button)))
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                Some(InputEvent::MouseMoved(position.x as f32, position.y as f32))
+            }
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(x, y), .. } => {
+                Some(InputEvent::Wheel(x, y))
+            }
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::PixelDelta(position), .. } => {
+                Some(InputEvent::PixelWheel(position.x as f32, position.y as f32))
+            }
+            WindowEvent::CursorLeft { .. } => Some(InputEvent::MouseLeft),
+            WindowEvent::Focused(false) => Some(InputEvent::FocusLost),
+            _ => None,
+        }
+    }
+
+    // hands the event to an InputMap the way handle_event would have
+    pub fn apply(&self, input: &mut InputMap) {
+        match *self {
+            InputEvent::Pressed(binding) => input.press(binding),
+            InputEvent::Released(binding) => input.release(binding),
+            InputEvent::MouseMoved(x, y) => {
+                let position = LogicalPosition::new(x as f64, y as f64);
+                input.mouse_mut().moved_logical(position)
+            }
+            InputEvent::Wheel(x, y) => {
+                input.mouse_mut().scrolled(MouseScrollDelta::LineDelta(x, y))
+            }
+            InputEvent::PixelWheel(x, y) => {
+                let position = LogicalPosition::new(x as f64, y as f64);
+                input.mouse_mut().scrolled(MouseScrollDelta::PixelDelta(position))
+            }
+            InputEvent::MouseLeft => input.mouse_mut().left_window(),
+            InputEvent::FocusLost => input.release_all(),
+        }
+    }
+
+    fn write_to(&self, frame: u64, out: &mut String) {
+        let line = match *self {
+            InputEvent::Pressed(binding) => format!("{} p {}", frame, binding_name(binding)),
+            InputEvent::Released(binding) => format!("{} r {}", frame, binding_name(binding)),
+            InputEvent::MouseMoved(x, y) => format!("{} m {} {}", frame, x, y),
+            InputEvent::Wheel(x, y) => format!("{} w {} {}", frame, x, y),
+            InputEvent::PixelWheel(x, y) => format!("{} pw {} {}", frame, x, y),
+            InputEvent::MouseLeft => format!("{} leave", frame),
+            InputEvent::FocusLost => format!("{} blur", frame),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    // the line number, counting from one, and what was wrong with it
+    Parse(usize, String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io(ref err) => write!(f, "{}", err),
+            ReplayError::Parse(line, ref message) => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+// every input event of a session along with the frame it arrived on. it is written as text,
+// one event per line, so recordings stay small and can be read in a bug report.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub events: Vec<(u64, InputEvent)>,
+    // how many fixed updates each frame ran, by frame number, so a replay runs the same ones
+    // however fast it is drawn. frames without one ran a single update.
+    pub updates: Vec<u32>,
+    // the number of the session's last frame, so quiet frames at the end are replayed too
+    pub frames: u64,
+}
+
+impl Recording {
+    pub fn updates(&self, frame: u64) -> u32 {
+        self.updates.get(frame as usize).cloned().unwrap_or(1)
+    }
+
+    // a frame's update count is only written when it differs from the frame before's
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(HEADER);
+        out.push('\n');
+        let mut events = self.events.iter().peekable();
+        let mut updates = 1;
+        for frame in 0..self.frames + 1 {
+            if self.updates(frame) != updates {
+                updates = self.updates(frame);
+                out.push_str(&format!("{} u {}\n", frame, updates));
+            }
+            while let Some((_, event)) = events.next_if(|&&(at, _)| at == frame) {
+                event.write_to(frame, &mut out);
+            }
+        }
+        out.push_str(&format!("{} end\n", self.frames));
+        out
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, ReplayError> {
+        let mut recording = Recording::default();
+        // the count carried on to the frames after the last u line
+        let mut updates = 1;
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let number = index + 1;
+            let error = |message: &str| ReplayError::Parse(number, message.to_string());
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let frame: u64 = parts[0].parse().map_err(|_| error("expected a frame number"))?;
+            if frame < recording.frames {
+                return Err(error("frames are out of order"));
+            }
+            recording.frames = frame;
+
+            let binding = |name: Option<&&str>| {
+                name.and_then(|name| binding_from_name(name))
+                    .ok_or_else(|| error("unknown binding"))
+            };
+            let pair = |x: Option<&&str>, y: Option<&&str>| -> Result<(f32, f32), ReplayError> {
+                match (x.and_then(|x| x.parse().ok()), y.and_then(|y| y.parse().ok())) {
+                    (Some(x), Some(y)) => Ok((x, y)),
+                    _ => Err(error("expected two numbers")),
+                }
+            };
+
+            let event = match parts.get(1).cloned() {
+                Some("p") => InputEvent::Pressed(binding(parts.get(2))?),
+                Some("r") => InputEvent::Released(binding(parts.get(2))?),
+                Some("m") => {
+                    let (x, y) = pair(parts.get(2), parts.get(3))?;
+                    InputEvent::MouseMoved(x, y)
+                }
+                Some("w") => {
+                    let (x, y) = pair(parts.get(2), parts.get(3))?;
+                    InputEvent::Wheel(x, y)
+                }
+                Some("pw") => {
+                    let (x, y) = pair(parts.get(2), parts.get(3))?;
+                    InputEvent::PixelWheel(x, y)
+                }
+                Some("leave") => InputEvent::MouseLeft,
+                Some("blur") => InputEvent::FocusLost,
+                Some("u") => {
+                    recording.updates.resize(frame as usize, updates);
+                    updates = parts.get(2)
+                        .and_then(|count| count.parse().ok())
+                        .ok_or_else(|| error("expected an update count"))?;
+                    continue;
+                }
+                Some("end") => break,
+                _ => return Err(error("unknown event")),
+            };
+            recording.events.push((frame, event));
+        }
+        recording.updates.resize(recording.frames as usize + 1, updates);
+
+        Ok(recording)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_text().as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        Recording::from_text(&text)
+    }
+}
+
+// captures events as they are handed to an InputMap. call begin_frame alongside the input
+// map's, then record each event and how many updates the frame ran.
+pub struct Recorder {
+    recording: Recording,
+    started: bool,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder {
+            recording: Recording::default(),
+            started: false,
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        if self.started {
+            self.recording.frames += 1;
+        }
+        self.started = true;
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if let Some(event) = InputEvent::from_event(event) {
+            self.recording.events.push((self.recording.frames, event));
+        }
+    }
+
+    pub fn record_updates(&mut self, updates: u32) {
+        let frame = self.recording.frames as usize;
+        self.recording.updates.resize(frame, 1);
+        self.recording.updates.push(updates);
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn finish(self) -> Recording {
+        self.recording
+    }
+}
+
+// plays a recording back into an InputMap one frame at a time, no window needed
+pub struct Replayer {
+    recording: Recording,
+    next_event: usize,
+    frame: u64,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording) -> Self {
+        Replayer {
+            recording: recording,
+            next_event: 0,
+            frame: 0,
+        }
+    }
+
+    // stands in for the input map's begin_frame and the frame's events. returns false once
+    // the recording has run out of frames, though the input map still begins a new frame so
+    // nothing stays just pressed.
+    pub fn next_frame(&mut self, input: &mut InputMap) -> bool {
+        input.begin_frame();
+        if self.is_finished() {
+            return false;
+        }
+
+        while let Some(&(frame, ref event)) = self.recording.events.get(self.next_event) {
+            if frame != self.frame {
+                break;
+            }
+            event.apply(input);
+            self.next_event += 1;
+        }
+
+        self.frame += 1;
+        true
+    }
+
+    // how many updates the frame next_frame last played ran when it was recorded
+    pub fn updates(&self) -> u32 {
+        self.recording.updates(self.frame.saturating_sub(1))
+    }
+
+    // the frame the next call to next_frame plays
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame > self.recording.frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::ManualClock;
+    use game_loop::GameLoop;
+    use glutin::{DeviceId, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode, WindowId};
+
+    const UPDATES_PER_SECOND: f64 = 60.0;
+
+    fn window_event(event: WindowEvent) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: event,
+        }
+    }
+
+    fn key(state: ElementState, key: VirtualKeyCode) -> Event {
+        window_event(WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state: state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::default(),
+            },
+        })
+    }
+
+    fn click(state: ElementState) -> Event {
+        window_event(WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        })
+    }
+
+    fn cursor(x: f64, y: f64) -> Event {
+        window_event(WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: LogicalPosition::new(x, y),
+            modifiers: ModifiersState::default(),
+        })
+    }
+
+    fn input_map() -> InputMap {
+        let mut input = InputMap::new();
+        input.bind("left", Binding::Key(VirtualKeyCode::Left));
+        input.bind("right", Binding::Key(VirtualKeyCode::Right));
+        input.bind("fire", Binding::Mouse(MouseButton::Left));
+        input.bind_axis("horizontal", "left", "right");
+        input
+    }
+
+    // what a game would have worked out by the end of a frame
+    #[derive(Debug, PartialEq)]
+    struct FrameState {
+        position: f32,
+        fired: bool,
+        firing: bool,
+        cursor: [f32; 2],
+    }
+
+    fn update(input: &InputMap, position: &mut f32, updates: u32) -> FrameState {
+        for _ in 0..updates {
+            *position += input.axis_value("horizontal") * 100.0 / UPDATES_PER_SECOND as f32;
+        }
+        FrameState {
+            position: *position,
+            fired: input.just_pressed("fire"),
+            firing: input.is_pressed("fire"),
+            cursor: input.mouse().position(),
+        }
+    }
+
+    #[test]
+    fn replay_matches_the_recorded_session() {
+        // frames of uneven length, so some run no updates and some several, with the events
+        // that arrived during them
+        let session = vec![(0.0, vec![]),
+                           (0.016, vec![key(ElementState::Pressed, VirtualKeyCode::Right)]),
+                           (0.004, vec![cursor(10.0, 20.0)]),
+                           (0.040, vec![click(ElementState::Pressed), cursor(12.5, 21.0)]),
+                           (0.017, vec![click(ElementState::Released)]),
+                           (0.300, vec![key(ElementState::Released, VirtualKeyCode::Right),
+                                        key(ElementState::Pressed, VirtualKeyCode::Left)]),
+                           (0.016, vec![]),
+                           (0.033, vec![key(ElementState::Released, VirtualKeyCode::Left)]),
+                           (0.016, vec![])];
+
+        let clock = ManualClock::new();
+        let mut game_loop = GameLoop::new(clock.clone(), UPDATES_PER_SECOND);
+        let mut input = input_map();
+        let mut recorder = Recorder::new();
+        let mut position = 0.0;
+        let mut recorded = Vec::new();
+        for &(seconds, ref events) in &session {
+            recorder.begin_frame();
+            input.begin_frame();
+            for event in events {
+                recorder.record(event);
+                input.handle_event(event);
+            }
+            clock.advance(seconds);
+            let updates = game_loop.step().updates;
+            recorder.record_updates(updates);
+            recorded.push(update(&input, &mut position, updates));
+        }
+        assert!(recorded.iter().any(|state| state.fired));
+        assert!(recorded.last().unwrap().position < recorded[4].position);
+
+        let text = recorder.finish().to_text();
+        let mut replayer = Replayer::new(Recording::from_text(&text).unwrap());
+        let mut input = input_map();
+        let mut position = 0.0;
+        let mut replayed = Vec::new();
+        while replayer.next_frame(&mut input) {
+            let updates = replayer.updates();
+            replayed.push(update(&input, &mut position, updates));
+        }
+        assert_eq!(replayed, recorded);
+    }
+
+    #[test]
+    fn finished_replay_still_begins_frames() {
+        let mut recording = Recording::default();
+        recording.events.push((0, InputEvent::Pressed(Binding::Mouse(MouseButton::Left))));
+        let mut replayer = Replayer::new(recording);
+        let mut input = input_map();
+
+        assert!(replayer.next_frame(&mut input));
+        assert!(input.just_pressed("fire"));
+        assert!(!replayer.next_frame(&mut input));
+        assert!(!input.just_pressed("fire"));
+        assert!(input.is_pressed("fire"));
+    }
+
+    #[test]
+    fn update_counts_survive_the_text() {
+        let mut recorder = Recorder::new();
+        for &updates in &[0, 1, 1, 2, 0, 1, 3, 3] {
+            recorder.begin_frame();
+            recorder.record_updates(updates);
+        }
+        let recording = recorder.finish();
+        assert_eq!(recording.updates, vec![0, 1, 1, 2, 0, 1, 3, 3]);
+
+        let text = recording.to_text();
+        assert_eq!(text.lines().filter(|line| line.contains(" u ")).count(), 6);
+        assert_eq!(Recording::from_text(&text).unwrap(), recording);
+    }
+}
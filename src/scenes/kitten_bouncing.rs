@@ -0,0 +1,11 @@
+use scenes::{HEIGHT, WIDTH};
+
+pub const CLEAR_COLOR: [f32; 4] = [0.59, 0.93, 0.59, 1.0];
+
+pub const SPRITE_SIZE: [f32; 2] = [266.0, 266.0];
+
+// bounces between the bottom of the window and the top, landing every pi seconds
+pub fn kitten_position(seconds: f32) -> [f32; 2] {
+    let y_offset = seconds.sin().abs();
+    [WIDTH as f32 / 2.0, SPRITE_SIZE[1] / 2.0 + (HEIGHT as f32 - SPRITE_SIZE[1]) * y_offset]
+}
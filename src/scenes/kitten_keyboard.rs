@@ -0,0 +1,10 @@
+use scenes::{HEIGHT, WIDTH};
+
+pub const CLEAR_COLOR: [f32; 4] = [0.59, 0.93, 0.59, 1.0];
+
+pub const SPRITE_SIZE: [f32; 2] = [266.0, 266.0];
+
+// the kitten starts out in the middle of the window
+pub fn start_position() -> [f32; 2] {
+    [WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0]
+}
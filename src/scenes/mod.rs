@@ -0,0 +1,13 @@
+// what each example draws, kept in the library so tests/golden.rs can draw the same frames
+// without a copy of its own. the examples copy these into their own vertex types.
+pub mod kitten_bouncing;
+pub mod kitten_keyboard;
+pub mod square_elements;
+pub mod square_interpolated;
+pub mod triangle_basic;
+pub mod triangle_flashing;
+pub mod triangle_interpolated;
+
+// every example opens a window this size
+pub const WIDTH: u32 = 640;
+pub const HEIGHT: u32 = 480;
@@ -0,0 +1,28 @@
+use raster::ColorVertex;
+
+pub const CLEAR_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
+
+// because we will specify the indices, we only need to define four vertices here
+pub fn square_vertices() -> [ColorVertex; 4] {
+    [ColorVertex {
+         position: [0.5, 0.5],
+         color: [1.0, 1.0, 0.0],
+     },
+     ColorVertex {
+         position: [0.5, -0.5],
+         color: [0.0, 1.0, 0.0],
+     },
+     ColorVertex {
+         position: [-0.5, -0.5],
+         color: [1.0, 0.0, 0.0],
+     },
+     ColorVertex {
+         position: [-0.5, 0.5],
+         color: [0.0, 0.0, 1.0],
+     }]
+}
+
+// here we define the two triangles by saying which indices to use in what order
+pub fn square_indices() -> [u16; 6] {
+    [0, 3, 1, 1, 3, 2]
+}
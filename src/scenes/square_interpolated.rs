@@ -0,0 +1,33 @@
+use raster::ColorVertex;
+
+pub const CLEAR_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
+
+// here we define our square in terms of two triangles
+pub fn square() -> [ColorVertex; 6] {
+    [// these first three vertices represent our first triangle
+     ColorVertex {
+         position: [-0.5, -0.5],
+         color: [1.0, 0.0, 0.0],
+     },
+     ColorVertex {
+         position: [0.5, -0.5],
+         color: [0.0, 1.0, 0.0],
+     },
+     ColorVertex {
+         position: [-0.5, 0.5],
+         color: [0.0, 0.0, 1.0],
+     },
+     // these are the second triangle
+     ColorVertex {
+         position: [-0.5, 0.5],
+         color: [0.0, 0.0, 1.0],
+     },
+     ColorVertex {
+         position: [0.5, 0.5],
+         color: [1.0, 1.0, 0.0],
+     },
+     ColorVertex {
+         position: [0.5, -0.5],
+         color: [0.0, 1.0, 0.0],
+     }]
+}
@@ -0,0 +1,22 @@
+use raster::ColorVertex;
+
+pub const ORANGE: [f32; 3] = [1.0, 0.5, 0.0];
+pub const CLEAR_COLOR: [f32; 4] = [0.8, 1.0, 0.6, 1.0];
+
+// here we define our triangle object in terms of points and colors
+// note that the position is using 'normalized device coordinates',
+// running from -1.0 to 1.0 in both the x and y axis
+pub fn triangle() -> [ColorVertex; 3] {
+    [ColorVertex {
+         position: [-0.5, -0.5],
+         color: ORANGE,
+     },
+     ColorVertex {
+         position: [0.5, -0.5],
+         color: ORANGE,
+     },
+     ColorVertex {
+         position: [0.0, 0.5],
+         color: ORANGE,
+     }]
+}
@@ -0,0 +1,12 @@
+pub const CLEAR_COLOR: [f32; 4] = [0.7, 0.85, 0.7, 1.0];
+
+// only positions, the color comes from a uniform shared by every vertex
+pub fn triangle() -> [[f32; 2]; 3] {
+    [[-0.5, -0.5], [0.5, -0.5], [0.0, 0.5]]
+}
+
+// fades between black and white as time goes on
+pub fn color(seconds: f32) -> [f32; 3] {
+    let value = seconds.sin().abs();
+    [value, value, value]
+}
@@ -0,0 +1,19 @@
+use raster::ColorVertex;
+
+pub const CLEAR_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
+
+// each corner gets its own color, which is blended across the triangle between them
+pub fn triangle() -> [ColorVertex; 3] {
+    [ColorVertex {
+         position: [-0.5, -0.5],
+         color: [1.0, 0.0, 0.0],
+     },
+     ColorVertex {
+         position: [0.5, -0.5],
+         color: [0.0, 1.0, 0.0],
+     },
+     ColorVertex {
+         position: [0.0, 0.5],
+         color: [0.0, 0.0, 1.0],
+     }]
+}
@@ -1,11 +1,12 @@
 #version 150 core
 
 in vec2 our_texture_coord;
+in vec4 our_color;
 
 out vec4 color;
 
 uniform sampler2D texture_sampler;
 
 void main() {
-  color = texture(texture_sampler, our_texture_coord);
+  color = texture(texture_sampler, our_texture_coord) * our_color;
 }
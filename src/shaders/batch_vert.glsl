@@ -0,0 +1,18 @@
+#version 150 core
+
+in vec2 position;
+in vec2 texture_coord;
+in vec4 color;
+
+uniform View {
+  mat4 projection;
+};
+
+out vec2 our_texture_coord;
+out vec4 our_color;
+
+void main() {
+  gl_Position = projection * vec4(position, 0.0, 1.0);
+  our_texture_coord = texture_coord;
+  our_color = color;
+}
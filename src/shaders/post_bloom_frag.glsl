@@ -0,0 +1,17 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+uniform sampler2D glow;
+
+uniform Params {
+  float intensity;
+};
+
+void main() {
+  vec4 scene = texture(source, our_texture_coord);
+  color = vec4(scene.rgb + texture(glow, our_texture_coord).rgb * intensity, scene.a);
+}
@@ -0,0 +1,23 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+uniform Params {
+  // the distance between samples in texture coordinates, along x or y but never both
+  vec2 offset;
+};
+
+void main() {
+  vec3 blurred = vec3(0.0);
+  float total = 0.0;
+  for (int i = -4; i <= 4; i++) {
+    float weight = exp(-float(i * i) / 8.0);
+    blurred += texture(source, our_texture_coord + offset * float(i)).rgb * weight;
+    total += weight;
+  }
+  color = vec4(blurred / total, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+uniform Params {
+  float threshold;
+};
+
+void main() {
+  vec3 scene = texture(source, our_texture_coord).rgb;
+
+  // keeps only the part of each color brighter than the threshold, for the blur passes to spread
+  float brightness = max(scene.r, max(scene.g, scene.b));
+  float bright_part = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+  color = vec4(scene * bright_part, 1.0);
+}
@@ -0,0 +1,34 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+// size slices of size x size texels laid out left to right. red runs across each slice,
+// green down it, and blue picks the slice.
+uniform sampler2D lut;
+
+uniform Params {
+  float size;
+  float amount;
+};
+
+vec3 lookup(vec3 scene, float slice) {
+  vec2 texel = vec2(1.0 / (size * size), 1.0 / size);
+  vec2 within = (scene.rg * (size - 1.0) + 0.5) * texel;
+  return texture(lut, within + vec2(slice * size * texel.x, 0.0)).rgb;
+}
+
+void main() {
+  vec4 scene = texture(source, our_texture_coord);
+  vec3 clamped = clamp(scene.rgb, 0.0, 1.0);
+
+  // blue falls between two slices, so both are looked up and blended
+  float blue = clamped.b * (size - 1.0);
+  float lower = floor(blue);
+  float upper = min(lower + 1.0, size - 1.0);
+  vec3 graded = mix(lookup(clamped, lower), lookup(clamped, upper), blue - lower);
+
+  color = vec4(mix(scene.rgb, graded, amount), scene.a);
+}
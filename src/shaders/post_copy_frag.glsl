@@ -0,0 +1,11 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+void main() {
+  color = texture(source, our_texture_coord);
+}
@@ -0,0 +1,17 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+uniform Params {
+  float amount;
+};
+
+void main() {
+  vec4 scene = texture(source, our_texture_coord);
+  float luma = dot(scene.rgb, vec3(0.299, 0.587, 0.114));
+  color = vec4(mix(scene.rgb, vec3(luma), amount), scene.a);
+}
@@ -0,0 +1,29 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+uniform Params {
+  vec2 resolution;
+  float intensity;
+  float curvature;
+};
+
+void main() {
+  // bulge the picture out like the glass of an old screen
+  vec2 centered = our_texture_coord * 2.0 - 1.0;
+  centered += centered * centered.yx * centered.yx * curvature;
+  vec2 uv = centered * 0.5 + 0.5;
+  if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+    color = vec4(0.0, 0.0, 0.0, 1.0);
+    return;
+  }
+
+  vec4 scene = texture(source, uv);
+  // darkens every other row of pixels
+  float line = sin(uv.y * resolution.y * 3.14159) * 0.5 + 0.5;
+  color = vec4(scene.rgb * (1.0 - intensity * (1.0 - line)), scene.a);
+}
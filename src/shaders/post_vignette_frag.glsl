@@ -0,0 +1,21 @@
+#version 150 core
+
+in vec2 our_texture_coord;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+uniform Params {
+  float radius;
+  float softness;
+  float strength;
+};
+
+void main() {
+  vec4 scene = texture(source, our_texture_coord);
+  // 0 in the middle of the screen and 1 in the corners
+  float from_center = length(our_texture_coord - vec2(0.5)) * 1.41421;
+  float shade = 1.0 - smoothstep(radius - softness, radius, from_center);
+  color = vec4(scene.rgb * mix(1.0, shade, strength), scene.a);
+}
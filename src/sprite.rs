@@ -0,0 +1,234 @@
+use cgmath;
+use gfx;
+use gfx::handle::{RenderTargetView, ShaderResourceView};
+use gfx::traits::FactoryExt;
+use rect::Rect;
+use render_target::RenderTarget2D;
+use texture::blank_texture;
+use viewport::Viewport;
+use ColorFormat;
+
+gfx_defines!{
+    constant View {
+        model: [[f32; 4]; 4] = "model",
+        projection: [[f32; 4]; 4] = "projection",
+    }
+
+    vertex Vertex {
+        position: [f32; 2] = "position",
+        tex_coord: [f32; 2] = "texture_coord",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        view: gfx::ConstantBuffer<View> = "View",
+        texture_sampler: gfx::TextureSampler<[f32; 4]> = "texture_sampler",
+        out: gfx::BlendTarget<ColorFormat> = ("color", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+        scissor: gfx::Scissor = (),
+    }
+}
+
+// each texture quad's position and what point on the texture to tie it to
+pub const TEX_QUAD: [Vertex; 4] = [Vertex {
+                                       position: [0.5, 0.5],
+                                       tex_coord: [1.0, 1.0],
+                                   },
+                                   Vertex {
+                                       position: [0.5, -0.5],
+                                       tex_coord: [1.0, 0.0],
+                                   },
+                                   Vertex {
+                                       position: [-0.5, -0.5],
+                                       tex_coord: [0.0, 0.0],
+                                   },
+                                   Vertex {
+                                       position: [-0.5, 0.5],
+                                       tex_coord: [0.0, 1.0],
+                                   }];
+
+// the indices used by the element buffer
+pub const TEX_INDICES: [u16; 6] = [0, 3, 1, 1, 3, 2];
+
+pub const NEAR_PLANE: f32 = -1.0;
+pub const FAR_PLANE: f32 = 10.0;
+
+// a pixel space orthographic projection with the origin at the bottom left
+pub fn pixel_projection(width: f32, height: f32) -> cgmath::Matrix4<f32> {
+    cgmath::ortho(0.0, width, 0.0, height, NEAR_PLANE, FAR_PLANE)
+}
+
+// moves and scales the unit quad so it is centered on position and covers size
+pub fn sprite_model(position: [f32; 2], size: [f32; 2]) -> cgmath::Matrix4<f32> {
+    let translation =
+        cgmath::Matrix4::from_translation(cgmath::Vector3::new(position[0], position[1], 0.0));
+    translation * cgmath::Matrix4::from_nonuniform_scale(size[0], size[1], 1.0)
+}
+
+// a scissor covering a whole target of the given size, so nothing is cut off
+pub fn full_scissor(width: f32, height: f32) -> gfx::Rect {
+    gfx::Rect {
+        x: 0,
+        y: 0,
+        w: width as u16,
+        h: height as u16,
+    }
+}
+
+pub fn viewport_scissor(viewport: &Viewport) -> gfx::Rect {
+    gfx::Rect {
+        x: viewport.x as u16,
+        y: viewport.y as u16,
+        w: viewport.width as u16,
+        h: viewport.height as u16,
+    }
+}
+
+// TEX_QUAD showing only part of its texture. the region is normalized with the origin at the
+// image's top left, while tex_coord counts up from the bottom because the vertex shader flips it.
+pub fn tex_quad(uv: Rect) -> [Vertex; 4] {
+    let top = 1.0 - uv.y;
+    let bottom = 1.0 - uv.bottom();
+    [Vertex {
+         position: TEX_QUAD[0].position,
+         tex_coord: [uv.right(), top],
+     },
+     Vertex {
+         position: TEX_QUAD[1].position,
+         tex_coord: [uv.right(), bottom],
+     },
+     Vertex {
+         position: TEX_QUAD[2].position,
+         tex_coord: [uv.x, bottom],
+     },
+     Vertex {
+         position: TEX_QUAD[3].position,
+         tex_coord: [uv.x, top],
+     }]
+}
+
+// owns everything needed to draw textured quads: the pipeline, the unit quad, the sampler
+// and the view constant buffer. each sprite drawn is the unit quad scaled to its size and
+// moved so that its center sits on its position.
+pub struct SpriteRenderer<R: gfx::Resources> {
+    pso: gfx::PipelineState<R, pipe::Meta>,
+    slice: gfx::Slice<R>,
+    data: pipe::Data<R>,
+    view: View,
+    // the region the quad in the vertex buffer currently shows
+    uv: Option<Rect>,
+}
+
+impl<R: gfx::Resources> SpriteRenderer<R> {
+    pub fn new<F>(factory: &mut F,
+                  out: RenderTargetView<R, ColorFormat>,
+                  width: f32,
+                  height: f32)
+                  -> Self
+        where F: gfx::Factory<R>
+    {
+        let pso = factory.create_pipeline_simple(include_bytes!("shaders/sprite_vert.glsl"),
+                                    include_bytes!("shaders/sprite_frag.glsl"),
+                                    pipe::new())
+            .unwrap();
+
+        // the quad's texture coordinates change with the region drawn, so it's kept dynamic
+        let vertex_buffer = factory.create_buffer(TEX_QUAD.len(),
+                           gfx::buffer::Role::Vertex,
+                           gfx::memory::Usage::Dynamic,
+                           gfx::memory::Bind::empty())
+            .unwrap();
+        let slice = gfx::Slice {
+            start: 0,
+            end: TEX_INDICES.len() as u32,
+            base_vertex: 0,
+            instances: None,
+            buffer: factory.create_index_buffer(&TEX_INDICES[..]),
+        };
+
+        // bound until the first sprite is drawn, so the pipeline data is always complete
+        let blank = blank_texture(factory);
+        let sampler = factory.create_sampler_linear();
+
+        let data = pipe::Data {
+            vbuf: vertex_buffer,
+            view: factory.create_constant_buffer(1),
+            texture_sampler: (blank, sampler),
+            out: out,
+            scissor: full_scissor(width, height),
+        };
+
+        SpriteRenderer {
+            pso: pso,
+            slice: slice,
+            data: data,
+            view: View {
+                model: cgmath::Matrix4::from_scale(1.0).into(),
+                projection: pixel_projection(width, height).into(),
+            },
+            uv: None,
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: cgmath::Matrix4<f32>) {
+        self.view.projection = projection.into();
+    }
+
+    // limits drawing to the viewport's part of the window and shows its world area
+    pub fn set_viewport(&mut self, viewport: &Viewport) {
+        self.set_projection(viewport.projection());
+        self.data.scissor = viewport_scissor(viewport);
+    }
+
+    pub fn target(&self) -> &RenderTargetView<R, ColorFormat> {
+        &self.data.out
+    }
+
+    pub fn set_target(&mut self, out: RenderTargetView<R, ColorFormat>) {
+        self.data.out = out;
+    }
+
+    // draws into a render target instead of the window, with a pixel projection covering it.
+    // switching back to the window means setting its target, projection or viewport again.
+    pub fn set_render_target(&mut self, target: &RenderTarget2D<R>) {
+        self.data.out = target.target().clone();
+        self.data.scissor = target.scissor();
+        self.set_projection(target.projection());
+    }
+
+    pub fn clear<C>(&self, encoder: &mut gfx::Encoder<R, C>, color: [f32; 4])
+        where C: gfx::CommandBuffer<R>
+    {
+        encoder.clear(&self.data.out, color);
+    }
+
+    pub fn draw_sprite<C>(&mut self,
+                          encoder: &mut gfx::Encoder<R, C>,
+                          texture: &ShaderResourceView<R, [f32; 4]>,
+                          position: [f32; 2],
+                          size: [f32; 2])
+        where C: gfx::CommandBuffer<R>
+    {
+        self.draw_sprite_region(encoder, texture, Rect::unit(), position, size);
+    }
+
+    // draws part of a texture, such as the current frame of an Animation
+    pub fn draw_sprite_region<C>(&mut self,
+                                 encoder: &mut gfx::Encoder<R, C>,
+                                 texture: &ShaderResourceView<R, [f32; 4]>,
+                                 uv: Rect,
+                                 position: [f32; 2],
+                                 size: [f32; 2])
+        where C: gfx::CommandBuffer<R>
+    {
+        if self.uv != Some(uv) {
+            encoder.update_buffer(&self.data.vbuf, &tex_quad(uv), 0).unwrap();
+            self.uv = Some(uv);
+        }
+
+        self.view.model = sprite_model(position, size).into();
+        self.data.texture_sampler.0 = texture.clone();
+
+        encoder.update_constant_buffer(&self.data.view, &self.view);
+        encoder.draw(&self.slice, &self.pso, &self.data);
+    }
+}
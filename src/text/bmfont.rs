@@ -0,0 +1,449 @@
+use atlas::PixelRect;
+use batch::{SpriteBatch, TextureId};
+use image::{self, RgbaImage};
+use rect::Rect;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use text::glyph_sprite;
+use text::layout::{FontMetrics, GlyphMetrics, TextLayout};
+use xml::reader::{self, EventReader, XmlEvent};
+
+// a glyph as BMFont describes it, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitmapGlyph {
+    // where the glyph is on its page, with the origin at the page's top left
+    pub region: PixelRect,
+    // from the pen position at the top of the line to the region's top left
+    pub offset: [f32; 2],
+    pub advance: f32,
+    pub page: usize,
+}
+
+#[derive(Debug)]
+pub enum BitmapFontError {
+    Io(io::Error),
+    Xml(reader::Error),
+    Image(image::ImageError),
+    // a line or element the file needs is missing or can't be read
+    Invalid(String),
+}
+
+impl fmt::Display for BitmapFontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BitmapFontError::Io(ref err) => write!(f, "{}", err),
+            BitmapFontError::Xml(ref err) => write!(f, "{}", err),
+            BitmapFontError::Image(ref err) => write!(f, "{}", err),
+            BitmapFontError::Invalid(ref what) => write!(f, "invalid font: {}", what),
+        }
+    }
+}
+
+impl Error for BitmapFontError {}
+
+impl From<io::Error> for BitmapFontError {
+    fn from(err: io::Error) -> Self {
+        BitmapFontError::Io(err)
+    }
+}
+
+impl From<reader::Error> for BitmapFontError {
+    fn from(err: reader::Error) -> Self {
+        BitmapFontError::Xml(err)
+    }
+}
+
+impl From<image::ImageError> for BitmapFontError {
+    fn from(err: image::ImageError) -> Self {
+        BitmapFontError::Image(err)
+    }
+}
+
+// both of BMFont's descriptor formats are a list of tags with attributes, the text one as
+// `tag key=value ...` lines and the xml one as elements, so they're read into this first
+struct Tag {
+    name: String,
+    attributes: HashMap<String, String>,
+}
+
+impl Tag {
+    fn get(&self, key: &str) -> Result<&str, BitmapFontError> {
+        self.attributes
+            .get(key)
+            .map(|value| value.as_str())
+            .ok_or_else(|| BitmapFontError::Invalid(format!("{} without {}", self.name, key)))
+    }
+
+    fn number(&self, key: &str) -> Result<i64, BitmapFontError> {
+        let value = self.get(key)?;
+        value.parse()
+            .map_err(|_| BitmapFontError::Invalid(format!("{} {}={}", self.name, key, value)))
+    }
+
+    // a count or a page id, which can't be negative
+    fn index(&self, key: &str) -> Result<usize, BitmapFontError> {
+        let value = self.get(key)?;
+        value.parse()
+            .map_err(|_| BitmapFontError::Invalid(format!("{} {}={}", self.name, key, value)))
+    }
+}
+
+fn read_text_tags(source: &str) -> Result<Vec<Tag>, BitmapFontError> {
+    let mut tags = Vec::new();
+    for line in source.lines() {
+        let line = line.trim();
+        let (name, mut rest) = match line.find(char::is_whitespace) {
+            Some(end) => (&line[..end], &line[end..]),
+            None => (line, ""),
+        };
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut attributes = HashMap::new();
+        loop {
+            rest = rest.trim_start();
+            let equals = match rest.find('=') {
+                Some(equals) => equals,
+                None => break,
+            };
+            let key = rest[..equals].trim().to_string();
+            rest = &rest[equals + 1..];
+
+            // quoted values, such as the face and file names, can have spaces in them
+            let value = if rest.starts_with('"') {
+                let end = rest[1..]
+                    .find('"')
+                    .ok_or_else(|| BitmapFontError::Invalid(format!("unclosed quote: {}", line)))?;
+                let value = &rest[1..end + 1];
+                rest = &rest[end + 2..];
+                value
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+                value
+            };
+            attributes.insert(key, value.to_string());
+        }
+
+        tags.push(Tag {
+            name: name.to_string(),
+            attributes: attributes,
+        });
+    }
+    Ok(tags)
+}
+
+fn read_xml_tags(source: &str) -> Result<Vec<Tag>, BitmapFontError> {
+    let mut tags = Vec::new();
+    for event in EventReader::from_str(source) {
+        if let XmlEvent::StartElement { name, attributes, .. } = event? {
+            tags.push(Tag {
+                name: name.local_name,
+                attributes: attributes.into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+            });
+        }
+    }
+    Ok(tags)
+}
+
+// a font exported by AngelCode's BMFont or anything that writes its format, in either the text
+// or the xml flavour. the page images are loaded separately, see load_pages.
+pub struct BitmapFont {
+    pub face: String,
+    pub line_height: f32,
+    // from the top of a line down to the baseline
+    pub base: f32,
+    // the size of every page, which the glyph regions are relative to
+    pub page_size: [u32; 2],
+    // the page images' file names, relative to the descriptor
+    pub pages: Vec<String>,
+    glyphs: HashMap<char, BitmapGlyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+impl BitmapFont {
+    pub fn parse(source: &str) -> Result<Self, BitmapFontError> {
+        let tags = if source.trim_start().starts_with('<') {
+            read_xml_tags(source)?
+        } else {
+            read_text_tags(source)?
+        };
+        BitmapFont::from_tags(&tags)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BitmapFontError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        BitmapFont::parse(&source)
+    }
+
+    fn from_tags(tags: &[Tag]) -> Result<Self, BitmapFontError> {
+        let mut font = BitmapFont {
+            face: String::new(),
+            line_height: 0.0,
+            base: 0.0,
+            page_size: [0, 0],
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+        };
+        // how many pages common says there are, once it's been read
+        let mut page_count = None;
+
+        for tag in tags {
+            match tag.name.as_str() {
+                "info" => font.face = tag.get("face").unwrap_or("").to_string(),
+                "common" => {
+                    font.line_height = tag.number("lineHeight")? as f32;
+                    font.base = tag.number("base")? as f32;
+                    font.page_size = [tag.number("scaleW")? as u32, tag.number("scaleH")? as u32];
+                    page_count = Some(tag.index("pages")?);
+                }
+                "page" => {
+                    let id = tag.index("id")?;
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+                    font.pages[id] = tag.get("file")?.to_string();
+                }
+                "char" => {
+                    // id -1 is the glyph drawn for missing characters, which isn't used here
+                    let character = match to_char(tag.number("id")?) {
+                        Some(character) => character,
+                        None => continue,
+                    };
+                    font.glyphs.insert(character,
+                                       BitmapGlyph {
+                                           region: PixelRect {
+                                               x: tag.number("x")? as u32,
+                                               y: tag.number("y")? as u32,
+                                               width: tag.number("width")? as u32,
+                                               height: tag.number("height")? as u32,
+                                           },
+                                           offset: [tag.number("xoffset")? as f32,
+                                                    tag.number("yoffset")? as f32],
+                                           advance: tag.number("xadvance")? as f32,
+                                           page: match tag.get("page") {
+                                               Ok(_) => tag.index("page")?,
+                                               Err(_) => 0,
+                                           },
+                                       });
+                }
+                "kerning" => {
+                    let first = to_char(tag.number("first")?);
+                    let second = to_char(tag.number("second")?);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        font.kernings.insert((first, second), tag.number("amount")? as f32);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let page_count = page_count
+            .ok_or_else(|| BitmapFontError::Invalid("no common line".to_string()))?;
+        if font.pages.len() > page_count {
+            return Err(BitmapFontError::Invalid(format!("page {} of {} pages",
+                                                        font.pages.len() - 1,
+                                                        page_count)));
+        }
+        if let Some(missing) = (0..page_count)
+            .find(|&id| font.pages.get(id).is_none_or(|file| file.is_empty())) {
+            return Err(BitmapFontError::Invalid(format!("no page {}", missing)));
+        }
+        for glyph in font.glyphs.values() {
+            if glyph.page >= page_count {
+                return Err(BitmapFontError::Invalid(format!("a glyph on page {} of {} pages",
+                                                            glyph.page,
+                                                            page_count)));
+            }
+        }
+        Ok(font)
+    }
+
+    // reads the page images from the directory the descriptor is in, in page order
+    pub fn load_pages<P>(&self, directory: P) -> Result<Vec<RgbaImage>, BitmapFontError>
+        where P: AsRef<Path>
+    {
+        let mut pages = Vec::with_capacity(self.pages.len());
+        for page in &self.pages {
+            pages.push(image::open(directory.as_ref().join(page))?.to_rgba8());
+        }
+        Ok(pages)
+    }
+
+    pub fn bitmap_glyph(&self, character: char) -> Option<&BitmapGlyph> {
+        self.glyphs.get(&character)
+    }
+
+    // a glyph's region of its page in normalized texture coordinates
+    pub fn uv(&self, glyph: &BitmapGlyph) -> Rect {
+        let width = self.page_size[0] as f32;
+        let height = self.page_size[1] as f32;
+        Rect::new(glyph.region.x as f32 / width,
+                  glyph.region.y as f32 / height,
+                  glyph.region.width as f32 / width,
+                  glyph.region.height as f32 / height)
+    }
+
+    // the texture and uv to draw a character with, given the TextureId each page image was
+    // registered as. None for characters the font doesn't have or whose page isn't in pages.
+    pub fn region(&self, pages: &[TextureId], character: char) -> Option<(TextureId, Rect)> {
+        let glyph = self.glyphs.get(&character)?;
+        pages.get(glyph.page).map(|&texture| (texture, self.uv(glyph)))
+    }
+
+    // queues a laid out string as one sprite per glyph. position is where the text's top left
+    // goes in the world.
+    pub fn draw(&self,
+                batch: &mut SpriteBatch,
+                pages: &[TextureId],
+                text: &TextLayout,
+                position: [f32; 2],
+                tint: [f32; 4]) {
+        for placed in &text.glyphs {
+            if let Some((texture, uv)) = self.region(pages, placed.character) {
+                batch.push(texture, glyph_sprite(placed, uv, position).with_tint(tint));
+            }
+        }
+    }
+}
+
+impl FontMetrics for BitmapFont {
+    fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    fn glyph(&self, character: char) -> Option<GlyphMetrics> {
+        self.glyphs.get(&character).map(|glyph| {
+            GlyphMetrics {
+                size: [glyph.region.width as f32, glyph.region.height as f32],
+                offset: glyph.offset,
+                advance: glyph.advance,
+            }
+        })
+    }
+
+    fn kerning(&self, left: char, right: char) -> f32 {
+        self.kernings.get(&(left, right)).cloned().unwrap_or(0.0)
+    }
+}
+
+fn to_char(id: i64) -> Option<char> {
+    if id < 0 || id > u32::MAX as i64 {
+        return None;
+    }
+    ::std::char::from_u32(id as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &'static str = include_str!("../../examples/text/fonts/dejavu_sans.fnt");
+    // the same font in BMFont's xml format
+    const XML: &'static str = include_str!("../../tests/fixtures/bmfont/dejavu_sans.xml");
+
+    fn invalid(source: &str) {
+        match BitmapFont::parse(source) {
+            Err(BitmapFontError::Invalid(_)) => {}
+            Err(err) => panic!("expected an invalid font, got {}", err),
+            Ok(_) => panic!("expected an invalid font"),
+        }
+    }
+
+    #[test]
+    fn text_fonts_are_read() {
+        let font = BitmapFont::parse(TEXT).unwrap();
+        assert_eq!(font.face, "DejaVu Sans");
+        assert_eq!((font.line_height, font.base), (24.0, 19.0));
+        assert_eq!(font.page_size, [256, 256]);
+        assert_eq!(font.pages, vec!["dejavu_sans_0.png".to_string()]);
+        assert_eq!(font.glyphs.len(), 95);
+        assert_eq!(font.kernings.len(), 146);
+
+        assert_eq!(font.bitmap_glyph('A'),
+                   Some(&BitmapGlyph {
+                       region: PixelRect {
+                           x: 60,
+                           y: 18,
+                           width: 14,
+                           height: 16,
+                       },
+                       offset: [0.0, 3.0],
+                       advance: 14.0,
+                       page: 0,
+                   }));
+        assert_eq!(font.glyph(' ').unwrap().size, [0.0, 0.0]);
+        assert!(font.bitmap_glyph('\u{e9}').is_none());
+
+        assert_eq!(font.kerning('A', 'V'), -1.0);
+        assert_eq!(font.kerning('y', '.'), -3.0);
+        assert_eq!(font.kerning('V', 'A'), -1.0);
+        assert_eq!(font.kerning('A', 'A'), 1.0);
+        assert_eq!(font.kerning('H', 'H'), 0.0);
+    }
+
+    #[test]
+    fn xml_fonts_read_the_same_as_text() {
+        let text = BitmapFont::parse(TEXT).unwrap();
+        let xml = BitmapFont::parse(XML).unwrap();
+        assert_eq!(xml.face, text.face);
+        assert_eq!((xml.line_height, xml.base), (text.line_height, text.base));
+        assert_eq!(xml.page_size, text.page_size);
+        assert_eq!(xml.pages, text.pages);
+        assert_eq!(xml.glyphs, text.glyphs);
+        assert_eq!(xml.kernings, text.kernings);
+    }
+
+    #[test]
+    fn regions_come_from_the_glyphs_page() {
+        let font = BitmapFont::parse(TEXT).unwrap();
+        let (texture, uv) = font.region(&[TextureId(3)], 'A').unwrap();
+        assert_eq!(texture, TextureId(3));
+        assert_eq!(uv, Rect::new(60.0 / 256.0, 18.0 / 256.0, 14.0 / 256.0, 16.0 / 256.0));
+        // too few pages given is the same as a missing glyph rather than a panic
+        assert!(font.region(&[], 'A').is_none());
+        assert!(font.region(&[TextureId(3)], '\u{e9}').is_none());
+    }
+
+    #[test]
+    fn quoted_values_keep_their_spaces() {
+        let font = BitmapFont::parse("info face=\"Some Font\" size=12\n\
+                                      common lineHeight=14 base=11 scaleW=64 scaleH=32 pages=1\n\
+                                      page id=0 file=\"some font.png\"\n")
+            .unwrap();
+        assert_eq!(font.face, "Some Font");
+        assert_eq!(font.pages, vec!["some font.png".to_string()]);
+    }
+
+    #[test]
+    fn pages_outside_the_count_are_invalid() {
+        let common = "common lineHeight=14 base=11 scaleW=64 scaleH=32 pages=1\n";
+        let glyph = |page: &str| {
+            format!("{}page id=0 file=\"a.png\"\n\
+                     char id=65 x=0 y=0 width=4 height=4 xoffset=0 yoffset=0 xadvance=5 {}\n",
+                    common,
+                    page)
+        };
+        assert!(BitmapFont::parse(&glyph("page=0")).is_ok());
+        assert_eq!(BitmapFont::parse(&glyph("")).unwrap().bitmap_glyph('A').unwrap().page, 0);
+        invalid(&glyph("page=1"));
+        invalid(&glyph("page=-1"));
+
+        invalid(&format!("{}page id=1 file=\"b.png\"\n", common));
+        invalid(&format!("{}page id=-1 file=\"b.png\"\n", common));
+        // common promises a page that isn't there
+        invalid("common lineHeight=14 base=11 scaleW=64 scaleH=32 pages=2\n\
+                 page id=0 file=\"a.png\"\n");
+        invalid("page id=0 file=\"a.png\"\n");
+    }
+}
@@ -0,0 +1,427 @@
+use atlas::{PixelRect, SkylinePacker};
+use batch::{SpriteBatch, TextureId};
+use gfx;
+use gfx::handle::ShaderResourceView;
+use image::{GenericImage, Rgba, RgbaImage};
+use rect::Rect;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use text::glyph_sprite;
+use text::layout::TextLayout;
+use text::truetype::{ScaledFont, TrueTypeFont};
+use texture::texture_from_image;
+
+const DEFAULT_SIZE: u32 = 256;
+const DEFAULT_MAX_SIZE: u32 = 2048;
+const DEFAULT_PADDING: u32 = 1;
+
+// identifies a font added to a GlyphCache
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(pub usize);
+
+// glyphs are cached per font, character and pixel size
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: FontId,
+    character: char,
+    size: u32,
+}
+
+impl GlyphKey {
+    fn new(font: FontId, character: char, size: f32) -> Self {
+        GlyphKey {
+            font: font,
+            character: character,
+            size: size.to_bits(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    region: PixelRect,
+    // the frame the glyph was last queued on, only glyphs the current frame uses survive
+    // eviction
+    last_used: u64,
+}
+
+#[derive(Debug)]
+pub enum GlyphCacheError {
+    // even an empty cache of the maximum size can't hold the glyph
+    TooLarge(char),
+    // the glyphs queued this frame don't all fit at once, even after evicting every other one
+    Full,
+}
+
+impl fmt::Display for GlyphCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GlyphCacheError::TooLarge(character) => {
+                write!(f, "{:?} is larger than the glyph cache can be", character)
+            }
+            GlyphCacheError::Full => write!(f, "too many glyphs in use to fit in the cache"),
+        }
+    }
+}
+
+impl Error for GlyphCacheError {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub glyphs: usize,
+    pub rasterized: usize,
+    pub evicted: usize,
+    pub size: [u32; 2],
+}
+
+// glyphs of TrueType fonts rasterized as text asks for them into one texture, shared by every
+// font and size. it starts small, doubles until it reaches its maximum size, and from then on
+// makes room by dropping every glyph the current frame hasn't asked for.
+pub struct GlyphCache {
+    fonts: Vec<TrueTypeFont>,
+    image: RgbaImage,
+    packer: SkylinePacker,
+    entries: HashMap<GlyphKey, Entry>,
+    max_size: u32,
+    padding: u32,
+    frame: u64,
+    // whether the image changed since the texture was last uploaded
+    dirty: bool,
+    stats: CacheStats,
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        GlyphCache::new()
+    }
+}
+
+impl GlyphCache {
+    pub fn new() -> Self {
+        GlyphCache {
+            fonts: Vec::new(),
+            image: RgbaImage::new(DEFAULT_SIZE, DEFAULT_SIZE),
+            packer: SkylinePacker::new(DEFAULT_SIZE, DEFAULT_SIZE),
+            entries: HashMap::new(),
+            max_size: DEFAULT_MAX_SIZE,
+            padding: DEFAULT_PADDING,
+            frame: 0,
+            dirty: true,
+            stats: CacheStats::default(),
+        }
+    }
+
+    // a maximum below the starting size is where the cache starts instead
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        let (width, height) = self.image.dimensions();
+        if width > max_size || height > max_size {
+            let (width, height) = (width.min(max_size), height.min(max_size));
+            self.image = RgbaImage::new(width, height);
+            self.packer = SkylinePacker::new(width, height);
+        }
+        self
+    }
+
+    pub fn add_font(&mut self, font: TrueTypeFont) -> FontId {
+        self.fonts.push(font);
+        FontId(self.fonts.len() - 1)
+    }
+
+    // a font's metrics at a size, to lay text out with before queueing it
+    pub fn font(&self, font: FontId, size: f32) -> ScaledFont<'_> {
+        self.fonts[font.0].scaled(size)
+    }
+
+    // call once a frame before queueing anything, so eviction knows what's in use
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.stats.rasterized = 0;
+        self.stats.evicted = 0;
+    }
+
+    // makes sure every glyph of a laid out string is in the cache, rasterizing the missing
+    // ones. glyphs can move when the cache grows or evicts, so draw only after everything for
+    // the frame has been queued.
+    pub fn queue(&mut self,
+                 font: FontId,
+                 size: f32,
+                 text: &TextLayout)
+                 -> Result<(), GlyphCacheError> {
+        for placed in &text.glyphs {
+            let key = GlyphKey::new(font, placed.character, size);
+            let frame = self.frame;
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.last_used = frame;
+                continue;
+            }
+            self.rasterize(key)?;
+        }
+        Ok(())
+    }
+
+    // queues sprites for a laid out string, like BitmapFont::draw. texture is the id the
+    // cache's texture was registered as.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(&self,
+                batch: &mut SpriteBatch,
+                texture: TextureId,
+                font: FontId,
+                size: f32,
+                text: &TextLayout,
+                position: [f32; 2],
+                tint: [f32; 4]) {
+        for placed in &text.glyphs {
+            if let Some((texture, uv)) = self.region(texture, font, size, placed.character) {
+                batch.push(texture, glyph_sprite(placed, uv, position).with_tint(tint));
+            }
+        }
+    }
+
+    // where a queued glyph is in the cache, along with the texture id passed in, to match
+    // BitmapFont::region
+    pub fn region(&self,
+                  texture: TextureId,
+                  font: FontId,
+                  size: f32,
+                  character: char)
+                  -> Option<(TextureId, Rect)> {
+        self.entries
+            .get(&GlyphKey::new(font, character, size))
+            .map(|entry| (texture, self.uv(entry.region)))
+    }
+
+    // a new texture when the cache changed since the last call, to hand the renderer with
+    // set_texture. the whole image is uploaded again, which is fine as long as glyphs are
+    // mostly found in the cache rather than rasterized.
+    pub fn update_texture<R, F>(&mut self,
+                                factory: &mut F)
+                                -> Result<Option<ShaderResourceView<R, [f32; 4]>>, String>
+        where R: gfx::Resources,
+              F: gfx::Factory<R>
+    {
+        if !self.dirty {
+            return Ok(None);
+        }
+        let texture = texture_from_image(factory, &self.image)?;
+        self.dirty = false;
+        Ok(Some(texture))
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            glyphs: self.entries.len(),
+            size: [self.image.width(), self.image.height()],
+            ..self.stats
+        }
+    }
+
+    fn uv(&self, region: PixelRect) -> Rect {
+        let width = self.image.width() as f32;
+        let height = self.image.height() as f32;
+        Rect::new(region.x as f32 / width,
+                  region.y as f32 / height,
+                  region.width as f32 / width,
+                  region.height as f32 / height)
+    }
+
+    fn rasterize(&mut self, key: GlyphKey) -> Result<(), GlyphCacheError> {
+        let glyph = self.fonts[key.font.0]
+            .scaled(f32::from_bits(key.size))
+            .positioned(key.character);
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+        let width = bounds.width() as u32;
+        let height = bounds.height() as u32;
+        let region = self.allocate(key.character, width, height)?;
+
+        // white, so the sprite's tint is the text color
+        {
+            let image = &mut self.image;
+            glyph.draw(|x, y, coverage| {
+                let alpha = (coverage * 255.0).round() as u8;
+                image.put_pixel(region.x + x, region.y + y, Rgba([255, 255, 255, alpha]));
+            });
+        }
+
+        self.entries.insert(key,
+                            Entry {
+                                region: region,
+                                last_used: self.frame,
+                            });
+        self.stats.rasterized += 1;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn allocate(&mut self,
+                character: char,
+                width: u32,
+                height: u32)
+                -> Result<PixelRect, GlyphCacheError> {
+        let padded_width = width + self.padding;
+        let padded_height = height + self.padding;
+        if padded_width > self.max_size || padded_height > self.max_size {
+            return Err(GlyphCacheError::TooLarge(character));
+        }
+
+        loop {
+            if let Some(rect) = self.packer.pack(padded_width, padded_height) {
+                return Ok(PixelRect {
+                    x: rect.x,
+                    y: rect.y,
+                    width: width,
+                    height: height,
+                });
+            }
+            if !self.grow() {
+                break;
+            }
+        }
+
+        // full at the maximum size, so start over with only what this frame uses
+        self.evict()?;
+        self.packer.pack(padded_width, padded_height).map(|rect| {
+            PixelRect {
+                x: rect.x,
+                y: rect.y,
+                width: width,
+                height: height,
+            }
+        }).ok_or(GlyphCacheError::Full)
+    }
+
+    // doubles the shorter side, keeping every glyph where it is
+    fn grow(&mut self) -> bool {
+        let (width, height) = self.image.dimensions();
+        let (new_width, new_height) = if width <= height {
+            ((width * 2).min(self.max_size), height)
+        } else {
+            (width, (height * 2).min(self.max_size))
+        };
+        if new_width == width && new_height == height {
+            return false;
+        }
+
+        let mut image = RgbaImage::new(new_width, new_height);
+        // the new image is never smaller than the old one
+        image.copy_from(&self.image, 0, 0).unwrap();
+        self.image = image;
+        self.packer.grow(new_width, new_height);
+        self.dirty = true;
+        true
+    }
+
+    // drops every glyph not queued this frame and packs the rest again from scratch. fails
+    // when there was nothing to drop, or when the glyphs this frame uses no longer fit packed
+    // the new way.
+    fn evict(&mut self) -> Result<(), GlyphCacheError> {
+        let frame = self.frame;
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.last_used == frame);
+        if self.entries.len() == before {
+            return Err(GlyphCacheError::Full);
+        }
+        self.stats.evicted += before - self.entries.len();
+
+        let (width, height) = self.image.dimensions();
+        let old = ::std::mem::replace(&mut self.image, RgbaImage::new(width, height));
+        self.packer = SkylinePacker::new(width, height);
+
+        // the tallest first, as the atlas builder does
+        let mut kept: Vec<(GlyphKey, Entry)> = self.entries.drain().collect();
+        kept.sort_by_key(|kept| Reverse(kept.1.region.height));
+        self.dirty = true;
+        for (key, mut entry) in kept {
+            let source = entry.region;
+            // packed differently they almost always fit again. when one doesn't, something
+            // already queued this frame would be drawn with a hole where it was, so the whole
+            // frame is reported as not fitting. the glyphs that were left out are rasterized
+            // again the next time they're queued.
+            let rect = match self.packer
+                .pack(source.width + self.padding, source.height + self.padding) {
+                Some(rect) => rect,
+                None => return Err(GlyphCacheError::Full),
+            };
+            for y in 0..source.height {
+                for x in 0..source.width {
+                    let pixel = *old.get_pixel(source.x + x, source.y + y);
+                    self.image.put_pixel(rect.x + x, rect.y + y, pixel);
+                }
+            }
+            entry.region.x = rect.x;
+            entry.region.y = rect.y;
+            self.entries.insert(key, entry);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text::layout::Layout;
+
+    const SIZE: f32 = 16.0;
+
+    fn cache(max_size: u32) -> (GlyphCache, FontId) {
+        let bytes = include_bytes!("../../examples/text/fonts/DejaVuSans.ttf");
+        let mut cache = GlyphCache::new().with_max_size(max_size);
+        let font = cache.add_font(TrueTypeFont::from_bytes(bytes.to_vec()).unwrap());
+        (cache, font)
+    }
+
+    fn queue_frame(cache: &mut GlyphCache,
+                   font: FontId,
+                   text: &str)
+                   -> Result<(), GlyphCacheError> {
+        cache.begin_frame();
+        let layout = Layout::new().layout(&cache.font(font, SIZE), text);
+        cache.queue(font, SIZE, &layout)?;
+        for placed in &layout.glyphs {
+            assert!(cache.region(TextureId(0), font, SIZE, placed.character).is_some(),
+                    "{:?} was queued but isn't in the cache",
+                    placed.character);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn a_max_size_below_the_start_is_where_it_starts() {
+        let (cache, _) = cache(64);
+        assert_eq!(cache.stats().size, [64, 64]);
+    }
+
+    #[test]
+    fn evicting_keeps_everything_the_frame_queued() {
+        let (mut cache, font) = cache(64);
+        let frames = ["ABCDEFGHIJKLM", "NOPQRSTUVWXYZ", "abcdefghijklm", "ABCDEFGHIJKLM"];
+        let mut evicted = 0;
+        for text in &frames {
+            queue_frame(&mut cache, font, text).unwrap();
+            evicted += cache.stats().evicted;
+            assert_eq!(cache.stats().size, [64, 64]);
+        }
+        assert!(evicted > 0);
+    }
+
+    #[test]
+    fn a_frame_that_cannot_fit_is_an_error() {
+        let (mut cache, font) = cache(64);
+        queue_frame(&mut cache, font, "abc").unwrap();
+        match queue_frame(&mut cache, font, "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789@#%&") {
+            Err(GlyphCacheError::Full) => {}
+            other => panic!("expected the cache to be full, got {:?}", other),
+        }
+        // the next frame that does fit gets all of its glyphs back
+        queue_frame(&mut cache, font, "abcXYZ").unwrap();
+    }
+}
@@ -0,0 +1,370 @@
+use rect::Rect;
+use unicode_normalization::char::is_combining_mark;
+
+// what laying out text needs to know about a glyph, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphMetrics {
+    // the size of the glyph's image, zero for glyphs with nothing to draw such as spaces
+    pub size: [f32; 2],
+    // from the pen position at the top of the line to the image's top left, y pointing down
+    pub offset: [f32; 2],
+    // how far the pen moves on after the glyph
+    pub advance: f32,
+}
+
+// anything text can be laid out with, whatever it ends up being drawn from
+pub trait FontMetrics {
+    // from the top of one line to the top of the next
+    fn line_height(&self) -> f32;
+
+    fn glyph(&self, character: char) -> Option<GlyphMetrics>;
+
+    // added to the advance between two glyphs, usually negative to tuck pairs like AV together.
+    // for a combining mark it only moves the mark, relative to the pen after the glyph it's on.
+    fn kerning(&self, _left: char, _right: char) -> f32 {
+        0.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// one glyph of laid out text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedGlyph {
+    pub character: char,
+    // which char of the string it came from, counting chars rather than bytes
+    pub index: usize,
+    pub line: usize,
+    // in pixels from the top left of the text, y pointing down
+    pub rect: Rect,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    // the chars of the string on this line, start included and end not, not counting the
+    // whitespace or newline it was broken at
+    pub start: usize,
+    pub end: usize,
+    // from the line's first pen position to the end of its last visible glyph's advance
+    pub width: f32,
+    // where the line starts once aligned
+    pub x: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    // only glyphs with something to draw, in string order
+    pub glyphs: Vec<PlacedGlyph>,
+    pub lines: Vec<Line>,
+    // the width of the wrapping box when there is one, otherwise of the widest line
+    pub width: f32,
+    pub height: f32,
+}
+
+// how to turn a string into glyph rectangles. this never touches a texture, so what it
+// produces can be checked without a window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    align: Align,
+    max_width: Option<f32>,
+    line_spacing: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::new()
+    }
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Layout {
+            align: Align::Left,
+            max_width: None,
+            line_spacing: 1.0,
+        }
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    // lines longer than this are wrapped at the last whitespace that fits, or mid word when a
+    // single word is wider than the whole line
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    // multiplies the font's line height
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn layout<F>(&self, font: &F, text: &str) -> TextLayout
+        where F: FontMetrics + ?Sized
+    {
+        let chars: Vec<char> = text.chars().collect();
+        let mut lines = Vec::new();
+
+        let mut paragraph_start = 0;
+        while paragraph_start <= chars.len() {
+            let paragraph_end = chars[paragraph_start..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |end| paragraph_start + end);
+            self.break_lines(font, &chars, paragraph_start, paragraph_end, &mut lines);
+            paragraph_start = paragraph_end + 1;
+        }
+
+        let width = match self.max_width {
+            Some(max_width) => max_width,
+            None => lines.iter().fold(0.0, |widest: f32, line| widest.max(line.width)),
+        };
+        let line_height = font.line_height() * self.line_spacing;
+
+        let mut glyphs = Vec::new();
+        for (number, line) in lines.iter_mut().enumerate() {
+            line.x = match self.align {
+                Align::Left => 0.0,
+                Align::Center => (width - line.width) / 2.0,
+                Align::Right => width - line.width,
+            };
+
+            let top = number as f32 * line_height;
+            let mut pen = line.x;
+            let mut previous = None;
+            for (index, &character) in chars.iter().enumerate().take(line.end).skip(line.start) {
+                let metrics = match font.glyph(character) {
+                    Some(metrics) => metrics,
+                    None => continue,
+                };
+                let kerning = previous.map_or(0.0, |previous| font.kerning(previous, character));
+                if metrics.size[0] > 0.0 && metrics.size[1] > 0.0 {
+                    glyphs.push(PlacedGlyph {
+                        character: character,
+                        index: index,
+                        line: number,
+                        rect: Rect::new(pen + kerning + metrics.offset[0],
+                                        top + metrics.offset[1],
+                                        metrics.size[0],
+                                        metrics.size[1]),
+                    });
+                }
+                // marks sit on the glyph before them, which stays the one the next is kerned to
+                if !is_combining_mark(character) {
+                    pen += kerning + metrics.advance;
+                    previous = Some(character);
+                }
+            }
+        }
+
+        TextLayout {
+            glyphs: glyphs,
+            height: lines.len() as f32 * line_height,
+            lines: lines,
+            width: width,
+        }
+    }
+
+    // greedily splits one paragraph into lines that fit the wrapping width
+    fn break_lines<F>(&self,
+                      font: &F,
+                      chars: &[char],
+                      start: usize,
+                      end: usize,
+                      lines: &mut Vec<Line>)
+        where F: FontMetrics + ?Sized
+    {
+        let mut line_start = start;
+        loop {
+            let mut pen = 0.0;
+            let mut width = 0.0;
+            let mut previous = None;
+            // the whitespace to break at if the line overflows, and the width up to it
+            let mut last_break = None;
+            let mut line_end = end;
+
+            for (index, &character) in chars.iter().enumerate().take(end).skip(line_start) {
+                if character.is_whitespace() && index > line_start {
+                    last_break = Some((index, width));
+                }
+                let metrics = match font.glyph(character) {
+                    Some(metrics) => metrics,
+                    None => continue,
+                };
+                let kerning = previous.map_or(0.0, |previous| font.kerning(previous, character));
+
+                if !character.is_whitespace() && !is_combining_mark(character) {
+                    let right = pen + kerning + metrics.advance.max(metrics.offset[0] +
+                                                                    metrics.size[0]);
+                    let overflows = self.max_width.is_some_and(|max_width| right > max_width);
+                    if overflows && index > line_start {
+                        match last_break {
+                            Some((at, width_before)) => {
+                                line_end = at;
+                                width = width_before;
+                            }
+                            None => line_end = index,
+                        }
+                        break;
+                    }
+                    width = pen + kerning + metrics.advance;
+                }
+
+                if !is_combining_mark(character) {
+                    pen += kerning + metrics.advance;
+                    previous = Some(character);
+                }
+            }
+
+            lines.push(Line {
+                start: line_start,
+                end: line_end,
+                width: width,
+                x: 0.0,
+            });
+            if line_end >= end {
+                return;
+            }
+
+            // the whitespace a line was broken at doesn't start the next one
+            line_start = line_end;
+            while line_start < end && chars[line_start].is_whitespace() {
+                line_start += 1;
+            }
+            if line_start >= end {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every glyph ten pixels apart and eight wide, one in from the pen and two down from the
+    // top of the line. spaces have nothing to draw, marks don't move the pen, and AV is kerned.
+    struct Monospace;
+
+    impl FontMetrics for Monospace {
+        fn line_height(&self) -> f32 {
+            12.0
+        }
+
+        fn glyph(&self, character: char) -> Option<GlyphMetrics> {
+            let (size, advance) = match character {
+                ' ' => ([0.0, 0.0], 10.0),
+                '\u{301}' => ([4.0, 3.0], 0.0),
+                _ => ([8.0, 10.0], 10.0),
+            };
+            Some(GlyphMetrics {
+                size: size,
+                offset: [1.0, 2.0],
+                advance: advance,
+            })
+        }
+
+        fn kerning(&self, left: char, right: char) -> f32 {
+            if left == 'A' && right == 'V' { -3.0 } else { 0.0 }
+        }
+    }
+
+    fn spans(text: &TextLayout) -> Vec<(usize, usize)> {
+        text.lines.iter().map(|line| (line.start, line.end)).collect()
+    }
+
+    fn lefts(text: &TextLayout) -> Vec<f32> {
+        text.glyphs.iter().map(|glyph| glyph.rect.x).collect()
+    }
+
+    #[test]
+    fn wraps_at_the_last_space_that_fits() {
+        let text = Layout::new().with_max_width(35.0).layout(&Monospace, "ab cd ef");
+        assert_eq!(spans(&text), vec![(0, 2), (3, 5), (6, 8)]);
+        assert!(text.lines.iter().all(|line| line.width == 20.0));
+        assert_eq!(text.width, 35.0);
+        assert_eq!(text.height, 36.0);
+        let tops: Vec<f32> = text.glyphs.iter().map(|glyph| glyph.rect.y).collect();
+        assert_eq!(tops, vec![2.0, 2.0, 14.0, 14.0, 26.0, 26.0]);
+    }
+
+    #[test]
+    fn a_line_that_exactly_fits_is_not_wrapped() {
+        let text = Layout::new().with_max_width(30.0).layout(&Monospace, "abc");
+        assert_eq!(spans(&text), vec![(0, 3)]);
+        let text = Layout::new().with_max_width(29.0).layout(&Monospace, "abc");
+        assert_eq!(spans(&text), vec![(0, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn words_wider_than_the_line_are_split() {
+        let text = Layout::new().with_max_width(35.0).layout(&Monospace, "abcdefgh ij");
+        assert_eq!(spans(&text), vec![(0, 3), (3, 6), (6, 8), (9, 11)]);
+    }
+
+    #[test]
+    fn newlines_start_lines_of_their_own() {
+        let text = Layout::new().layout(&Monospace, "ab\n\ncd");
+        assert_eq!(spans(&text), vec![(0, 2), (3, 3), (4, 6)]);
+        assert_eq!(text.lines[1].width, 0.0);
+        assert_eq!(text.height, 36.0);
+        let c = text.glyphs[2];
+        assert_eq!((c.character, c.index, c.line), ('c', 4, 2));
+        assert_eq!((c.rect.x, c.rect.y), (1.0, 26.0));
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_widest() {
+        let left = Layout::new().layout(&Monospace, "ab\nabcd");
+        assert_eq!(left.width, 40.0);
+        assert_eq!((left.lines[0].x, left.lines[1].x), (0.0, 0.0));
+
+        let center = Layout::new().with_align(Align::Center).layout(&Monospace, "ab\nabcd");
+        assert_eq!((center.lines[0].x, center.lines[1].x), (10.0, 0.0));
+        assert_eq!(lefts(&center)[..2], [11.0, 21.0]);
+
+        let right = Layout::new().with_align(Align::Right).layout(&Monospace, "ab\nabcd");
+        assert_eq!((right.lines[0].x, right.lines[1].x), (20.0, 0.0));
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_wrapping_box() {
+        let center = Layout::new()
+            .with_align(Align::Center)
+            .with_max_width(100.0)
+            .layout(&Monospace, "ab");
+        assert_eq!((center.width, center.lines[0].x), (100.0, 40.0));
+        let right = Layout::new()
+            .with_align(Align::Right)
+            .with_max_width(100.0)
+            .layout(&Monospace, "ab");
+        assert_eq!(right.lines[0].x, 80.0);
+    }
+
+    #[test]
+    fn kerned_pairs_move_closer() {
+        let text = Layout::new().layout(&Monospace, "AVA");
+        assert_eq!(lefts(&text), vec![1.0, 8.0, 18.0]);
+        assert_eq!(text.lines[0].width, 27.0);
+    }
+
+    #[test]
+    fn glyphs_know_which_char_they_came_from() {
+        // the space has nothing to draw and the precomposed é is one char of two bytes
+        let text = Layout::new().layout(&Monospace, "é b\u{301}c");
+        let indices: Vec<(char, usize)> =
+            text.glyphs.iter().map(|glyph| (glyph.character, glyph.index)).collect();
+        assert_eq!(indices, vec![('é', 0), ('b', 2), ('\u{301}', 3), ('c', 4)]);
+        // the mark sits after the b without moving the c along
+        assert_eq!(lefts(&text), vec![1.0, 21.0, 31.0, 31.0]);
+    }
+}
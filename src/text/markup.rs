@@ -0,0 +1,529 @@
+use batch::{SpriteBatch, TextureId};
+use rect::Rect;
+use std::error::Error;
+use std::fmt;
+use text::glyph_sprite;
+use text::layout::TextLayout;
+use text::truetype::shape;
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// how a run of characters is drawn, as set by the tags around it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    // the text's own color when none
+    pub color: Option<[f32; 4]>,
+    pub bold: bool,
+    pub wave: bool,
+    pub shake: bool,
+}
+
+// a stretch of the plain text with one style, counted in chars with start included and end not
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Run {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MarkupError {
+    // a [ without a ] after it
+    UnclosedBracket(usize),
+    UnknownTag(String),
+    InvalidColor(String),
+    // a closing tag that doesn't close the innermost open one
+    MismatchedClose {
+        expected: Option<String>,
+        found: String,
+    },
+    // tags still open at the end of the text
+    Unclosed(String),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MarkupError::UnclosedBracket(at) => write!(f, "[ at {} is never closed", at),
+            MarkupError::UnknownTag(ref tag) => write!(f, "unknown tag [{}]", tag),
+            MarkupError::InvalidColor(ref color) => write!(f, "invalid color {}", color),
+            MarkupError::MismatchedClose { expected: Some(ref expected), ref found } => {
+                write!(f, "expected [/{}] but found [/{}]", expected, found)
+            }
+            MarkupError::MismatchedClose { expected: None, ref found } => {
+                write!(f, "[/{}] closes nothing", found)
+            }
+            MarkupError::Unclosed(ref tag) => write!(f, "[{}] is never closed", tag),
+        }
+    }
+}
+
+impl Error for MarkupError {}
+
+// how far the animated styles move glyphs, in pixels and seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextEffects {
+    pub wave_height: f32,
+    // how many times a second each glyph bobs up and down
+    pub wave_speed: f32,
+    // how far along the wave each character is from the one before, in radians
+    pub wave_spacing: f32,
+    pub shake_distance: f32,
+    // how many times a second shaking glyphs jump somewhere new
+    pub shake_rate: f32,
+    // bold is faked by drawing each glyph a second time this far to the right
+    pub bold_offset: f32,
+}
+
+impl Default for TextEffects {
+    fn default() -> Self {
+        TextEffects::new()
+    }
+}
+
+impl TextEffects {
+    pub fn new() -> Self {
+        TextEffects {
+            wave_height: 3.0,
+            wave_speed: 1.5,
+            wave_spacing: 0.6,
+            shake_distance: 1.5,
+            shake_rate: 20.0,
+            bold_offset: 1.0,
+        }
+    }
+
+    // where the character at index is moved to at the given time, y pointing down like the
+    // layout it's added to
+    pub fn offset(&self, style: &Style, index: usize, seconds: f32) -> [f32; 2] {
+        let mut offset = [0.0, 0.0];
+        if style.wave {
+            let phase = seconds * self.wave_speed * 2.0 * ::std::f32::consts::PI +
+                        index as f32 * self.wave_spacing;
+            offset[1] -= phase.sin() * self.wave_height;
+        }
+        if style.shake {
+            let step = (seconds * self.shake_rate).floor() as u32;
+            let seed = (index as u32).wrapping_mul(0x9e37_79b9) ^ step.wrapping_mul(0x85eb_ca6b);
+            offset[0] += noise(seed) * self.shake_distance;
+            offset[1] += noise(seed ^ 0x68e3_1da4) * self.shake_distance;
+        }
+        offset
+    }
+}
+
+// text with inline tags, such as "it's [color=#f80]hot[/color] in [wave]here[/wave]". tags
+// nest and are closed by name:
+//
+//   [color=#rgb], [color=#rgba], [color=#rrggbb] or [color=#rrggbbaa] ... [/color]
+//   [b] ... [/b]
+//   [wave] ... [/wave]
+//   [shake] ... [/shake]
+//
+// and [[ is a literal [. the tags are stripped from the text, which is what gets laid out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RichText {
+    text: String,
+    runs: Vec<Run>,
+    effects: TextEffects,
+    color: [f32; 4],
+}
+
+impl RichText {
+    pub fn parse(markup: &str) -> Result<Self, MarkupError> {
+        let mut text = String::new();
+        let mut runs = Vec::new();
+        // the open tags, innermost last, each with the style from before it was opened
+        let mut open: Vec<(String, Style)> = Vec::new();
+        let mut style = Style::default();
+        let mut run_start = 0;
+        let mut length = 0;
+
+        let mut chars = markup.char_indices().peekable();
+        while let Some((at, character)) = chars.next() {
+            if character != '[' {
+                text.push(character);
+                length += 1;
+                continue;
+            }
+            if let Some(&(_, '[')) = chars.peek() {
+                chars.next();
+                text.push('[');
+                length += 1;
+                continue;
+            }
+
+            let mut tag = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, ']')) => break,
+                    Some((_, character)) => tag.push(character),
+                    None => return Err(MarkupError::UnclosedBracket(at)),
+                }
+            }
+
+            let next = if let Some(name) = tag.strip_prefix('/') {
+                match open.pop() {
+                    Some((ref opened, before)) if opened == name => before,
+                    Some((opened, _)) => {
+                        return Err(MarkupError::MismatchedClose {
+                            expected: Some(opened),
+                            found: name.to_string(),
+                        })
+                    }
+                    None => {
+                        return Err(MarkupError::MismatchedClose {
+                            expected: None,
+                            found: name.to_string(),
+                        })
+                    }
+                }
+            } else {
+                let (name, value) = match tag.find('=') {
+                    Some(equals) => (&tag[..equals], Some(&tag[equals + 1..])),
+                    None => (tag.as_str(), None),
+                };
+                let mut next = style;
+                match (name, value) {
+                    ("color", Some(value)) => next.color = Some(parse_color(value)?),
+                    ("b", None) => next.bold = true,
+                    ("wave", None) => next.wave = true,
+                    ("shake", None) => next.shake = true,
+                    _ => return Err(MarkupError::UnknownTag(tag.clone())),
+                }
+                open.push((name.to_string(), style));
+                next
+            };
+
+            if next != style {
+                if length > run_start {
+                    runs.push(Run {
+                        start: run_start,
+                        end: length,
+                        style: style,
+                    });
+                }
+                run_start = length;
+                style = next;
+            }
+        }
+
+        if let Some((tag, _)) = open.pop() {
+            return Err(MarkupError::Unclosed(tag));
+        }
+        if length > run_start {
+            runs.push(Run {
+                start: run_start,
+                end: length,
+                style: style,
+            });
+        }
+
+        Ok(RichText {
+            text: text,
+            runs: runs,
+            effects: TextEffects::new(),
+            color: WHITE,
+        })
+    }
+
+    pub fn with_effects(mut self, effects: TextEffects) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    // the color of text no color tag applies to
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    // the text without its tags, to lay out. the runs count its chars, so text for a TrueType
+    // font should come from shaped rather than from passing this to shape.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // the same text composed like truetype::shape does, with the runs moved to the chars they
+    // end up on. each run is composed on its own, so a mark styled apart from its letter stays
+    // a separate char.
+    pub fn shaped(&self) -> RichText {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut text = String::new();
+        let mut runs = Vec::new();
+        let mut length = 0;
+        for run in &self.runs {
+            let composed = shape(&chars[run.start..run.end].iter().collect::<String>());
+            let start = length;
+            length += composed.chars().count();
+            text.push_str(&composed);
+            runs.push(Run {
+                start: start,
+                end: length,
+                style: run.style,
+            });
+        }
+
+        RichText {
+            text: text,
+            runs: runs,
+            effects: self.effects,
+            color: self.color,
+        }
+    }
+
+    pub fn runs(&self) -> &[Run] {
+        &self.runs
+    }
+
+    // the style of the char at index
+    pub fn style(&self, index: usize) -> Style {
+        self.runs
+            .iter()
+            .find(|run| run.start <= index && index < run.end)
+            .map_or(Style::default(), |run| run.style)
+    }
+
+    // queues the glyphs of a layout of this text whose index is below visible, each tinted
+    // and moved by its style. region finds the texture and uv to draw a character with, such
+    // as BitmapFont::region or GlyphCache::region.
+    pub fn draw<F>(&self,
+                   batch: &mut SpriteBatch,
+                   layout: &TextLayout,
+                   position: [f32; 2],
+                   seconds: f32,
+                   visible: usize,
+                   mut region: F)
+        where F: FnMut(char) -> Option<(TextureId, Rect)>
+    {
+        for placed in layout.glyphs.iter().filter(|placed| placed.index < visible) {
+            let (texture, uv) = match region(placed.character) {
+                Some(region) => region,
+                None => continue,
+            };
+            let style = self.style(placed.index);
+            let offset = self.effects.offset(&style, placed.index, seconds);
+            // the glyph's color goes into its vertices, the way triangle_interpolated colors
+            // its corners
+            let tint = style.color.unwrap_or(self.color);
+            let origin = [position[0] + offset[0], position[1] - offset[1]];
+
+            batch.push(texture, glyph_sprite(placed, uv, origin).with_tint(tint));
+            if style.bold {
+                let bold_origin = [origin[0] + self.effects.bold_offset, origin[1]];
+                batch.push(texture, glyph_sprite(placed, uv, bold_origin).with_tint(tint));
+            }
+        }
+    }
+}
+
+// reveals text a character at a time, like a dialogue box typing itself out
+pub struct Typewriter {
+    chars: Vec<char>,
+    chars_per_second: f32,
+    // how far into the next character it is, in characters
+    progress: f32,
+    revealed: usize,
+}
+
+impl Typewriter {
+    pub fn new(text: &str, chars_per_second: f32) -> Self {
+        Typewriter {
+            chars: text.chars().collect(),
+            chars_per_second: chars_per_second,
+            progress: 0.0,
+            revealed: 0,
+        }
+    }
+
+    // moves time on, calling on_reveal with the index and char of every character that
+    // appears, whitespace included, in order. a slow frame can reveal several at once.
+    pub fn update<F>(&mut self, dt: f32, mut on_reveal: F)
+        where F: FnMut(usize, char)
+    {
+        if self.is_finished() {
+            return;
+        }
+        self.progress += dt * self.chars_per_second;
+        while self.progress >= 1.0 && !self.is_finished() {
+            self.progress -= 1.0;
+            on_reveal(self.revealed, self.chars[self.revealed]);
+            self.revealed += 1;
+        }
+    }
+
+    // how many characters are showing, to pass to RichText::draw
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.revealed >= self.chars.len()
+    }
+
+    // shows the rest straight away, without calling back for it, such as when the player
+    // presses a button to skip
+    pub fn finish(&mut self) {
+        self.revealed = self.chars.len();
+        self.progress = 0.0;
+    }
+
+    pub fn restart(&mut self) {
+        self.revealed = 0;
+        self.progress = 0.0;
+    }
+}
+
+fn parse_color(value: &str) -> Result<[f32; 4], MarkupError> {
+    let invalid = || MarkupError::InvalidColor(value.to_string());
+    if !value.starts_with('#') {
+        return Err(invalid());
+    }
+    let digits = &value[1..];
+    if !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    // short forms repeat each digit, so #f80 is #ff8800
+    let (width, count) = match digits.len() {
+        3 => (1, 3),
+        4 => (1, 4),
+        6 => (2, 3),
+        8 => (2, 4),
+        _ => return Err(invalid()),
+    };
+    let mut color = WHITE;
+    for channel in 0..count {
+        let digit = &digits[channel * width..(channel + 1) * width];
+        let value = u8::from_str_radix(digit, 16).map_err(|_| invalid())?;
+        let value = if width == 1 { value * 17 } else { value };
+        color[channel] = value as f32 / 255.0;
+    }
+    Ok(color)
+}
+
+// a repeatable value between -1 and 1 for a seed
+fn noise(seed: u32) -> f32 {
+    let mut x = seed;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+    fn spans(text: &RichText) -> Vec<(usize, usize, Style)> {
+        text.runs().iter().map(|run| (run.start, run.end, run.style)).collect()
+    }
+
+    fn color(value: &str) -> Result<Option<[f32; 4]>, MarkupError> {
+        RichText::parse(&format!("[color={}]x[/color]", value)).map(|text| text.style(0).color)
+    }
+
+    #[test]
+    fn tags_nest() {
+        let text = RichText::parse("a[color=#f00]b[b]c[/b]d[/color]e").unwrap();
+        assert_eq!(text.text(), "abcde");
+        let red = Style { color: Some(RED), ..Style::default() };
+        let bold_red = Style { bold: true, ..red };
+        assert_eq!(spans(&text),
+                   vec![(0, 1, Style::default()),
+                        (1, 2, red),
+                        (2, 3, bold_red),
+                        (3, 4, red),
+                        (4, 5, Style::default())]);
+        assert_eq!(text.style(2), bold_red);
+        assert_eq!(text.style(9), Style::default());
+    }
+
+    #[test]
+    fn empty_tags_leave_no_run() {
+        let text = RichText::parse("[wave][/wave]x[shake][/shake]").unwrap();
+        assert_eq!(spans(&text), vec![(0, 1, Style::default())]);
+    }
+
+    #[test]
+    fn double_brackets_are_literal() {
+        let text = RichText::parse("a[[b] [[[b]c[/b]").unwrap();
+        assert_eq!(text.text(), "a[b] [c");
+        assert!(text.style(6).bold);
+        assert!(!text.style(5).bold);
+    }
+
+    #[test]
+    fn closing_tags_must_match() {
+        assert_eq!(RichText::parse("[b][wave]x[/b][/wave]"),
+                   Err(MarkupError::MismatchedClose {
+                       expected: Some("wave".to_string()),
+                       found: "b".to_string(),
+                   }));
+        assert_eq!(RichText::parse("x[/b]"),
+                   Err(MarkupError::MismatchedClose {
+                       expected: None,
+                       found: "b".to_string(),
+                   }));
+    }
+
+    #[test]
+    fn unclosed_tags_and_brackets_are_errors() {
+        assert_eq!(RichText::parse("[b]x[wave]y[/wave]"),
+                   Err(MarkupError::Unclosed("b".to_string())));
+        assert_eq!(RichText::parse("ab[color=#fff"), Err(MarkupError::UnclosedBracket(2)));
+    }
+
+    #[test]
+    fn unknown_tags_are_errors() {
+        assert_eq!(RichText::parse("[i]x[/i]"), Err(MarkupError::UnknownTag("i".to_string())));
+        assert_eq!(RichText::parse("[b=1]x[/b]"),
+                   Err(MarkupError::UnknownTag("b=1".to_string())));
+        assert_eq!(RichText::parse("[color]x[/color]"),
+                   Err(MarkupError::UnknownTag("color".to_string())));
+    }
+
+    #[test]
+    fn every_color_form_parses() {
+        let orange = Some([1.0, 136.0 / 255.0, 0.0, 1.0]);
+        let faded = Some([1.0, 136.0 / 255.0, 0.0, 136.0 / 255.0]);
+        assert_eq!(color("#f80"), Ok(orange));
+        assert_eq!(color("#F80"), Ok(orange));
+        assert_eq!(color("#f808"), Ok(faded));
+        assert_eq!(color("#ff8800"), Ok(orange));
+        assert_eq!(color("#ff880088"), Ok(faded));
+    }
+
+    #[test]
+    fn malformed_colors_are_errors() {
+        for &value in &["ff8800", "#", "#ff", "#ff880", "#ff88001", "#gg8800", "#ff８800"] {
+            assert_eq!(color(value), Err(MarkupError::InvalidColor(value.to_string())));
+        }
+    }
+
+    #[test]
+    fn shaping_keeps_runs_on_their_chars() {
+        let text = RichText::parse("a[b]e\u{301}[/b]x").unwrap();
+        assert_eq!(text.text().chars().count(), 4);
+        let bold = Style { bold: true, ..Style::default() };
+
+        let shaped = text.shaped();
+        assert_eq!(shaped.text(), "a\u{e9}x");
+        assert_eq!(spans(&shaped),
+                   vec![(0, 1, Style::default()), (1, 2, bold), (2, 3, Style::default())]);
+        assert_eq!(shaped.style(2), Style::default());
+
+        // a mark styled apart from its letter isn't composed into it
+        let apart = RichText::parse("[b]e[/b]\u{301}").unwrap().shaped();
+        assert_eq!(apart.text(), "e\u{301}");
+        assert_eq!(spans(&apart), vec![(0, 1, bold), (1, 2, Style::default())]);
+    }
+}
@@ -0,0 +1,21 @@
+// text is laid out against a font's metrics into glyph rectangles, then drawn as sprites
+// through a SpriteBatch with whatever texture the font keeps its glyphs in
+pub mod bmfont;
+pub mod glyph_cache;
+pub mod layout;
+pub mod markup;
+pub mod truetype;
+
+use batch::Sprite;
+use rect::Rect;
+use text::layout::PlacedGlyph;
+
+// the sprite for a laid out glyph, given where the text's top left is in the world. layouts
+// point y down like the text they came from while the world points it up, as in the examples.
+pub fn glyph_sprite(glyph: &PlacedGlyph, uv: Rect, position: [f32; 2]) -> Sprite {
+    let rect = glyph.rect;
+    Sprite::new([position[0] + rect.x + rect.width / 2.0,
+                 position[1] - rect.y - rect.height / 2.0],
+                [rect.width, rect.height])
+        .with_uv(uv)
+}
@@ -0,0 +1,155 @@
+use rusttype::{self, Font, PositionedGlyph, Scale, point};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use text::layout::{FontMetrics, GlyphMetrics};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    Font(rusttype::Error),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontError::Io(ref err) => write!(f, "{}", err),
+            FontError::Font(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+impl From<io::Error> for FontError {
+    fn from(err: io::Error) -> Self {
+        FontError::Io(err)
+    }
+}
+
+impl From<rusttype::Error> for FontError {
+    fn from(err: rusttype::Error) -> Self {
+        FontError::Font(err)
+    }
+}
+
+// a TrueType or OpenType font, which can be laid out and rasterized at any size
+pub struct TrueTypeFont {
+    font: Font<'static>,
+}
+
+impl TrueTypeFont {
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, FontError> {
+        Ok(TrueTypeFont { font: Font::from_bytes(data)? })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FontError> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        TrueTypeFont::from_bytes(data)
+    }
+
+    // the font's metrics at a pixel size, which is the distance from the highest ascender to
+    // the lowest descender
+    pub fn scaled(&self, size: f32) -> ScaledFont<'_> {
+        let scale = Scale::uniform(size);
+        let v_metrics = self.font.v_metrics(scale);
+        ScaledFont {
+            font: &self.font,
+            scale: scale,
+            ascent: v_metrics.ascent,
+            line_height: (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil(),
+        }
+    }
+}
+
+// a font at one size
+#[derive(Clone, Copy)]
+pub struct ScaledFont<'a> {
+    font: &'a Font<'static>,
+    scale: Scale,
+    ascent: f32,
+    line_height: f32,
+}
+
+impl<'a> ScaledFont<'a> {
+    pub fn size(&self) -> f32 {
+        self.scale.y
+    }
+
+    pub fn has_glyph(&self, character: char) -> bool {
+        self.font.glyph(character).id().0 != 0
+    }
+
+    // the glyph with its pen at the top left of the line, the same place its metrics are
+    // measured from, so what's rasterized lines up with what was laid out
+    pub fn positioned(&self, character: char) -> PositionedGlyph<'static> {
+        self.font
+            .glyph(character)
+            .scaled(self.scale)
+            .positioned(point(0.0, self.ascent))
+    }
+
+    // how far the glyph's image is from its pen position, horizontally, and how wide it is
+    fn extent(&self, character: char) -> Option<(f32, f32)> {
+        self.positioned(character)
+            .pixel_bounding_box()
+            .map(|bounds| (bounds.min.x as f32, bounds.width() as f32))
+    }
+}
+
+impl<'a> FontMetrics for ScaledFont<'a> {
+    fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    fn glyph(&self, character: char) -> Option<GlyphMetrics> {
+        if !self.has_glyph(character) && !character.is_whitespace() {
+            return None;
+        }
+        let glyph = self.positioned(character);
+        let advance = glyph.unpositioned().h_metrics().advance_width;
+        let (offset, size) = match glyph.pixel_bounding_box() {
+            Some(bounds) => {
+                ([bounds.min.x as f32, bounds.min.y as f32],
+                 [bounds.width() as f32, bounds.height() as f32])
+            }
+            None => ([0.0, 0.0], [0.0, 0.0]),
+        };
+        Some(GlyphMetrics {
+            size: size,
+            offset: offset,
+            advance: advance,
+        })
+    }
+
+    // besides the font's own kerning, a combining mark is moved to sit centered over the
+    // glyph before it, which is all the shaping Latin, Greek and Cyrillic need once composed
+    fn kerning(&self, left: char, right: char) -> f32 {
+        if is_combining_mark(right) {
+            let base = self.extent(left);
+            let mark = self.extent(right);
+            if let (Some(base), Some(mark)) = (base, mark) {
+                let advance = self.font
+                    .glyph(left)
+                    .scaled(self.scale)
+                    .h_metrics()
+                    .advance_width;
+                return (base.0 + base.1 / 2.0) - advance - (mark.0 + mark.1 / 2.0);
+            }
+            return 0.0;
+        }
+        self.font.pair_kerning(self.scale, left, right)
+    }
+}
+
+// composes base letters and their combining marks into single characters where unicode has
+// one, so fonts with precomposed glyphs draw them instead of stacking marks. lay out what this
+// returns rather than the original string.
+pub fn shape(text: &str) -> String {
+    text.nfc().collect()
+}
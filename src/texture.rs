@@ -0,0 +1,48 @@
+use gfx;
+use gfx::texture;
+use image;
+use std::io::Cursor;
+
+// stolen from: https://github.com/gfx-rs/gfx/tree/master/examples/blend
+pub fn load_texture<R, F>(factory: &mut F,
+                          data: &[u8])
+                          -> Result<gfx::handle::ShaderResourceView<R, [f32; 4]>, String>
+    where R: gfx::Resources,
+          F: gfx::Factory<R>
+{
+    let img = image::load(Cursor::new(data), image::ImageFormat::Png)
+        .map_err(|e| format!("{:?}", e))?
+        .to_rgba8();
+    texture_from_image(factory, &img)
+}
+
+// uploads an image that is already in memory, such as a packed atlas
+pub fn texture_from_image<R, F>(factory: &mut F,
+                                img: &image::RgbaImage)
+                                -> Result<gfx::handle::ShaderResourceView<R, [f32; 4]>, String>
+    where R: gfx::Resources,
+          F: gfx::Factory<R>
+{
+    let (width, height) = img.dimensions();
+    let kind = texture::Kind::D2(width as texture::Size,
+                                 height as texture::Size,
+                                 texture::AaMode::Single);
+    let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Rgba8>(kind,
+                                                                        texture::Mipmap::Provided,
+                                                                        &[img])
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(view)
+}
+
+// a single opaque white texel, bound by renderers before any real texture is available
+pub fn blank_texture<R, F>(factory: &mut F) -> gfx::handle::ShaderResourceView<R, [f32; 4]>
+    where R: gfx::Resources,
+          F: gfx::Factory<R>
+{
+    let kind = texture::Kind::D2(1, 1, texture::AaMode::Single);
+    let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Rgba8>(kind,
+                                                              texture::Mipmap::Provided,
+                                                              &[&[255, 255, 255, 255]])
+        .unwrap();
+    view
+}
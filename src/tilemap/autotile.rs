@@ -0,0 +1,310 @@
+use tilemap::map::TileMap;
+use tilemap::tiled::Tile;
+
+// the neighbors of a cell as bits of a mask, clockwise from the one above. rows count down
+// from the top like a TileMap's, so north is the row before.
+pub const NORTH: u8 = 1;
+pub const NORTH_EAST: u8 = 2;
+pub const EAST: u8 = 4;
+pub const SOUTH_EAST: u8 = 8;
+pub const SOUTH: u8 = 16;
+pub const SOUTH_WEST: u8 = 32;
+pub const WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+// the offset to each neighbor, in the same order as their bits
+const NEIGHBORS: [(u8, [i32; 2]); 8] = [(NORTH, [0, -1]),
+                                        (NORTH_EAST, [1, -1]),
+                                        (EAST, [1, 0]),
+                                        (SOUTH_EAST, [1, 1]),
+                                        (SOUTH, [0, 1]),
+                                        (SOUTH_WEST, [-1, 1]),
+                                        (WEST, [-1, 0]),
+                                        (NORTH_WEST, [-1, -1])];
+
+// every mask a 47 tile set has a tile for, in the order of its tiles. a corner only matters
+// when both sides next to it are the same terrain too, which leaves 47 of the 256.
+const BLOB_MASKS: [u8; 47] = [0, 1, 4, 5, 7, 16, 17, 20, 21, 23, 28, 29, 31, 64, 65, 68, 69, 71,
+                              80, 81, 84, 85, 87, 92, 93, 95, 112, 113, 116, 117, 119, 124, 125,
+                              127, 193, 197, 199, 209, 213, 215, 221, 223, 241, 245, 247, 253, 255];
+
+// how a tileset draws a terrain's edges
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlobSet {
+    // one tile for each combination of the four sides, numbered by their mask with north as
+    // 1, east 2, south 4 and west 8
+    Sixteen,
+    // also rounds inner corners, one tile for each of BLOB_MASKS in order
+    FortySeven,
+}
+
+impl BlobSet {
+    pub fn tile_count(&self) -> usize {
+        match *self {
+            BlobSet::Sixteen => 16,
+            BlobSet::FortySeven => BLOB_MASKS.len(),
+        }
+    }
+
+    // the tile for a cell, given the mask of which neighbors share its terrain
+    pub fn index(&self, mask: u8) -> usize {
+        match *self {
+            BlobSet::Sixteen => {
+                ((mask & NORTH) | (mask & EAST) >> 1 | (mask & SOUTH) >> 2 |
+                 (mask & WEST) >> 3) as usize
+            }
+            BlobSet::FortySeven => BLOB_MASKS.binary_search(&blob_mask(mask)).unwrap(),
+        }
+    }
+
+    // the neighbors a tile stands for, the opposite of index
+    pub fn mask(&self, index: usize) -> u8 {
+        match *self {
+            BlobSet::Sixteen => {
+                let index = index as u8;
+                (index & 1) | (index & 2) << 1 | (index & 4) << 2 | (index & 8) << 3
+            }
+            BlobSet::FortySeven => BLOB_MASKS[index],
+        }
+    }
+}
+
+// drops the corners whose sides aren't both set, since they can't change how a tile looks
+pub fn blob_mask(mask: u8) -> u8 {
+    let mut blob = mask & (NORTH | EAST | SOUTH | WEST);
+    for &(corner, sides) in &[(NORTH_EAST, NORTH | EAST),
+                              (SOUTH_EAST, SOUTH | EAST),
+                              (SOUTH_WEST, SOUTH | WEST),
+                              (NORTH_WEST, NORTH | WEST)] {
+        if mask & corner != 0 && mask & sides == sides {
+            blob |= corner;
+        }
+    }
+    blob
+}
+
+// keeps the tile index of every cell of a grid of terrain ids up to date with its neighbors.
+// a cell joins up with neighbors of the same terrain, so a tileset's index for that terrain
+// draws it with edges only where the terrain ends.
+pub struct Autotiler {
+    width: u32,
+    height: u32,
+    set: BlobSet,
+    // row by row, starting with the top row
+    terrain: Vec<u32>,
+    indices: Vec<usize>,
+    // whether the terrain carries on past the edges of the grid, so it isn't outlined there
+    edges_connect: bool,
+}
+
+impl Autotiler {
+    // a grid with terrain zero everywhere
+    pub fn new(width: u32, height: u32, set: BlobSet) -> Self {
+        Autotiler::from_terrain(width, height, set, vec![0; (width * height) as usize])
+    }
+
+    pub fn from_terrain(width: u32, height: u32, set: BlobSet, terrain: Vec<u32>) -> Self {
+        assert_eq!(terrain.len(), (width * height) as usize);
+        let mut autotiler = Autotiler {
+            width: width,
+            height: height,
+            set: set,
+            terrain: terrain,
+            indices: vec![0; (width * height) as usize],
+            edges_connect: false,
+        };
+        autotiler.resolve_all();
+        autotiler
+    }
+
+    pub fn with_edges_connect(mut self, edges_connect: bool) -> Self {
+        self.edges_connect = edges_connect;
+        self.resolve_all();
+        self
+    }
+
+    fn resolve_all(&mut self) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = self.resolve(x, y);
+                self.indices[(y * self.width + x) as usize] = index;
+            }
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn set(&self) -> BlobSet {
+        self.set
+    }
+
+    pub fn terrain(&self, x: u32, y: u32) -> u32 {
+        self.terrain[(y * self.width + x) as usize]
+    }
+
+    // the tile for the cell within its terrain's tiles
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        self.indices[(y * self.width + x) as usize]
+    }
+
+    // which neighbors share the cell's terrain
+    pub fn neighbor_mask(&self, x: u32, y: u32) -> u8 {
+        let terrain = self.terrain(x, y);
+        let mut mask = 0;
+        for &(bit, offset) in &NEIGHBORS {
+            let neighbor = [x as i32 + offset[0], y as i32 + offset[1]];
+            let same = if neighbor[0] < 0 || neighbor[1] < 0 || neighbor[0] >= self.width as i32 ||
+                          neighbor[1] >= self.height as i32 {
+                self.edges_connect
+            } else {
+                self.terrain(neighbor[0] as u32, neighbor[1] as u32) == terrain
+            };
+            if same {
+                mask |= bit;
+            }
+        }
+        mask
+    }
+
+    fn resolve(&self, x: u32, y: u32) -> usize {
+        self.set.index(self.neighbor_mask(x, y))
+    }
+
+    // paints a cell and works out its neighbors again, which are the only cells whose tiles
+    // can change. returns the cells whose tiles need redrawing, the painted one included
+    // whenever its terrain changed.
+    pub fn set_terrain(&mut self, x: u32, y: u32, terrain: u32) -> Vec<[u32; 2]> {
+        let mut changed = Vec::new();
+        if x >= self.width || y >= self.height || self.terrain(x, y) == terrain {
+            return changed;
+        }
+        self.terrain[(y * self.width + x) as usize] = terrain;
+
+        let left = x.saturating_sub(1);
+        let top = y.saturating_sub(1);
+        let right = (x + 1).min(self.width - 1);
+        let bottom = (y + 1).min(self.height - 1);
+        for cell_y in top..bottom + 1 {
+            for cell_x in left..right + 1 {
+                let index = self.resolve(cell_x, cell_y);
+                let cell = (cell_y * self.width + cell_x) as usize;
+                if self.indices[cell] != index || (cell_x == x && cell_y == y) {
+                    self.indices[cell] = index;
+                    changed.push([cell_x, cell_y]);
+                }
+            }
+        }
+        changed
+    }
+
+    // writes the tiles for cells, such as those set_terrain returns, into a layer of a map the
+    // same size. tile picks the tile for a terrain and an index into its tiles.
+    pub fn apply<F>(&self, map: &mut TileMap, layer: usize, cells: &[[u32; 2]], mut tile: F)
+        where F: FnMut(u32, usize) -> Tile
+    {
+        for &cell in cells {
+            let tile = tile(self.terrain(cell[0], cell[1]), self.index(cell[0], cell[1]));
+            map.set_tile(layer, cell[0], cell[1], tile);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_mask_has_a_blob_tile() {
+        for mask in 0..256 {
+            let mask = mask as u8;
+            let index = BlobSet::FortySeven.index(mask);
+            assert!(index < BlobSet::FortySeven.tile_count());
+            assert_eq!(BlobSet::FortySeven.mask(index), blob_mask(mask));
+        }
+    }
+
+    #[test]
+    fn index_and_mask_round_trip() {
+        for &set in &[BlobSet::Sixteen, BlobSet::FortySeven] {
+            for index in 0..set.tile_count() {
+                assert_eq!(set.index(set.mask(index)), index, "{:?} tile {}", set, index);
+            }
+        }
+    }
+
+    #[test]
+    fn sixteen_ignores_corners() {
+        let corners = NORTH_EAST | SOUTH_EAST | SOUTH_WEST | NORTH_WEST;
+        for mask in 0..256 {
+            let mask = mask as u8;
+            assert_eq!(BlobSet::Sixteen.index(mask), BlobSet::Sixteen.index(mask & !corners));
+        }
+        assert_eq!(BlobSet::Sixteen.index(NORTH | EAST | SOUTH | WEST), 15);
+    }
+
+    // paints one cell and checks the cells it says changed are exactly the ones whose tiles
+    // did, and that every tile is what working the whole grid out again gives
+    fn check_paint(autotiler: &mut Autotiler, x: u32, y: u32, terrain: u32) {
+        let before: Vec<usize> = autotiler.indices.clone();
+        let mut changed = autotiler.set_terrain(x, y, terrain);
+        changed.sort();
+
+        let fresh = Autotiler::from_terrain(autotiler.width,
+                                            autotiler.height,
+                                            autotiler.set,
+                                            autotiler.terrain.clone())
+            .with_edges_connect(autotiler.edges_connect);
+        assert_eq!(autotiler.indices, fresh.indices);
+
+        let mut expected = Vec::new();
+        for cell_y in 0..autotiler.height {
+            for cell_x in 0..autotiler.width {
+                let cell = (cell_y * autotiler.width + cell_x) as usize;
+                if before[cell] != autotiler.indices[cell] || (cell_x == x && cell_y == y) {
+                    expected.push([cell_x, cell_y]);
+                }
+            }
+        }
+        expected.sort();
+        assert_eq!(changed, expected, "painting {:?}", [x, y]);
+        for cell in &changed {
+            assert!((cell[0] as i32 - x as i32).abs() <= 1);
+            assert!((cell[1] as i32 - y as i32).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn set_terrain_returns_the_changed_neighborhood() {
+        for &set in &[BlobSet::Sixteen, BlobSet::FortySeven] {
+            for &edges_connect in &[false, true] {
+                let mut autotiler = Autotiler::new(5, 4, set).with_edges_connect(edges_connect);
+                // corners, edges and the middle, then painting over and back
+                let cells = [[0, 0], [4, 0], [0, 3], [4, 3], [2, 0], [0, 2], [4, 1], [2, 3],
+                             [2, 1], [1, 1], [3, 2], [2, 2]];
+                for &cell in &cells {
+                    check_paint(&mut autotiler, cell[0], cell[1], 1);
+                }
+                for &cell in &cells {
+                    check_paint(&mut autotiler, cell[0], cell[1], 0);
+                }
+                assert!(autotiler.set_terrain(2, 2, 0).is_empty());
+                assert!(autotiler.set_terrain(5, 0, 1).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn edges_connect_joins_cells_to_the_outside() {
+        let autotiler = Autotiler::new(3, 3, BlobSet::FortySeven);
+        assert_eq!(autotiler.neighbor_mask(0, 0), EAST | SOUTH_EAST | SOUTH);
+        let autotiler = autotiler.with_edges_connect(true);
+        assert_eq!(autotiler.neighbor_mask(0, 0), 255);
+        assert_eq!(autotiler.index(0, 0), BlobSet::FortySeven.tile_count() - 1);
+    }
+}
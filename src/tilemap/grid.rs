@@ -0,0 +1,570 @@
+use camera::Camera2D;
+use rect::Rect;
+
+// which rows or columns of a staggered or hexagonal map are pushed along by half a tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StaggerIndex {
+    Odd,
+    Even,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StaggerAxis {
+    // every other column is pushed down
+    X,
+    // every other row is pushed right
+    Y,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexOrientation {
+    // a corner at the top, in rows with every other one pushed right
+    Pointy,
+    // a flat side at the top, in columns with every other one pushed down
+    Flat,
+}
+
+// how a map's columns and rows are arranged on screen, named the way Tiled names them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapLayout {
+    Orthogonal,
+    // diamond shaped tiles in a diamond shaped map, columns running down to the right and rows
+    // down to the left
+    Isometric,
+    // diamond shaped tiles in a rectangular map, zigzagging along the axis
+    Staggered(StaggerAxis, StaggerIndex),
+    // hexagons whose sides are half as long as the tile is tall, or wide for flat ones, which
+    // is what makes them regular when the tile is about 0.87 times as wide as it's tall
+    Hexagonal(HexOrientation, StaggerIndex),
+}
+
+// a cell of a hexagonal map in axial coordinates, where q runs along one axis of hexagons and
+// r along another, so neighbors and distances work the same everywhere on the map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Axial {
+    pub q: i32,
+    pub r: i32,
+}
+
+// the six ways to step from a hexagon to its neighbors
+const AXIAL_DIRECTIONS: [[i32; 2]; 6] = [[1, 0], [1, -1], [0, -1], [-1, 0], [-1, 1], [0, 1]];
+
+impl Axial {
+    pub fn new(q: i32, r: i32) -> Self {
+        Axial { q: q, r: r }
+    }
+
+    // rounds fractional coordinates to the hexagon they're in
+    pub fn round(q: f32, r: f32) -> Self {
+        let s = -q - r;
+        let mut rounded_q = q.round();
+        let mut rounded_r = r.round();
+        let rounded_s = s.round();
+        let q_error = (rounded_q - q).abs();
+        let r_error = (rounded_r - r).abs();
+        let s_error = (rounded_s - s).abs();
+        // the three always add up to zero, so the one rounded furthest is worked out from the
+        // other two
+        if q_error > r_error && q_error > s_error {
+            rounded_q = -rounded_r - rounded_s;
+        } else if r_error > s_error {
+            rounded_r = -rounded_q - rounded_s;
+        }
+        Axial::new(rounded_q as i32, rounded_r as i32)
+    }
+
+    pub fn neighbors(&self) -> [Axial; 6] {
+        let mut neighbors = [*self; 6];
+        for (neighbor, direction) in neighbors.iter_mut().zip(AXIAL_DIRECTIONS.iter()) {
+            neighbor.q += direction[0];
+            neighbor.r += direction[1];
+        }
+        neighbors
+    }
+
+    // how many steps it is to another hexagon
+    pub fn distance(&self, other: Axial) -> i32 {
+        let q = self.q - other.q;
+        let r = self.r - other.r;
+        (q.abs() + r.abs() + (q + r).abs()) / 2
+    }
+}
+
+// where a map's cells are in the world. cells are counted in columns and rows from the map's
+// top left like they are in Tiled, and the map is laid out in pixels the way Tiled lays it out
+// with y pointing down, then put in the y up world with its top left at origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileGrid {
+    pub layout: MapLayout,
+    // the size of a cell's bounding box
+    pub tile_width: f32,
+    pub tile_height: f32,
+    // in tiles
+    pub width: u32,
+    pub height: u32,
+    pub origin: [f32; 2],
+}
+
+impl TileGrid {
+    // a grid whose map covers from the world's origin up and to the right
+    pub fn new(layout: MapLayout, tile_size: [u32; 2], map_size: [u32; 2]) -> Self {
+        let mut grid = TileGrid {
+            layout: layout,
+            tile_width: tile_size[0] as f32,
+            tile_height: tile_size[1] as f32,
+            width: map_size[0],
+            height: map_size[1],
+            origin: [0.0, 0.0],
+        };
+        grid.origin = [0.0, grid.pixel_size()[1]];
+        grid
+    }
+
+    pub fn with_origin(mut self, origin: [f32; 2]) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    // the size of the box around the whole map
+    pub fn pixel_size(&self) -> [f32; 2] {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let (tile_width, tile_height) = (self.tile_width, self.tile_height);
+        match self.layout {
+            MapLayout::Orthogonal => [width * tile_width, height * tile_height],
+            MapLayout::Isometric => {
+                [(width + height) * tile_width / 2.0, (width + height) * tile_height / 2.0]
+            }
+            MapLayout::Staggered(StaggerAxis::X, _) => {
+                [(width + 1.0) * tile_width / 2.0, (height + 0.5) * tile_height]
+            }
+            MapLayout::Staggered(StaggerAxis::Y, _) => {
+                [(width + 0.5) * tile_width, (height + 1.0) * tile_height / 2.0]
+            }
+            MapLayout::Hexagonal(HexOrientation::Pointy, _) => {
+                [(width + 0.5) * tile_width, (height * 3.0 + 1.0) * tile_height / 4.0]
+            }
+            MapLayout::Hexagonal(HexOrientation::Flat, _) => {
+                [(width * 3.0 + 1.0) * tile_width / 4.0, (height + 0.5) * tile_height]
+            }
+        }
+    }
+
+    pub fn contains(&self, cell: [i32; 2]) -> bool {
+        cell[0] >= 0 && cell[1] >= 0 && cell[0] < self.width as i32 &&
+        cell[1] < self.height as i32
+    }
+
+    // whether a staggered or hexagonal map pushes this row or column along
+    fn is_shifted(&self, index: i32) -> bool {
+        let odd = index & 1 == 1;
+        match self.layout {
+            MapLayout::Staggered(_, StaggerIndex::Odd) |
+            MapLayout::Hexagonal(_, StaggerIndex::Odd) => odd,
+            MapLayout::Staggered(_, StaggerIndex::Even) |
+            MapLayout::Hexagonal(_, StaggerIndex::Even) => !odd,
+            _ => false,
+        }
+    }
+
+    // the middle of a cell in the map's pixels, y pointing down
+    fn pixel_center(&self, cell: [i32; 2]) -> [f32; 2] {
+        let (column, row) = (cell[0] as f32, cell[1] as f32);
+        let (tile_width, tile_height) = (self.tile_width, self.tile_height);
+        match self.layout {
+            MapLayout::Orthogonal => [(column + 0.5) * tile_width, (row + 0.5) * tile_height],
+            MapLayout::Isometric => {
+                [(column - row + self.height as f32) * tile_width / 2.0,
+                 (column + row + 1.0) * tile_height / 2.0]
+            }
+            MapLayout::Staggered(StaggerAxis::X, _) => {
+                let shift = if self.is_shifted(cell[0]) { 1.0 } else { 0.5 };
+                [(column + 1.0) * tile_width / 2.0, (row + shift) * tile_height]
+            }
+            MapLayout::Staggered(StaggerAxis::Y, _) => {
+                let shift = if self.is_shifted(cell[1]) { 1.0 } else { 0.5 };
+                [(column + shift) * tile_width, (row + 1.0) * tile_height / 2.0]
+            }
+            MapLayout::Hexagonal(HexOrientation::Pointy, _) => {
+                let shift = if self.is_shifted(cell[1]) { 1.0 } else { 0.5 };
+                [(column + shift) * tile_width, (row * 3.0 + 2.0) * tile_height / 4.0]
+            }
+            MapLayout::Hexagonal(HexOrientation::Flat, _) => {
+                let shift = if self.is_shifted(cell[0]) { 1.0 } else { 0.5 };
+                [(column * 3.0 + 2.0) * tile_width / 4.0, (row + shift) * tile_height]
+            }
+        }
+    }
+
+    fn to_world(self, pixel: [f32; 2]) -> [f32; 2] {
+        [self.origin[0] + pixel[0], self.origin[1] - pixel[1]]
+    }
+
+    fn to_pixel(self, world: [f32; 2]) -> [f32; 2] {
+        [world[0] - self.origin[0], self.origin[1] - world[1]]
+    }
+
+    // the middle of a cell in the world
+    pub fn cell_center(&self, cell: [i32; 2]) -> [f32; 2] {
+        self.to_world(self.pixel_center(cell))
+    }
+
+    // the world space box around a cell, which tiles are drawn from the bottom left of
+    pub fn cell_rect(&self, cell: [i32; 2]) -> Rect {
+        let center = self.cell_center(cell);
+        Rect::new(center[0] - self.tile_width / 2.0,
+                  center[1] - self.tile_height / 2.0,
+                  self.tile_width,
+                  self.tile_height)
+    }
+
+    // the cell whose shape a world position is inside, even off the edge of the map
+    pub fn cell_at(&self, world: [f32; 2]) -> [i32; 2] {
+        let pixel = self.to_pixel(world);
+        let half_width = self.tile_width / 2.0;
+        let half_height = self.tile_height / 2.0;
+        match self.layout {
+            MapLayout::Orthogonal => {
+                [(pixel[0] / self.tile_width).floor() as i32,
+                 (pixel[1] / self.tile_height).floor() as i32]
+            }
+            MapLayout::Isometric => {
+                // how many half tiles along and down from the top corner of the first tile
+                let across = (pixel[0] - self.height as f32 * half_width) / half_width;
+                let down = pixel[1] / half_height;
+                [((across + down) / 2.0).floor() as i32, ((down - across) / 2.0).floor() as i32]
+            }
+            MapLayout::Staggered(axis, _) => {
+                // the diamonds tile the plane, so the one the position is inside is whichever
+                // of the cells around its rough guess it's the fewest half tiles away from
+                let guess = match axis {
+                    StaggerAxis::X => {
+                        [(pixel[0] / half_width).floor() as i32,
+                         (pixel[1] / self.tile_height).floor() as i32]
+                    }
+                    StaggerAxis::Y => {
+                        [(pixel[0] / self.tile_width).floor() as i32,
+                         (pixel[1] / half_height).floor() as i32]
+                    }
+                };
+                let mut closest = guess;
+                let mut closest_distance = f32::MAX;
+                for row in guess[1] - 1..guess[1] + 2 {
+                    for column in guess[0] - 1..guess[0] + 2 {
+                        let center = self.pixel_center([column, row]);
+                        let distance = ((pixel[0] - center[0]) / half_width).abs() +
+                                       ((pixel[1] - center[1]) / half_height).abs();
+                        if distance < closest_distance {
+                            closest = [column, row];
+                            closest_distance = distance;
+                        }
+                    }
+                }
+                closest
+            }
+            MapLayout::Hexagonal(..) => {
+                let axial = self.axial_at(world);
+                self.from_axial(axial)
+            }
+        }
+    }
+
+    // the cell on the map under a world position, if there is one
+    pub fn pick(&self, world: [f32; 2]) -> Option<[u32; 2]> {
+        let cell = self.cell_at(world);
+        if self.contains(cell) {
+            Some([cell[0] as u32, cell[1] as u32])
+        } else {
+            None
+        }
+    }
+
+    // the cell on the map under a window position, such as the mouse's
+    pub fn pick_screen(&self, camera: &Camera2D, screen: [f32; 2]) -> Option<[u32; 2]> {
+        self.pick(camera.screen_to_world(screen))
+    }
+
+    // where the middle of a cell is in the window
+    pub fn cell_to_screen(&self, camera: &Camera2D, cell: [i32; 2]) -> [f32; 2] {
+        camera.world_to_screen(self.cell_center(cell))
+    }
+
+    // the hexagon a world position is inside, for hexagonal maps. other layouts give the cell
+    // at the position with its column and row as q and r.
+    pub fn axial_at(&self, world: [f32; 2]) -> Axial {
+        let pixel = self.to_pixel(world);
+        match self.layout {
+            MapLayout::Hexagonal(HexOrientation::Pointy, index) => {
+                let offset = if index == StaggerIndex::Even { 1.0 } else { 0.5 };
+                let r = (pixel[1] / self.tile_height - 0.5) * 4.0 / 3.0;
+                let q = pixel[0] / self.tile_width - offset - r / 2.0;
+                Axial::round(q, r)
+            }
+            MapLayout::Hexagonal(HexOrientation::Flat, index) => {
+                let offset = if index == StaggerIndex::Even { 1.0 } else { 0.5 };
+                let q = (pixel[0] / self.tile_width - 0.5) * 4.0 / 3.0;
+                let r = pixel[1] / self.tile_height - offset - q / 2.0;
+                Axial::round(q, r)
+            }
+            _ => {
+                let cell = self.cell_at(world);
+                Axial::new(cell[0], cell[1])
+            }
+        }
+    }
+
+    // the axial coordinates of a cell of a hexagonal map
+    pub fn to_axial(&self, cell: [i32; 2]) -> Axial {
+        let (column, row) = (cell[0], cell[1]);
+        match self.layout {
+            MapLayout::Hexagonal(HexOrientation::Flat, index) => {
+                let shift = if index == StaggerIndex::Even { 1 } else { -1 };
+                Axial::new(column, row - (column + shift * (column & 1)) / 2)
+            }
+            MapLayout::Hexagonal(HexOrientation::Pointy, index) => {
+                let shift = if index == StaggerIndex::Even { 1 } else { -1 };
+                Axial::new(column - (row + shift * (row & 1)) / 2, row)
+            }
+            _ => Axial::new(column, row),
+        }
+    }
+
+    // the cell of a hexagonal map at axial coordinates
+    pub fn from_axial(&self, axial: Axial) -> [i32; 2] {
+        match self.layout {
+            MapLayout::Hexagonal(HexOrientation::Flat, index) => {
+                let shift = if index == StaggerIndex::Even { 1 } else { -1 };
+                [axial.q, axial.r + (axial.q + shift * (axial.q & 1)) / 2]
+            }
+            MapLayout::Hexagonal(HexOrientation::Pointy, index) => {
+                let shift = if index == StaggerIndex::Even { 1 } else { -1 };
+                [axial.q + (axial.r + shift * (axial.r & 1)) / 2, axial.r]
+            }
+            _ => [axial.q, axial.r],
+        }
+    }
+
+    // every cell of the map in the order to draw them, from the back of the screen to the
+    // front, so tiles taller than their cell overlap the ones behind them. things standing on
+    // the map go in front of each other the same way, by how far down the screen they are.
+    pub fn draw_order(&self) -> Vec<[u32; 2]> {
+        let mut cells = Vec::with_capacity((self.width * self.height) as usize);
+        for row in 0..self.height {
+            for column in 0..self.width {
+                cells.push([column, row]);
+            }
+        }
+        self.sort_back_to_front(&mut cells);
+        cells
+    }
+
+    pub fn sort_back_to_front(&self, cells: &mut [[u32; 2]]) {
+        cells.sort_by(|a, b| {
+            let a = self.pixel_center([a[0] as i32, a[1] as i32]);
+            let b = self.pixel_center([b[0] as i32, b[1] as i32]);
+            (a[1], a[0]).partial_cmp(&(b[1], b[0])).unwrap()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDICES: [StaggerIndex; 2] = [StaggerIndex::Odd, StaggerIndex::Even];
+
+    // every layout, with a tile size that suits it, on a five by four map
+    fn grids() -> Vec<TileGrid> {
+        let mut grids = vec![TileGrid::new(MapLayout::Orthogonal, [16, 16], [5, 4]),
+                             TileGrid::new(MapLayout::Isometric, [32, 16], [5, 4])];
+        for &index in &INDICES {
+            for &axis in &[StaggerAxis::X, StaggerAxis::Y] {
+                grids.push(TileGrid::new(MapLayout::Staggered(axis, index), [32, 16], [5, 4]));
+            }
+            grids.push(TileGrid::new(MapLayout::Hexagonal(HexOrientation::Pointy, index),
+                                     [28, 32],
+                                     [5, 4]));
+            grids.push(TileGrid::new(MapLayout::Hexagonal(HexOrientation::Flat, index),
+                                     [32, 28],
+                                     [5, 4]));
+        }
+        grids
+    }
+
+    // cells on the map and a ring of them around it
+    fn cells() -> Vec<[i32; 2]> {
+        let mut cells = Vec::new();
+        for row in -2..6 {
+            for column in -2..7 {
+                cells.push([column, row]);
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn cells_round_trip_through_the_world() {
+        for grid in grids() {
+            for cell in cells() {
+                let center = grid.cell_center(cell);
+                assert_eq!(grid.cell_at(center), cell, "{:?} {:?}", grid.layout, cell);
+
+                // a little way from the middle is still inside the same cell
+                let (nudge_x, nudge_y) = (grid.tile_width * 0.15, grid.tile_height * 0.15);
+                for &(x, y) in &[(nudge_x, 0.0), (-nudge_x, 0.0), (0.0, nudge_y), (0.0, -nudge_y)] {
+                    assert_eq!(grid.cell_at([center[0] + x, center[1] + y]),
+                               cell,
+                               "{:?} {:?} nudged by {:?}",
+                               grid.layout,
+                               cell,
+                               (x, y));
+                }
+
+                let on_map = grid.contains(cell);
+                assert_eq!(grid.pick(center).is_some(), on_map);
+                if on_map {
+                    assert_eq!(grid.pick(center), Some([cell[0] as u32, cell[1] as u32]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hexagons_round_trip_through_axial_coordinates() {
+        for grid in grids() {
+            if let MapLayout::Hexagonal(..) = grid.layout {
+                for cell in cells() {
+                    let axial = grid.to_axial(cell);
+                    assert_eq!(grid.from_axial(axial), cell, "{:?} {:?}", grid.layout, cell);
+                    assert_eq!(grid.axial_at(grid.cell_center(cell)), axial);
+                    // neighbors in axial coordinates are next to each other in the world too
+                    for neighbor in axial.neighbors().iter() {
+                        assert_eq!(axial.distance(*neighbor), 1);
+                        let a = grid.cell_center(cell);
+                        let b = grid.cell_center(grid.from_axial(*neighbor));
+                        let apart = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+                        assert!(apart < grid.tile_width.max(grid.tile_height), "{:?}", cell);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_map_covers_its_pixel_size() {
+        for grid in grids() {
+            let size = grid.pixel_size();
+            for row in 0..grid.height as i32 {
+                for column in 0..grid.width as i32 {
+                    let rect = grid.cell_rect([column, row]);
+                    assert!(rect.x >= -0.001 && rect.x + rect.width <= size[0] + 0.001);
+                    assert!(rect.y >= -0.001 && rect.y + rect.height <= size[1] + 0.001);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draw_order_goes_down_the_screen() {
+        for grid in grids() {
+            let order = grid.draw_order();
+            assert_eq!(order.len(), (grid.width * grid.height) as usize);
+            for row in 0..grid.height {
+                for column in 0..grid.width {
+                    assert!(order.contains(&[column, row]));
+                }
+            }
+            for pair in order.windows(2) {
+                let back = grid.cell_center([pair[0][0] as i32, pair[0][1] as i32]);
+                let front = grid.cell_center([pair[1][0] as i32, pair[1][1] as i32]);
+                assert!(back[1] > front[1] || back[1] == front[1] && back[0] < front[0],
+                        "{:?} drew {:?} before {:?}",
+                        grid.layout,
+                        pair[0],
+                        pair[1]);
+            }
+        }
+
+        let isometric = TileGrid::new(MapLayout::Isometric, [32, 16], [2, 2]);
+        assert_eq!(isometric.draw_order(), vec![[0, 0], [0, 1], [1, 0], [1, 1]]);
+        let staggered = TileGrid::new(MapLayout::Staggered(StaggerAxis::X, StaggerIndex::Odd),
+                                      [32, 16],
+                                      [2, 2]);
+        assert_eq!(staggered.draw_order(), vec![[0, 0], [1, 0], [0, 1], [1, 1]]);
+    }
+
+    #[test]
+    fn picking_on_an_edge_takes_one_side() {
+        // the line between two columns or rows belongs to the one right of or below it
+        let orthogonal = TileGrid::new(MapLayout::Orthogonal, [16, 16], [5, 4]);
+        assert_eq!(orthogonal.pick([16.0, 60.0]), Some([1, 0]));
+        assert_eq!(orthogonal.pick([8.0, 48.0]), Some([0, 1]));
+        assert_eq!(orthogonal.pick([0.0, 64.0]), Some([0, 0]));
+        assert_eq!(orthogonal.pick([80.0, 60.0]), None);
+        assert_eq!(orthogonal.pick([8.0, 0.0]), None);
+
+        // on any layout, a point on the middle of a cell's edge picks the cell on one side of
+        // it or the other
+        for grid in grids() {
+            let edges = edge_middles(&grid);
+            for row in 0..grid.height as i32 {
+                for column in 0..grid.width as i32 {
+                    let cell = [column, row];
+                    let center = grid.cell_center(cell);
+                    let along = |edge: [f32; 2], scale: f32| {
+                        [center[0] + edge[0] * scale, center[1] + edge[1] * scale]
+                    };
+                    for &edge in &edges {
+                        let outside = grid.cell_at(along(edge, 1.1));
+                        assert_eq!(grid.cell_at(along(edge, 0.9)), cell);
+                        assert!(outside != cell);
+                        let picked = grid.cell_at(along(edge, 1.0));
+                        assert!(picked == cell || picked == outside,
+                                "{:?} picked {:?} between {:?} and {:?}",
+                                grid.layout,
+                                picked,
+                                cell,
+                                outside);
+                    }
+                }
+            }
+        }
+    }
+
+    // from a cell's middle to the middle of each of its edges
+    fn edge_middles(grid: &TileGrid) -> Vec<[f32; 2]> {
+        let (half_width, half_height) = (grid.tile_width / 2.0, grid.tile_height / 2.0);
+        let corners = match grid.layout {
+            MapLayout::Orthogonal => {
+                return vec![[half_width, 0.0], [-half_width, 0.0], [0.0, half_height],
+                            [0.0, -half_height]]
+            }
+            MapLayout::Isometric |
+            MapLayout::Staggered(..) => {
+                vec![[half_width, 0.0], [0.0, half_height], [-half_width, 0.0],
+                     [0.0, -half_height]]
+            }
+            MapLayout::Hexagonal(HexOrientation::Pointy, _) => {
+                vec![[0.0, half_height],
+                     [half_width, half_height / 2.0],
+                     [half_width, -half_height / 2.0],
+                     [0.0, -half_height],
+                     [-half_width, -half_height / 2.0],
+                     [-half_width, half_height / 2.0]]
+            }
+            MapLayout::Hexagonal(HexOrientation::Flat, _) => {
+                vec![[half_width, 0.0],
+                     [half_width / 2.0, half_height],
+                     [-half_width / 2.0, half_height],
+                     [-half_width, 0.0],
+                     [-half_width / 2.0, -half_height],
+                     [half_width / 2.0, -half_height]]
+            }
+        };
+        (0..corners.len())
+            .map(|at| {
+                let (a, b) = (corners[at], corners[(at + 1) % corners.len()]);
+                [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,557 @@
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use rect::Rect;
+use tilemap::grid::{MapLayout, TileGrid};
+use tilemap::mesh::{ChunkMesh, push_tile};
+use tilemap::tiled::{Layer, Tile, TiledError, TiledMap, Tileset, find_tileset};
+
+// how many tiles across and down a chunk covers unless asked for another size
+pub const DEFAULT_CHUNK_SIZE: u32 = 16;
+
+struct MapLayer {
+    name: String,
+    // row by row, starting with the top row
+    tiles: Vec<Tile>,
+    visible: bool,
+    opacity: f32,
+    // in pixels, y pointing down like Tiled's
+    offset: [f32; 2],
+}
+
+// a square of one layer's tiles, meshed together and drawn or culled as one
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    pub layer: usize,
+    // in chunks from the map's top left
+    pub position: [u32; 2],
+    // the world space box its tiles can cover, whether or not they're empty
+    pub bounds: Rect,
+    // one per tileset the chunk's tiles use, empty until the chunk is first seen
+    pub meshes: Vec<ChunkMesh>,
+    // goes up every time the meshes are rebuilt, so whatever uploaded them knows to again
+    pub version: u32,
+    dirty: bool,
+}
+
+// what the last prepare did
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileMapStats {
+    pub chunks: usize,
+    // chunks of visible layers that overlap the view
+    pub visible: usize,
+    // visible chunks whose tiles had changed since they were last meshed
+    pub rebuilt: usize,
+}
+
+// a grid of tile layers that can be edited while the game runs. each layer is split into
+// chunks, and a chunk's quads are only built again after one of its tiles changes, and only
+// once it's on screen.
+pub struct TileMap {
+    layout: MapLayout,
+    // in tiles
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    chunk_size: u32,
+    // sorted by first gid
+    tilesets: Vec<Tileset>,
+    layers: Vec<MapLayer>,
+    // every layer's chunks by layer, then row, then column
+    chunks: Vec<Chunk>,
+    // the chunks by index in the order they're drawn: by layer, then from the back of the
+    // screen to the front
+    order: Vec<usize>,
+    visible: Vec<usize>,
+}
+
+impl TileMap {
+    pub fn new(width: u32,
+               height: u32,
+               tile_width: u32,
+               tile_height: u32,
+               mut tilesets: Vec<Tileset>)
+               -> Self {
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+        TileMap {
+            layout: MapLayout::Orthogonal,
+            width: width,
+            height: height,
+            tile_width: tile_width,
+            tile_height: tile_height,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            tilesets: tilesets,
+            layers: Vec::new(),
+            chunks: Vec::new(),
+            order: Vec::new(),
+            visible: Vec::new(),
+        }
+    }
+
+    // copies a Tiled map's tile layers, hidden ones included, leaving its objects behind. every
+    // layer has to cover the whole map.
+    pub fn from_tiled(map: &TiledMap) -> Result<Self, TiledError> {
+        let mut tile_map = TileMap::new(map.width,
+                                        map.height,
+                                        map.tile_width,
+                                        map.tile_height,
+                                        map.tilesets.clone())
+            .with_layout(map.layout);
+        for layer in &map.layers {
+            if let Layer::Tiles(ref layer) = *layer {
+                if layer.width != map.width || layer.height != map.height ||
+                   layer.tiles.len() != (map.width * map.height) as usize {
+                    let size = format!("layer {} is {}x{} tiles, the map {}x{}",
+                                       layer.name,
+                                       layer.width,
+                                       layer.height,
+                                       map.width,
+                                       map.height);
+                    return Err(TiledError::Invalid(size));
+                }
+                // the offset moves the chunks' bounds, so the layer is filled in before they're
+                // made
+                tile_map.push_layer(MapLayer {
+                    name: layer.name.clone(),
+                    tiles: layer.tiles.clone(),
+                    visible: layer.visible,
+                    opacity: layer.opacity,
+                    offset: layer.offset,
+                });
+            }
+        }
+        Ok(tile_map)
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self.recreate_chunks();
+        self
+    }
+
+    pub fn with_layout(mut self, layout: MapLayout) -> Self {
+        self.layout = layout;
+        self.recreate_chunks();
+        self
+    }
+
+    fn recreate_chunks(&mut self) {
+        self.chunks.clear();
+        self.order.clear();
+        self.visible.clear();
+        for layer in 0..self.layers.len() {
+            self.add_chunks(layer);
+        }
+    }
+
+    // adds an empty layer drawn over the rest and returns its index
+    pub fn add_layer<S: Into<String>>(&mut self, name: S) -> usize {
+        let tiles = vec![Tile::EMPTY; (self.width * self.height) as usize];
+        self.push_layer(MapLayer {
+            name: name.into(),
+            tiles: tiles,
+            visible: true,
+            opacity: 1.0,
+            offset: [0.0, 0.0],
+        })
+    }
+
+    fn push_layer(&mut self, layer: MapLayer) -> usize {
+        self.layers.push(layer);
+        let layer = self.layers.len() - 1;
+        self.add_chunks(layer);
+        layer
+    }
+
+    fn add_chunks(&mut self, layer: usize) {
+        let columns = self.width.div_ceil(self.chunk_size);
+        let rows = self.height.div_ceil(self.chunk_size);
+        let first = self.chunks.len();
+        for row in 0..rows {
+            for column in 0..columns {
+                let bounds = self.chunk_bounds(layer, [column, row]);
+                self.chunks.push(Chunk {
+                    layer: layer,
+                    position: [column, row],
+                    bounds: bounds,
+                    meshes: Vec::new(),
+                    version: 0,
+                    dirty: true,
+                });
+            }
+        }
+
+        // the top of a chunk's bounds is how far back it is, even when its tiles zigzag
+        let mut order: Vec<usize> = (first..self.chunks.len()).collect();
+        {
+            let chunks = &self.chunks;
+            order.sort_by(|&a, &b| {
+                let a = &chunks[a].bounds;
+                let b = &chunks[b].bounds;
+                (-a.bottom(), a.x).partial_cmp(&(-b.bottom(), b.x)).unwrap()
+            });
+        }
+        self.order.extend(order);
+    }
+
+    pub fn layout(&self) -> MapLayout {
+        self.layout
+    }
+
+    // where the map's cells are in the world
+    pub fn grid(&self) -> TileGrid {
+        TileGrid::new(self.layout,
+                      [self.tile_width, self.tile_height],
+                      [self.width, self.height])
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tile_size(&self) -> [u32; 2] {
+        [self.tile_width, self.tile_height]
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    pub fn tilesets(&self) -> &[Tileset] {
+        &self.tilesets
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    // hidden layers keep their chunks but they're never visible
+    pub fn set_layer_visible(&mut self, layer: usize, visible: bool) {
+        self.layers[layer].visible = visible;
+    }
+
+    // the tile at a column and row, counting rows from the top
+    pub fn tile(&self, layer: usize, x: u32, y: u32) -> Tile {
+        if x >= self.width || y >= self.height {
+            return Tile::EMPTY;
+        }
+        self.layers[layer].tiles[(y * self.width + x) as usize]
+    }
+
+    // changes a tile, marking its chunk to be meshed again. returns whether anything changed.
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: Tile) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let cell = &mut self.layers[layer].tiles[(y * self.width + x) as usize];
+        if *cell == tile {
+            return false;
+        }
+        *cell = tile;
+        let chunk = self.chunk_index(layer, x / self.chunk_size, y / self.chunk_size);
+        self.chunks[chunk].dirty = true;
+        true
+    }
+
+    fn chunk_index(&self, layer: usize, column: u32, row: u32) -> usize {
+        let columns = self.width.div_ceil(self.chunk_size);
+        let rows = self.height.div_ceil(self.chunk_size);
+        layer * (columns * rows) as usize + (row * columns + column) as usize
+    }
+
+    // where the bottom left of the box around a cell is in the world, which is where its tile
+    // is drawn from. rows count down from the top of the map like they do in Tiled, while the
+    // world points y up, so row zero ends up highest.
+    pub fn cell_origin(&self, x: u32, y: u32) -> [f32; 2] {
+        let rect = self.grid().cell_rect([x as i32, y as i32]);
+        [rect.x, rect.y]
+    }
+
+    // the column and row of the cell under a world position, such as the mouse's
+    pub fn cell_at(&self, position: [f32; 2]) -> Option<[u32; 2]> {
+        self.grid().pick(position)
+    }
+
+    // the chunk's cells plus however far its tileset's biggest tiles can stick out of them,
+    // up and to the right
+    fn chunk_bounds(&self, layer: usize, position: [u32; 2]) -> Rect {
+        let largest = self.tilesets
+            .iter()
+            .fold(0, |largest, tileset| largest.max(tileset.tile_width).max(tileset.tile_height));
+        let overhang = [largest.saturating_sub(self.tile_width) as f32,
+                        largest.saturating_sub(self.tile_height) as f32];
+
+        let grid = self.grid();
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for cell in self.chunk_cells(position) {
+            let rect = grid.cell_rect([cell[0] as i32, cell[1] as i32]);
+            min = [min[0].min(rect.x), min[1].min(rect.y)];
+            max = [max[0].max(rect.right() + overhang[0]),
+                   max[1].max(rect.bottom() + overhang[1])];
+        }
+        let offset = self.layers[layer].offset;
+        Rect::new(min[0] + offset[0],
+                  min[1] - offset[1],
+                  max[0] - min[0],
+                  max[1] - min[1])
+    }
+
+    // a chunk's cells, in the order they're drawn
+    fn chunk_cells(&self, position: [u32; 2]) -> Vec<[u32; 2]> {
+        let first = [position[0] * self.chunk_size, position[1] * self.chunk_size];
+        let last = [(first[0] + self.chunk_size).min(self.width),
+                    (first[1] + self.chunk_size).min(self.height)];
+        let mut cells = Vec::with_capacity((self.chunk_size * self.chunk_size) as usize);
+        for y in first[1]..last[1] {
+            for x in first[0]..last[0] {
+                cells.push([x, y]);
+            }
+        }
+        if self.layout != MapLayout::Orthogonal {
+            self.grid().sort_back_to_front(&mut cells);
+        }
+        cells
+    }
+
+    // finds the chunks of visible layers that overlap view, a world space rectangle such as
+    // Camera2D::visible_rect or visible_area, and meshes any of them whose tiles changed
+    pub fn prepare(&mut self, view: Rect) -> TileMapStats {
+        self.visible.clear();
+        let mut rebuilt = 0;
+        for &index in &self.order {
+            let chunk = &self.chunks[index];
+            if !self.layers[chunk.layer].visible || !overlaps(&chunk.bounds, &view) {
+                continue;
+            }
+            if chunk.dirty {
+                let meshes = self.build_meshes(chunk.layer, chunk.position);
+                let chunk = &mut self.chunks[index];
+                chunk.meshes = meshes;
+                chunk.version = chunk.version.wrapping_add(1);
+                chunk.dirty = false;
+                rebuilt += 1;
+            }
+            self.visible.push(index);
+        }
+
+        TileMapStats {
+            chunks: self.chunks.len(),
+            visible: self.visible.len(),
+            rebuilt: rebuilt,
+        }
+    }
+
+    fn build_meshes(&self, layer: usize, position: [u32; 2]) -> Vec<ChunkMesh> {
+        let map_layer = &self.layers[layer];
+        let tint = [1.0, 1.0, 1.0, map_layer.opacity];
+        let mut meshes: Vec<ChunkMesh> = (0..self.tilesets.len())
+            .map(|tileset| {
+                ChunkMesh {
+                    tileset: tileset,
+                    vertices: Vec::new(),
+                    indices: Vec::new(),
+                }
+            })
+            .collect();
+
+        let grid = self.grid();
+        for cell in self.chunk_cells(position) {
+            let (x, y) = (cell[0], cell[1]);
+            let tile = self.tile(layer, x, y);
+            let tileset = match find_tileset(&self.tilesets, tile.gid()) {
+                Some((tileset, _)) => tileset,
+                None => continue,
+            };
+            let rect = grid.cell_rect([x as i32, y as i32]);
+            let mesh = &mut meshes[tileset];
+            push_tile(&mut mesh.vertices,
+                      &mut mesh.indices,
+                      &self.tilesets[tileset],
+                      tile,
+                      [rect.x + map_layer.offset[0], rect.y - map_layer.offset[1]],
+                      tint);
+        }
+
+        meshes.retain(|mesh| !mesh.indices.is_empty());
+        meshes
+    }
+
+    // the chunks found by the last prepare, by index, in the order they should be drawn
+    pub fn visible_chunks(&self) -> &[usize] {
+        &self.visible
+    }
+
+    pub fn chunk(&self, index: usize) -> &Chunk {
+        &self.chunks[index]
+    }
+
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+}
+
+// the world space box a projection shows, found by taking the corners of clip space back
+// through it. rotated projections give the box around the rotated view.
+pub fn visible_area(projection: Matrix4<f32>) -> Rect {
+    let inverse = match projection.invert() {
+        Some(inverse) => inverse,
+        None => return Rect::new(0.0, 0.0, 0.0, 0.0),
+    };
+    let mut min = [f32::MAX; 2];
+    let mut max = [f32::MIN; 2];
+    for &corner in &[[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]] {
+        let world = inverse * Vector4::new(corner[0], corner[1], 0.0, 1.0);
+        for axis in 0..2 {
+            min[axis] = min[axis].min(world[axis] / world.w);
+            max[axis] = max[axis].max(world[axis] / world.w);
+        }
+    }
+    Rect::new(min[0], min[1], max[0] - min[0], max[1] - min[1])
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tilemap::tiled::{Properties, TileLayer};
+
+    // eight tiles square in chunks of four, so each layer is two chunks by two. the map covers
+    // x and y from 0 to 128, with row zero at the top.
+    fn small_map() -> TileMap {
+        let mut map = TileMap::new(8, 8, 16, 16, Vec::new()).with_chunk_size(4);
+        map.add_layer("ground");
+        map
+    }
+
+    fn everything() -> Rect {
+        Rect::new(-1000.0, -1000.0, 2000.0, 2000.0)
+    }
+
+    fn stats(visible: usize, rebuilt: usize) -> TileMapStats {
+        TileMapStats {
+            chunks: 4,
+            visible: visible,
+            rebuilt: rebuilt,
+        }
+    }
+
+    fn tile_layer(name: &str, size: [u32; 2], offset: [f32; 2]) -> Layer {
+        Layer::Tiles(TileLayer {
+            name: name.to_string(),
+            width: size[0],
+            height: size[1],
+            tiles: (0..size[0] * size[1]).map(Tile).collect(),
+            visible: true,
+            opacity: 1.0,
+            offset: offset,
+            properties: Properties::new(),
+        })
+    }
+
+    fn tiled_map(layers: Vec<Layer>) -> TiledMap {
+        TiledMap {
+            layout: MapLayout::Orthogonal,
+            width: 8,
+            height: 8,
+            tile_width: 16,
+            tile_height: 16,
+            tilesets: Vec::new(),
+            layers: layers,
+            properties: Properties::new(),
+        }
+    }
+
+    #[test]
+    fn a_view_inside_one_chunk_builds_only_it() {
+        let mut map = small_map();
+        assert_eq!(map.prepare(Rect::new(8.0, 104.0, 16.0, 16.0)), stats(1, 1));
+        assert_eq!(map.chunk(map.visible_chunks()[0]).position, [0, 0]);
+        assert_eq!(map.prepare(Rect::new(8.0, 104.0, 16.0, 16.0)), stats(1, 0));
+    }
+
+    #[test]
+    fn a_view_over_the_corners_finds_every_chunk_it_touches() {
+        let mut map = small_map();
+        assert_eq!(map.prepare(Rect::new(56.0, 56.0, 16.0, 16.0)), stats(4, 4));
+        // across the map's top left corner only the first chunk is there to see
+        assert_eq!(map.prepare(Rect::new(-50.0, 98.0, 70.0, 100.0)), stats(1, 0));
+        assert_eq!(map.chunk(map.visible_chunks()[0]).position, [0, 0]);
+    }
+
+    #[test]
+    fn a_view_outside_the_map_finds_nothing() {
+        let mut map = small_map();
+        assert_eq!(map.prepare(Rect::new(200.0, 200.0, 10.0, 10.0)), stats(0, 0));
+        // touching an edge isn't overlapping it
+        assert_eq!(map.prepare(Rect::new(128.0, 64.0, 10.0, 10.0)), stats(0, 0));
+        assert!(map.visible_chunks().is_empty());
+    }
+
+    #[test]
+    fn set_tile_rebuilds_its_chunk_once_it_is_seen() {
+        let mut map = small_map();
+        map.prepare(everything());
+        assert!(map.set_tile(0, 5, 1, Tile(1)));
+        assert!(!map.set_tile(0, 5, 1, Tile(1)));
+        assert!(!map.set_tile(0, 8, 1, Tile(1)));
+
+        // the changed chunk is the second one, off to the right of this view
+        assert_eq!(map.prepare(Rect::new(8.0, 104.0, 16.0, 16.0)), stats(1, 0));
+        assert_eq!(map.prepare(everything()), stats(4, 1));
+        let versions: Vec<u32> = map.chunks().iter().map(|chunk| chunk.version).collect();
+        assert_eq!(versions, vec![1, 2, 1, 1]);
+        assert_eq!(map.tile(0, 5, 1), Tile(1));
+    }
+
+    #[test]
+    fn hidden_layers_are_never_visible() {
+        let mut map = small_map();
+        map.add_layer("hidden");
+        map.set_layer_visible(1, false);
+        let found = map.prepare(everything());
+        assert_eq!((found.chunks, found.visible, found.rebuilt), (8, 4, 4));
+        assert!(map.visible_chunks().iter().all(|&index| map.chunk(index).layer == 0));
+
+        // its chunks are meshed the first time it's shown
+        map.set_layer_visible(1, true);
+        let found = map.prepare(everything());
+        assert_eq!((found.chunks, found.visible, found.rebuilt), (8, 8, 4));
+    }
+
+    #[test]
+    fn from_tiled_keeps_layer_offsets_in_the_chunk_bounds() {
+        let map = tiled_map(vec![tile_layer("ground", [8, 8], [0.0, 0.0]),
+                                 tile_layer("shifted", [8, 8], [64.0, 16.0])]);
+        let mut tile_map = TileMap::from_tiled(&map).unwrap().with_chunk_size(4);
+        assert_eq!(tile_map.tile(1, 3, 2), Tile(19));
+
+        // tiled's offsets point y down, the world's up
+        assert_eq!(tile_map.chunk(4).bounds, Rect::new(64.0, 48.0, 64.0, 64.0));
+        let found = tile_map.prepare(Rect::new(136.0, 72.0, 16.0, 16.0));
+        assert_eq!((found.visible, found.rebuilt), (1, 1));
+        let chunk = tile_map.chunk(tile_map.visible_chunks()[0]);
+        assert_eq!((chunk.layer, chunk.position), (1, [1, 0]));
+    }
+
+    #[test]
+    fn from_tiled_refuses_layers_of_another_size() {
+        let map = tiled_map(vec![tile_layer("ground", [8, 8], [0.0, 0.0]),
+                                 tile_layer("small", [4, 8], [0.0, 0.0])]);
+        match TileMap::from_tiled(&map) {
+            Err(TiledError::Invalid(_)) => {}
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("a layer smaller than the map was accepted"),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use batch::BatchVertex;
+use tilemap::tiled::{Tile, Tileset};
+
+// the quads of one tileset's tiles in one chunk, ready to upload and draw with the tileset's
+// texture
+#[derive(Clone, Debug)]
+pub struct ChunkMesh {
+    pub tileset: usize,
+    pub vertices: Vec<BatchVertex>,
+    pub indices: Vec<u32>,
+}
+
+// writes the quad for a tile with its cell's bottom left at origin. tiles bigger than the
+// map's grid stick out above and to the right of their cell, the way Tiled draws them.
+pub fn push_tile(vertices: &mut Vec<BatchVertex>,
+                 indices: &mut Vec<u32>,
+                 tileset: &Tileset,
+                 tile: Tile,
+                 origin: [f32; 2],
+                 tint: [f32; 4]) {
+    let uv = tileset.uv(tile.gid() - tileset.first_gid);
+    let (width, height) = if tile.flipped_diagonally() {
+        (tileset.tile_height as f32, tileset.tile_width as f32)
+    } else {
+        (tileset.tile_width as f32, tileset.tile_height as f32)
+    };
+
+    // bottom left, bottom right, top right and top left, like SpriteBatch's quads, each with
+    // where it is across and down the tile's image before flipping
+    let corners = [([0.0, 0.0], [0.0, 1.0]),
+                   ([width, 0.0], [1.0, 1.0]),
+                   ([width, height], [1.0, 0.0]),
+                   ([0.0, height], [0.0, 0.0])];
+
+    let base = vertices.len() as u32;
+    for &(corner, image) in &corners {
+        // Tiled flips diagonally first, then horizontally and vertically, so finding where a
+        // corner's texel comes from undoes them in the opposite order
+        let mut image: [f32; 2] = image;
+        if tile.flipped_vertically() {
+            image[1] = 1.0 - image[1];
+        }
+        if tile.flipped_horizontally() {
+            image[0] = 1.0 - image[0];
+        }
+        if tile.flipped_diagonally() {
+            image = [image[1], image[0]];
+        }
+        vertices.push(BatchVertex {
+            position: [origin[0] + corner[0], origin[1] + corner[1]],
+            tex_coord: [uv.x + image[0] * uv.width, uv.y + image[1] * uv.height],
+            color: tint,
+        });
+    }
+    indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+}
@@ -0,0 +1,9 @@
+// maps made in Tiled are read into a TiledMap and copied into a TileMap, whose layers are split
+// into chunks of quads that are only rebuilt when their tiles change and only drawn when in view.
+// a TileGrid places the cells of orthogonal, isometric and hexagonal maps in the world.
+pub mod autotile;
+pub mod grid;
+pub mod map;
+pub mod mesh;
+pub mod renderer;
+pub mod tiled;
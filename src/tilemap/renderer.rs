@@ -0,0 +1,158 @@
+use batch::{BatchView, BatchVertex, batch_pipe};
+use cgmath;
+use gfx;
+use gfx::handle::{Buffer, RenderTargetView, ShaderResourceView};
+use gfx::texture::{FilterMethod, SamplerInfo, WrapMode};
+use gfx::traits::FactoryExt;
+use sprite::{full_scissor, pixel_projection, viewport_scissor};
+use texture::blank_texture;
+use tilemap::map::{TileMap, TileMapStats, visible_area};
+use viewport::Viewport;
+use ColorFormat;
+
+// a chunk's mesh for one tileset once it's on the gpu
+struct UploadedMesh<R: gfx::Resources> {
+    vbuf: Buffer<R, BatchVertex>,
+    slice: gfx::Slice<R>,
+    tileset: usize,
+}
+
+// the buffers made from one version of a chunk's meshes
+struct UploadedChunk<R: gfx::Resources> {
+    version: u32,
+    meshes: Vec<UploadedMesh<R>>,
+}
+
+// draws the chunks of a TileMap that are in view, one draw per chunk and tileset, with the
+// same shaders as the sprite batch so tiles and sprites can share a projection. each chunk's
+// buffers are kept and only made again after the map has rebuilt its meshes.
+pub struct TileMapRenderer<R: gfx::Resources> {
+    pso: gfx::PipelineState<R, batch_pipe::Meta>,
+    data: batch_pipe::Data<R>,
+    // by chunk index, for the chunks that have been drawn
+    uploaded: Vec<Option<UploadedChunk<R>>>,
+    // one per tileset, in the map's order
+    textures: Vec<ShaderResourceView<R, [f32; 4]>>,
+    projection: cgmath::Matrix4<f32>,
+}
+
+impl<R: gfx::Resources> TileMapRenderer<R> {
+    // textures are the images of map's tilesets, in the same order as map.tilesets()
+    pub fn new<F>(factory: &mut F,
+                  out: RenderTargetView<R, ColorFormat>,
+                  width: f32,
+                  height: f32,
+                  map: &TileMap,
+                  textures: Vec<ShaderResourceView<R, [f32; 4]>>)
+                  -> Result<Self, String>
+        where F: gfx::Factory<R>
+    {
+        if textures.len() < map.tilesets().len() {
+            return Err(format!("the map has {} tilesets but only {} textures were given for them",
+                               map.tilesets().len(),
+                               textures.len()));
+        }
+
+        let pso = factory.create_pipeline_simple(include_bytes!("../shaders/batch_vert.glsl"),
+                                    include_bytes!("../shaders/batch_frag.glsl"),
+                                    batch_pipe::new())
+            .map_err(|e| format!("{:?}", e))?;
+
+        // neighboring tiles in a tileset would bleed into each other's edges if filtered
+        let sampler =
+            factory.create_sampler(SamplerInfo::new(FilterMethod::Scale, WrapMode::Clamp));
+        let placeholder = blank_texture(factory);
+
+        let data = batch_pipe::Data {
+            vbuf: factory.create_vertex_buffer(&[]),
+            view: factory.create_constant_buffer(1),
+            texture_sampler: (placeholder, sampler),
+            out: out,
+            scissor: full_scissor(width, height),
+        };
+
+        Ok(TileMapRenderer {
+            pso: pso,
+            data: data,
+            uploaded: Vec::new(),
+            textures: textures,
+            projection: pixel_projection(width, height),
+        })
+    }
+
+    // chunks outside what this projection shows aren't drawn
+    pub fn set_projection(&mut self, projection: cgmath::Matrix4<f32>) {
+        self.projection = projection;
+    }
+
+    pub fn set_viewport(&mut self, viewport: &Viewport) {
+        self.set_projection(viewport.projection());
+        self.data.scissor = viewport_scissor(viewport);
+    }
+
+    pub fn set_target(&mut self, out: RenderTargetView<R, ColorFormat>) {
+        self.data.out = out;
+    }
+
+    pub fn clear<C>(&self, encoder: &mut gfx::Encoder<R, C>, color: [f32; 4])
+        where C: gfx::CommandBuffer<R>
+    {
+        encoder.clear(&self.data.out, color);
+    }
+
+    // culls the map against the projection, uploads the visible chunks that were rebuilt and
+    // draws the visible ones in the map's layer order
+    pub fn draw<F, C>(&mut self,
+                      factory: &mut F,
+                      encoder: &mut gfx::Encoder<R, C>,
+                      map: &mut TileMap)
+                      -> TileMapStats
+        where F: gfx::Factory<R>,
+              C: gfx::CommandBuffer<R>
+    {
+        let stats = map.prepare(visible_area(self.projection));
+        while self.uploaded.len() < map.chunks().len() {
+            self.uploaded.push(None);
+        }
+
+        let view = BatchView { projection: self.projection.into() };
+        encoder.update_constant_buffer(&self.data.view, &view);
+
+        for &index in map.visible_chunks() {
+            let chunk = map.chunk(index);
+            let current = match self.uploaded[index] {
+                Some(ref uploaded) => uploaded.version == chunk.version,
+                None => false,
+            };
+            if !current {
+                let meshes = chunk.meshes
+                    .iter()
+                    .map(|mesh| {
+                        let (vbuf, slice) =
+                            factory.create_vertex_buffer_with_slice(&mesh.vertices,
+                                                                    &mesh.indices[..]);
+                        UploadedMesh {
+                            vbuf: vbuf,
+                            slice: slice,
+                            tileset: mesh.tileset,
+                        }
+                    })
+                    .collect();
+                self.uploaded[index] = Some(UploadedChunk {
+                    version: chunk.version,
+                    meshes: meshes,
+                });
+            }
+
+            if let Some(ref uploaded) = self.uploaded[index] {
+                for mesh in &uploaded.meshes {
+                    self.data.vbuf = mesh.vbuf.clone();
+                    self.data.texture_sampler.0 = self.textures[mesh.tileset].clone();
+                    encoder.draw(&mesh.slice, &self.pso, &self.data);
+                }
+            }
+        }
+
+        stats
+    }
+}
@@ -0,0 +1,1341 @@
+use atlas::PixelRect;
+use base64;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use image::{self, RgbaImage};
+use rect::Rect;
+use serde_json::{self, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use tilemap::grid::{HexOrientation, MapLayout, StaggerAxis, StaggerIndex};
+use xml::reader::{self, EventReader, XmlEvent};
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+// only used by hexagonal maps, cleared along with the others to get at the gid
+const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const FLAGS: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY |
+                   ROTATED_HEXAGONAL_120;
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(io::Error),
+    Xml(reader::Error),
+    Json(serde_json::Error),
+    Base64(base64::DecodeError),
+    Image(image::ImageError),
+    // something the file needs is missing or can't be read
+    Invalid(String),
+    // something Tiled can write that this loader doesn't handle
+    Unsupported(String),
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TiledError::Io(ref err) => write!(f, "{}", err),
+            TiledError::Xml(ref err) => write!(f, "{}", err),
+            TiledError::Json(ref err) => write!(f, "{}", err),
+            TiledError::Base64(ref err) => write!(f, "{}", err),
+            TiledError::Image(ref err) => write!(f, "{}", err),
+            TiledError::Invalid(ref what) => write!(f, "invalid map: {}", what),
+            TiledError::Unsupported(ref what) => write!(f, "unsupported {}", what),
+        }
+    }
+}
+
+impl Error for TiledError {}
+
+impl From<io::Error> for TiledError {
+    fn from(err: io::Error) -> Self {
+        TiledError::Io(err)
+    }
+}
+
+impl From<reader::Error> for TiledError {
+    fn from(err: reader::Error) -> Self {
+        TiledError::Xml(err)
+    }
+}
+
+impl From<serde_json::Error> for TiledError {
+    fn from(err: serde_json::Error) -> Self {
+        TiledError::Json(err)
+    }
+}
+
+impl From<base64::DecodeError> for TiledError {
+    fn from(err: base64::DecodeError) -> Self {
+        TiledError::Base64(err)
+    }
+}
+
+impl From<image::ImageError> for TiledError {
+    fn from(err: image::ImageError) -> Self {
+        TiledError::Image(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    // rgba, Tiled writes these as #aarrggbb
+    Color([u8; 4]),
+    // a path relative to the file the property is in
+    File(String),
+    // the id of another object on the map
+    Object(u32),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+// a cell of a tile layer: which tile is there and how it's flipped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tile(pub u32);
+
+impl Tile {
+    pub const EMPTY: Tile = Tile(0);
+
+    // the global id, which picks the tileset and a tile in it. zero for an empty cell.
+    pub fn gid(&self) -> u32 {
+        self.0 & !FLAGS
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gid() == 0
+    }
+
+    pub fn flipped_horizontally(&self) -> bool {
+        self.0 & FLIPPED_HORIZONTALLY != 0
+    }
+
+    pub fn flipped_vertically(&self) -> bool {
+        self.0 & FLIPPED_VERTICALLY != 0
+    }
+
+    // swaps x and y, applied before the other two flips. with them it's how Tiled rotates.
+    pub fn flipped_diagonally(&self) -> bool {
+        self.0 & FLIPPED_DIAGONALLY != 0
+    }
+}
+
+// a grid of tiles cut out of one image, with margin pixels around the edge of the image and
+// spacing pixels between tiles
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tileset {
+    // the gid of the first tile, the rest follow on from it
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    // relative to the map file
+    pub image: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    // by local tile id, only for tiles that have any
+    pub tile_properties: HashMap<u32, Properties>,
+    pub properties: Properties,
+}
+
+impl Tileset {
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid < self.first_gid + self.tile_count
+    }
+
+    // where a tile is in the image, by its id within the tileset
+    pub fn region(&self, local_id: u32) -> PixelRect {
+        let columns = self.columns.max(1);
+        PixelRect {
+            x: self.margin + (local_id % columns) * (self.tile_width + self.spacing),
+            y: self.margin + (local_id / columns) * (self.tile_height + self.spacing),
+            width: self.tile_width,
+            height: self.tile_height,
+        }
+    }
+
+    // the region in normalized texture coordinates
+    pub fn uv(&self, local_id: u32) -> Rect {
+        let region = self.region(local_id);
+        let width = self.image_width as f32;
+        let height = self.image_height as f32;
+        Rect::new(region.x as f32 / width,
+                  region.y as f32 / height,
+                  region.width as f32 / width,
+                  region.height as f32 / height)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    // row by row, starting with the top row
+    pub tiles: Vec<Tile>,
+    pub visible: bool,
+    pub opacity: f32,
+    // in pixels, y pointing down
+    pub offset: [f32; 2],
+    pub properties: Properties,
+}
+
+impl TileLayer {
+    // the tile at a column and row, counting rows from the top
+    pub fn tile(&self, x: u32, y: u32) -> Tile {
+        if x >= self.width || y >= self.height {
+            return Tile::EMPTY;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    // points relative to the object's position, y pointing down
+    Polygon(Vec<[f32; 2]>),
+    Polyline(Vec<[f32; 2]>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Object {
+    pub id: u32,
+    pub name: String,
+    // what Tiled calls the object's type, or class since 1.9
+    pub kind: String,
+    // in pixels from the map's top left, y pointing down
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // clockwise, in degrees
+    pub rotation: f32,
+    // tile objects draw a tile, the rest are only shapes
+    pub tile: Option<Tile>,
+    pub shape: ObjectShape,
+    pub visible: bool,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<Object>,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offset: [f32; 2],
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl Layer {
+    pub fn name(&self) -> &str {
+        match *self {
+            Layer::Tiles(ref layer) => &layer.name,
+            Layer::Objects(ref layer) => &layer.name,
+        }
+    }
+}
+
+// a map made in Tiled, saved as tmx or as json. only fixed size maps are read. layers in
+// groups are flattened into the map's own list, in the order they're drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    pub layout: MapLayout,
+    // in tiles
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    // sorted by first gid
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+}
+
+impl TiledMap {
+    // reads a .tmx, or a .tmj or .json map, along with any tilesets it keeps in their own files
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TiledError> {
+        let path = path.as_ref();
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("tmx") => TiledMap::from_tmx(&source, directory),
+            _ => TiledMap::from_json(&source, directory),
+        }
+    }
+
+    // directory is where external tilesets are looked for, the map's own directory
+    pub fn from_tmx(source: &str, directory: &Path) -> Result<Self, TiledError> {
+        let root = parse_xml(source)?;
+        if root.name != "map" {
+            return Err(TiledError::Invalid(format!("expected a map but found {}", root.name)));
+        }
+        if root.attribute("infinite") == Some("1") {
+            return Err(TiledError::Unsupported("infinite maps".to_string()));
+        }
+        let tile_width = root.number("tilewidth")?;
+        let tile_height = root.number("tileheight")?;
+        let hex_side = match root.attribute("hexsidelength") {
+            Some(_) => Some(root.number("hexsidelength")?),
+            None => None,
+        };
+        let layout = map_layout(root.attribute("orientation").unwrap_or("orthogonal"),
+                                root.attribute("staggeraxis"),
+                                root.attribute("staggerindex"),
+                                hex_side,
+                                [tile_width, tile_height])?;
+
+        let mut tilesets = Vec::new();
+        let mut layers = Vec::new();
+        for child in &root.children {
+            match child.name.as_str() {
+                "tileset" => tilesets.push(tmx_tileset(child, directory)?),
+                _ => tmx_layer(child, &mut layers)?,
+            }
+        }
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        Ok(TiledMap {
+            layout: layout,
+            width: root.number("width")?,
+            height: root.number("height")?,
+            tile_width: tile_width,
+            tile_height: tile_height,
+            tilesets: tilesets,
+            layers: layers,
+            properties: tmx_properties(&root)?,
+        })
+    }
+
+    pub fn from_json(source: &str, directory: &Path) -> Result<Self, TiledError> {
+        let map: JsonMap = serde_json::from_str(source)?;
+        if map.infinite {
+            return Err(TiledError::Unsupported("infinite maps".to_string()));
+        }
+        let layout = map_layout(&map.orientation,
+                                map.staggeraxis.as_deref(),
+                                map.staggerindex.as_deref(),
+                                map.hexsidelength,
+                                [map.tilewidth, map.tileheight])?;
+
+        let mut tilesets = Vec::new();
+        for tileset in map.tilesets {
+            tilesets.push(json_tileset(tileset, directory)?);
+        }
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let mut layers = Vec::new();
+        for layer in map.layers {
+            json_layer(layer, &mut layers)?;
+        }
+
+        Ok(TiledMap {
+            layout: layout,
+            width: map.width,
+            height: map.height,
+            tile_width: map.tilewidth,
+            tile_height: map.tileheight,
+            tilesets: tilesets,
+            layers: layers,
+            properties: json_properties(&map.properties)?,
+        })
+    }
+
+    // the tileset a gid belongs to, by index, and the tile's id within it
+    pub fn tileset_for(&self, gid: u32) -> Option<(usize, u32)> {
+        find_tileset(&self.tilesets, gid)
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    pub fn tile_layers(&self) -> Vec<&TileLayer> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match *layer {
+                Layer::Tiles(ref layer) => Some(layer),
+                Layer::Objects(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn object_layers(&self) -> Vec<&ObjectLayer> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match *layer {
+                Layer::Objects(ref layer) => Some(layer),
+                Layer::Tiles(_) => None,
+            })
+            .collect()
+    }
+
+    // reads every tileset's image from the directory the map is in, in tileset order
+    pub fn load_tileset_images<P>(&self, directory: P) -> Result<Vec<RgbaImage>, TiledError>
+        where P: AsRef<Path>
+    {
+        let mut images = Vec::with_capacity(self.tilesets.len());
+        for tileset in &self.tilesets {
+            images.push(image::open(directory.as_ref().join(&tileset.image))?.to_rgba8());
+        }
+        Ok(images)
+    }
+}
+
+// which of a list of tilesets sorted by first gid a gid belongs to, and its id within it
+pub fn find_tileset(tilesets: &[Tileset], gid: u32) -> Option<(usize, u32)> {
+    tilesets.iter()
+        .rposition(|tileset| tileset.first_gid <= gid)
+        .and_then(|index| {
+            let tileset = &tilesets[index];
+            if tileset.contains(gid) {
+                Some((index, gid - tileset.first_gid))
+            } else {
+                None
+            }
+        })
+}
+
+fn map_layout(orientation: &str,
+              stagger_axis: Option<&str>,
+              stagger_index: Option<&str>,
+              hex_side: Option<u32>,
+              tile_size: [u32; 2])
+              -> Result<MapLayout, TiledError> {
+    let axis = match stagger_axis {
+        Some("x") => StaggerAxis::X,
+        Some("y") | None => StaggerAxis::Y,
+        Some(other) => return Err(TiledError::Invalid(format!("stagger axis {}", other))),
+    };
+    let index = match stagger_index {
+        Some("odd") | None => StaggerIndex::Odd,
+        Some("even") => StaggerIndex::Even,
+        Some(other) => return Err(TiledError::Invalid(format!("stagger index {}", other))),
+    };
+
+    match orientation {
+        "orthogonal" => Ok(MapLayout::Orthogonal),
+        "isometric" => Ok(MapLayout::Isometric),
+        "staggered" => Ok(MapLayout::Staggered(axis, index)),
+        "hexagonal" => {
+            let (hex, across) = match axis {
+                StaggerAxis::X => (HexOrientation::Flat, tile_size[0]),
+                StaggerAxis::Y => (HexOrientation::Pointy, tile_size[1]),
+            };
+            // grids only lay out hexagons whose sides are half the tile, as Tiled makes them
+            match hex_side {
+                Some(side) if side != across / 2 => {
+                    Err(TiledError::Unsupported(format!("hexagons with {} pixel sides", side)))
+                }
+                _ => Ok(MapLayout::Hexagonal(hex, index)),
+            }
+        }
+        _ => Err(TiledError::Unsupported(format!("{} maps", orientation))),
+    }
+}
+
+fn parse_color(value: &str) -> Result<[u8; 4], TiledError> {
+    let invalid = || TiledError::Invalid(format!("color {}", value));
+    let digits = value.trim_start_matches('#');
+    let channel = |at: usize| {
+        let pair = digits.get(at..at + 2).ok_or_else(invalid)?;
+        u8::from_str_radix(pair, 16).map_err(|_| invalid())
+    };
+    match digits.len() {
+        6 => Ok([channel(0)?, channel(2)?, channel(4)?, 255]),
+        8 => Ok([channel(2)?, channel(4)?, channel(6)?, channel(0)?]),
+        0 => Ok([0, 0, 0, 0]),
+        _ => Err(invalid()),
+    }
+}
+
+// the gids of a layer's data once it's been decoded from base64, little endian u32s
+fn decode_tiles(encoded: &str,
+                compression: Option<&str>,
+                expected: usize)
+                -> Result<Vec<Tile>, TiledError> {
+    let stripped: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = base64::decode(&stripped)?;
+    let bytes = match compression {
+        None | Some("") => bytes,
+        Some("zlib") => {
+            let mut decompressed = Vec::new();
+            ZlibDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+            decompressed
+        }
+        Some("gzip") => {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+            decompressed
+        }
+        Some(other) => return Err(TiledError::Unsupported(format!("{} compression", other))),
+    };
+
+    if bytes.len() != expected * 4 {
+        return Err(TiledError::Invalid(format!("expected {} tiles but found {}",
+                                               expected,
+                                               bytes.len() / 4)));
+    }
+    Ok(bytes.chunks(4)
+        .map(|gid| {
+            Tile(gid[0] as u32 | (gid[1] as u32) << 8 | (gid[2] as u32) << 16 |
+                 (gid[3] as u32) << 24)
+        })
+        .collect())
+}
+
+fn check_tile_count(tiles: &[Tile], width: u32, height: u32) -> Result<(), TiledError> {
+    if tiles.len() != (width * height) as usize {
+        return Err(TiledError::Invalid(format!("a {}x{} layer with {} tiles",
+                                               width,
+                                               height,
+                                               tiles.len())));
+    }
+    Ok(())
+}
+
+// tmx is read into a tree of these first, since xml-rs only hands out events
+
+struct Element {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|value| value.as_str())
+    }
+
+    fn string(&self, name: &str) -> String {
+        self.attribute(name).unwrap_or("").to_string()
+    }
+
+    fn number<T: ::std::str::FromStr>(&self, name: &str) -> Result<T, TiledError> {
+        let value = self.attribute(name)
+            .ok_or_else(|| TiledError::Invalid(format!("{} without {}", self.name, name)))?;
+        value.parse()
+            .map_err(|_| TiledError::Invalid(format!("{} {}={}", self.name, name, value)))
+    }
+
+    fn number_or<T: ::std::str::FromStr>(&self, name: &str, default: T) -> Result<T, TiledError> {
+        match self.attribute(name) {
+            Some(_) => self.number(name),
+            None => Ok(default),
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> Box<dyn Iterator<Item = &'a Element> + 'a> {
+        Box::new(self.children.iter().filter(move |child| child.name == name))
+    }
+}
+
+fn parse_xml(source: &str) -> Result<Element, TiledError> {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::from_str(source) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes.into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                });
+            }
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            XmlEvent::Characters(text) |
+            XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(TiledError::Invalid("no root element".to_string()))
+}
+
+fn tmx_properties(element: &Element) -> Result<Properties, TiledError> {
+    let mut properties = HashMap::new();
+    let list = match element.child("properties") {
+        Some(list) => list,
+        None => return Ok(properties),
+    };
+    for property in list.children("property") {
+        // multiline strings are kept as the element's text instead
+        let value = match property.attribute("value") {
+            Some(value) => value.to_string(),
+            None => property.text.clone(),
+        };
+        let kind = property.attribute("type").unwrap_or("string");
+        properties.insert(property.string("name"), property_value(kind, &value)?);
+    }
+    Ok(properties)
+}
+
+fn property_value(kind: &str, value: &str) -> Result<PropertyValue, TiledError> {
+    let invalid = || TiledError::Invalid(format!("{} property {}", kind, value));
+    Ok(match kind {
+        "bool" => PropertyValue::Bool(value == "true"),
+        "int" => PropertyValue::Int(value.parse().map_err(|_| invalid())?),
+        "float" => PropertyValue::Float(value.parse().map_err(|_| invalid())?),
+        "color" => PropertyValue::Color(parse_color(value)?),
+        "file" => PropertyValue::File(value.to_string()),
+        "object" => PropertyValue::Object(value.parse().map_err(|_| invalid())?),
+        _ => PropertyValue::String(value.to_string()),
+    })
+}
+
+fn tmx_tileset(element: &Element, directory: &Path) -> Result<Tileset, TiledError> {
+    let first_gid = element.number("firstgid")?;
+    let source = match element.attribute("source") {
+        Some(source) => source,
+        None => return tmx_tileset_contents(element, first_gid, Path::new("")),
+    };
+
+    // external tilesets are .tsx or .tsj, their images relative to them rather than the map
+    let path = directory.join(source);
+    let mut contents = String::new();
+    File::open(&path)?.read_to_string(&mut contents)?;
+    let relative = Path::new(source).parent().unwrap_or(Path::new("")).to_path_buf();
+    if source.ends_with(".tsx") {
+        let root = parse_xml(&contents)?;
+        tmx_tileset_contents(&root, first_gid, &relative)
+    } else {
+        let mut tileset: JsonTileset = serde_json::from_str(&contents)?;
+        tileset.firstgid = first_gid;
+        json_tileset_contents(tileset, &relative)
+    }
+}
+
+fn tmx_tileset_contents(element: &Element,
+                        first_gid: u32,
+                        relative: &Path)
+                        -> Result<Tileset, TiledError> {
+    let image = element.child("image")
+        .ok_or_else(|| TiledError::Unsupported("tilesets of separate images".to_string()))?;
+
+    let mut tile_properties = HashMap::new();
+    for tile in element.children("tile") {
+        let properties = tmx_properties(tile)?;
+        if !properties.is_empty() {
+            tile_properties.insert(tile.number("id")?, properties);
+        }
+    }
+
+    let tile_width = element.number("tilewidth")?;
+    let margin = element.number_or("margin", 0)?;
+    let spacing = element.number_or("spacing", 0)?;
+    let image_width: u32 = image.number("width")?;
+    // older versions of Tiled don't write columns, so they're worked out from the image
+    let columns = match element.attribute("columns") {
+        Some(_) => element.number("columns")?,
+        None => {
+            image_width.checked_sub(margin * 2)
+                .and_then(|width| (width + spacing).checked_div(tile_width + spacing))
+                .ok_or_else(|| {
+                    TiledError::Invalid(format!("tileset {} doesn't fit its {} pixel image",
+                                                element.string("name"),
+                                                image_width))
+                })?
+        }
+    };
+    Ok(Tileset {
+        first_gid: first_gid,
+        name: element.string("name"),
+        tile_width: tile_width,
+        tile_height: element.number("tileheight")?,
+        margin: margin,
+        spacing: spacing,
+        columns: columns,
+        tile_count: element.number("tilecount")?,
+        image: join(relative, &image.string("source")),
+        image_width: image_width,
+        image_height: image.number("height")?,
+        tile_properties: tile_properties,
+        properties: tmx_properties(element)?,
+    })
+}
+
+fn tmx_layer(element: &Element, layers: &mut Vec<Layer>) -> Result<(), TiledError> {
+    let name = element.string("name");
+    let visible = element.attribute("visible") != Some("0");
+    let opacity = element.number_or("opacity", 1.0)?;
+    let offset = [element.number_or("offsetx", 0.0)?, element.number_or("offsety", 0.0)?];
+    let properties = tmx_properties(element)?;
+
+    match element.name.as_str() {
+        "layer" => {
+            let width = element.number("width")?;
+            let height = element.number("height")?;
+            let data = element.child("data")
+                .ok_or_else(|| TiledError::Invalid(format!("layer {} without data", name)))?;
+            let tiles = match data.attribute("encoding") {
+                Some("csv") => {
+                    let mut tiles = Vec::new();
+                    for gid in data.text.split(',') {
+                        let gid = gid.trim();
+                        if !gid.is_empty() {
+                            tiles.push(Tile(gid.parse()
+                                .map_err(|_| TiledError::Invalid(format!("tile {}", gid)))?));
+                        }
+                    }
+                    tiles
+                }
+                Some("base64") => {
+                    decode_tiles(&data.text,
+                                 data.attribute("compression"),
+                                 (width * height) as usize)?
+                }
+                Some(other) => return Err(TiledError::Unsupported(format!("{} encoding", other))),
+                None => {
+                    let mut tiles = Vec::new();
+                    for tile in data.children("tile") {
+                        tiles.push(Tile(tile.number_or("gid", 0)?));
+                    }
+                    tiles
+                }
+            };
+            check_tile_count(&tiles, width, height)?;
+            layers.push(Layer::Tiles(TileLayer {
+                name: name,
+                width: width,
+                height: height,
+                tiles: tiles,
+                visible: visible,
+                opacity: opacity,
+                offset: offset,
+                properties: properties,
+            }));
+        }
+        "objectgroup" => {
+            let mut objects = Vec::new();
+            for object in element.children("object") {
+                objects.push(tmx_object(object)?);
+            }
+            layers.push(Layer::Objects(ObjectLayer {
+                name: name,
+                objects: objects,
+                visible: visible,
+                opacity: opacity,
+                offset: offset,
+                properties: properties,
+            }));
+        }
+        "group" => {
+            for child in &element.children {
+                tmx_layer(child, layers)?;
+            }
+        }
+        // image layers and anything else this doesn't draw
+        _ => {}
+    }
+    Ok(())
+}
+
+fn tmx_points(element: &Element) -> Result<Vec<[f32; 2]>, TiledError> {
+    let mut points = Vec::new();
+    for pair in element.string("points").split_whitespace() {
+        let mut coordinates = pair.split(',').map(|coordinate| coordinate.parse::<f32>());
+        match (coordinates.next(), coordinates.next()) {
+            (Some(Ok(x)), Some(Ok(y))) => points.push([x, y]),
+            _ => return Err(TiledError::Invalid(format!("point {}", pair))),
+        }
+    }
+    Ok(points)
+}
+
+fn tmx_object(element: &Element) -> Result<Object, TiledError> {
+    let shape = if element.child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if element.child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = element.child("polygon") {
+        ObjectShape::Polygon(tmx_points(polygon)?)
+    } else if let Some(polyline) = element.child("polyline") {
+        ObjectShape::Polyline(tmx_points(polyline)?)
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    let kind = match element.attribute("type") {
+        Some(kind) => kind.to_string(),
+        None => element.string("class"),
+    };
+    Ok(Object {
+        id: element.number_or("id", 0)?,
+        name: element.string("name"),
+        kind: kind,
+        x: element.number_or("x", 0.0)?,
+        y: element.number_or("y", 0.0)?,
+        width: element.number_or("width", 0.0)?,
+        height: element.number_or("height", 0.0)?,
+        rotation: element.number_or("rotation", 0.0)?,
+        tile: match element.attribute("gid") {
+            Some(_) => Some(Tile(element.number("gid")?)),
+            None => None,
+        },
+        shape: shape,
+        visible: element.attribute("visible") != Some("0"),
+        properties: tmx_properties(element)?,
+    })
+}
+
+// the shapes below mirror Tiled's json format, only keeping what is used
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_orientation() -> String {
+    "orthogonal".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    value: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct JsonTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct JsonTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    tiles: Vec<JsonTile>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    gid: Option<u32>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<JsonPoint>>,
+    polyline: Option<Vec<JsonPoint>>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    // an array of gids, or a base64 string when encoding says so
+    data: Option<Value>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonMap {
+    #[serde(default = "default_orientation")]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    staggeraxis: Option<String>,
+    staggerindex: Option<String>,
+    hexsidelength: Option<u32>,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+fn json_properties(list: &[JsonProperty]) -> Result<Properties, TiledError> {
+    let mut properties = HashMap::new();
+    for property in list {
+        let value = match property.value {
+            Value::Bool(value) => PropertyValue::Bool(value),
+            Value::Number(ref number) => {
+                match property.kind.as_str() {
+                    "float" => PropertyValue::Float(number.as_f64().unwrap_or(0.0)),
+                    "object" => PropertyValue::Object(json_id(number, "object id")?),
+                    _ => {
+                        match number.as_i64() {
+                            Some(value) => PropertyValue::Int(value),
+                            None => PropertyValue::Float(number.as_f64().unwrap_or(0.0)),
+                        }
+                    }
+                }
+            }
+            Value::String(ref value) => property_value(&property.kind, value)?,
+            ref other => PropertyValue::String(other.to_string()),
+        };
+        properties.insert(property.name.clone(), value);
+    }
+    Ok(properties)
+}
+
+// a gid or an object id, which have to fit in a u32
+fn json_id<T: ToString>(value: &T, what: &str) -> Result<u32, TiledError> {
+    let text = value.to_string();
+    text.parse().map_err(|_| TiledError::Invalid(format!("{} {}", what, text)))
+}
+
+fn json_tileset(tileset: JsonTileset, directory: &Path) -> Result<Tileset, TiledError> {
+    let source = match tileset.source {
+        Some(ref source) => source.clone(),
+        None => return json_tileset_contents(tileset, Path::new("")),
+    };
+
+    let path = directory.join(&source);
+    let mut contents = String::new();
+    File::open(&path)?.read_to_string(&mut contents)?;
+    let relative = Path::new(&source).parent().unwrap_or(Path::new("")).to_path_buf();
+    if source.ends_with(".tsx") {
+        let root = parse_xml(&contents)?;
+        tmx_tileset_contents(&root, tileset.firstgid, &relative)
+    } else {
+        let mut external: JsonTileset = serde_json::from_str(&contents)?;
+        external.firstgid = tileset.firstgid;
+        json_tileset_contents(external, &relative)
+    }
+}
+
+fn json_tileset_contents(tileset: JsonTileset, relative: &Path) -> Result<Tileset, TiledError> {
+    let image = tileset.image
+        .ok_or_else(|| TiledError::Unsupported("tilesets of separate images".to_string()))?;
+
+    let mut tile_properties = HashMap::new();
+    for tile in &tileset.tiles {
+        let properties = json_properties(&tile.properties)?;
+        if !properties.is_empty() {
+            tile_properties.insert(tile.id, properties);
+        }
+    }
+
+    Ok(Tileset {
+        first_gid: tileset.firstgid,
+        name: tileset.name,
+        tile_width: tileset.tilewidth,
+        tile_height: tileset.tileheight,
+        margin: tileset.margin,
+        spacing: tileset.spacing,
+        columns: tileset.columns,
+        tile_count: tileset.tilecount,
+        image: join(relative, &image),
+        image_width: tileset.imagewidth,
+        image_height: tileset.imageheight,
+        tile_properties: tile_properties,
+        properties: json_properties(&tileset.properties)?,
+    })
+}
+
+fn json_layer(layer: JsonLayer, layers: &mut Vec<Layer>) -> Result<(), TiledError> {
+    let properties = json_properties(&layer.properties)?;
+    match layer.kind.as_str() {
+        "tilelayer" => {
+            let base64 = layer.encoding.as_deref() == Some("base64");
+            let compression = layer.compression.as_deref();
+            let tiles = match layer.data {
+                Some(Value::String(ref encoded)) if base64 => {
+                    decode_tiles(encoded, compression, (layer.width * layer.height) as usize)?
+                }
+                Some(Value::Array(ref gids)) => {
+                    let mut tiles = Vec::with_capacity(gids.len());
+                    for gid in gids {
+                        tiles.push(Tile(json_id(gid, "tile")?));
+                    }
+                    tiles
+                }
+                _ => {
+                    return Err(TiledError::Invalid(format!("layer {} without data", layer.name)))
+                }
+            };
+            check_tile_count(&tiles, layer.width, layer.height)?;
+            layers.push(Layer::Tiles(TileLayer {
+                name: layer.name,
+                width: layer.width,
+                height: layer.height,
+                tiles: tiles,
+                visible: layer.visible,
+                opacity: layer.opacity,
+                offset: [layer.offsetx, layer.offsety],
+                properties: properties,
+            }));
+        }
+        "objectgroup" => {
+            let mut objects = Vec::with_capacity(layer.objects.len());
+            for object in layer.objects {
+                objects.push(json_object(object)?);
+            }
+            layers.push(Layer::Objects(ObjectLayer {
+                name: layer.name,
+                objects: objects,
+                visible: layer.visible,
+                opacity: layer.opacity,
+                offset: [layer.offsetx, layer.offsety],
+                properties: properties,
+            }));
+        }
+        "group" => {
+            for child in layer.layers {
+                json_layer(child, layers)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn json_object(object: JsonObject) -> Result<Object, TiledError> {
+    let points = |points: Vec<JsonPoint>| points.iter().map(|point| [point.x, point.y]).collect();
+    let shape = if object.ellipse {
+        ObjectShape::Ellipse
+    } else if object.point {
+        ObjectShape::Point
+    } else if let Some(polygon) = object.polygon {
+        ObjectShape::Polygon(points(polygon))
+    } else if let Some(polyline) = object.polyline {
+        ObjectShape::Polyline(points(polyline))
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(Object {
+        id: object.id,
+        name: object.name,
+        kind: if object.kind.is_empty() {
+            object.class
+        } else {
+            object.kind
+        },
+        x: object.x,
+        y: object.y,
+        width: object.width,
+        height: object.height,
+        rotation: object.rotation,
+        tile: object.gid.map(Tile),
+        shape: shape,
+        visible: object.visible,
+        properties: json_properties(&object.properties)?,
+    })
+}
+
+// paths in external tilesets are relative to the tileset, this makes them relative to the map
+fn join(relative: &Path, path: &str) -> String {
+    let joined: PathBuf = relative.join(path);
+    joined.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tiled");
+
+    fn load(name: &str) -> TiledMap {
+        TiledMap::load(Path::new(FIXTURES).join(name)).unwrap()
+    }
+
+    fn tile_layer<'a>(map: &'a TiledMap, name: &str) -> &'a TileLayer {
+        match map.layer(name) {
+            Some(Layer::Tiles(layer)) => layer,
+            other => panic!("expected a tile layer called {} but found {:?}", name, other),
+        }
+    }
+
+    fn gids(layer: &TileLayer) -> Vec<u32> {
+        layer.tiles.iter().map(|tile| tile.0).collect()
+    }
+
+    fn invalid<T: fmt::Debug>(result: Result<T, TiledError>) {
+        match result {
+            Err(TiledError::Invalid(_)) => {}
+            other => panic!("expected an invalid map, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tmx_and_json_read_the_same() {
+        assert_eq!(load("map.tmx"), load("map.tmj"));
+    }
+
+    #[test]
+    fn every_encoding_reads_the_same_tiles() {
+        let ground = vec![1, 2, 2, 3, 5, 6, 6, 7, 0, 9, 10, 0];
+        let tmx = load("encodings.tmx");
+        for name in &["csv", "base64", "zlib", "gzip", "xml"] {
+            assert_eq!(gids(tile_layer(&tmx, name)), ground, "{}", name);
+        }
+        let json = load("encodings.tmj");
+        for name in &["array", "base64", "zlib", "gzip"] {
+            assert_eq!(gids(tile_layer(&json, name)), ground, "{}", name);
+        }
+    }
+
+    #[test]
+    fn flip_flags_are_kept_apart_from_the_gid() {
+        let map = load("map.tmx");
+        let decor = tile_layer(&map, "decor");
+
+        let flipped = decor.tile(1, 0);
+        assert_eq!(flipped.gid(), 9);
+        assert!(flipped.flipped_horizontally());
+        assert!(!flipped.flipped_vertically() && !flipped.flipped_diagonally());
+
+        assert_eq!(decor.tile(3, 0).gid(), 2);
+        assert!(decor.tile(3, 0).flipped_vertically());
+        assert_eq!(decor.tile(0, 1).gid(), 3);
+        assert!(decor.tile(0, 1).flipped_diagonally());
+        let both = decor.tile(2, 1);
+        assert_eq!(both.gid(), 10);
+        assert!(both.flipped_horizontally() && both.flipped_vertically());
+
+        assert!(decor.tile(0, 0).is_empty());
+        assert_eq!(decor.tile(4, 0), Tile::EMPTY);
+        assert_eq!(map.tileset_for(decor.tile(2, 1).gid()), Some((1, 1)));
+    }
+
+    #[test]
+    fn layers_keep_their_settings() {
+        let map = load("map.tmx");
+        let names: Vec<&str> = map.layers.iter().map(|layer| layer.name()).collect();
+        // the group is flattened and the image layer is left out
+        assert_eq!(names, vec!["ground", "decor", "objects"]);
+
+        let decor = tile_layer(&map, "decor");
+        assert!(!decor.visible);
+        assert_eq!(decor.opacity, 0.5);
+        assert_eq!(decor.offset, [2.0, -4.0]);
+        assert_eq!(decor.properties["parallax"], PropertyValue::Float(0.25));
+    }
+
+    #[test]
+    fn properties_keep_their_types() {
+        let map = load("map.tmx");
+        let properties = &map.properties;
+        assert_eq!(properties["title"], PropertyValue::String("fixture".to_string()));
+        assert_eq!(properties["level"], PropertyValue::Int(3));
+        assert_eq!(properties["gravity"], PropertyValue::Float(9.5));
+        assert_eq!(properties["dark"], PropertyValue::Bool(true));
+        assert_eq!(properties["tint"], PropertyValue::Color([0x10, 0x20, 0x30, 0xff]));
+        assert_eq!(properties["music"], PropertyValue::File("music/theme.ogg".to_string()));
+    }
+
+    #[test]
+    fn objects_keep_their_shapes() {
+        let map = load("map.tmx");
+        let objects = &map.object_layers()[0].objects;
+        assert_eq!(objects.len(), 6);
+
+        let spawn = &objects[0];
+        assert_eq!((spawn.id, spawn.name.as_str(), spawn.kind.as_str()), (1, "spawn", "player"));
+        assert_eq!([spawn.x, spawn.y, spawn.width, spawn.height], [8.0, 8.0, 16.0, 16.0]);
+        assert_eq!(spawn.shape, ObjectShape::Rectangle);
+        assert_eq!(spawn.properties["target"], PropertyValue::Object(2));
+        assert_eq!(spawn.properties["note"], PropertyValue::String("two\nlines".to_string()));
+
+        assert_eq!(objects[1].shape, ObjectShape::Ellipse);
+        assert_eq!(objects[1].rotation, 45.0);
+        assert_eq!(objects[2].shape, ObjectShape::Point);
+        // class is what newer versions of Tiled write instead of type
+        assert_eq!(objects[2].kind, "door");
+        assert_eq!(objects[3].shape,
+                   ObjectShape::Polyline(vec![[0.0, 0.0], [16.0, 4.0], [32.0, 0.0]]));
+
+        let crate_tile = objects[4].tile.unwrap();
+        assert_eq!(crate_tile.gid(), 10);
+        assert!(crate_tile.flipped_horizontally());
+        assert!(!objects[4].visible);
+
+        assert_eq!(objects[5].shape,
+                   ObjectShape::Polygon(vec![[0.0, 0.0], [8.0, 0.0], [4.0, -6.0]]));
+    }
+
+    #[test]
+    fn tilesets_are_read_inline_and_from_tsx() {
+        let map = load("map.tmx");
+        assert_eq!(map.tilesets.len(), 2);
+
+        let terrain = &map.tilesets[0];
+        // the fixture leaves columns out, as older versions of Tiled did
+        assert_eq!(terrain.columns, 4);
+        assert_eq!(terrain.region(5),
+                   PixelRect {
+                       x: 19,
+                       y: 19,
+                       width: 16,
+                       height: 16,
+                   });
+        assert_eq!(terrain.tile_properties[&4]["water"], PropertyValue::Bool(true));
+
+        let props = &map.tilesets[1];
+        assert_eq!((props.first_gid, props.name.as_str()), (9, "props"));
+        assert_eq!((props.columns, props.tile_count), (2, 4));
+        // images in external tilesets are relative to the tileset
+        assert_eq!(Path::new(&props.image), Path::new("tilesets/props.png"));
+        assert_eq!(props.tile_properties[&1]["solid"], PropertyValue::Bool(true));
+        assert_eq!(props.properties["layer"], PropertyValue::String("objects".to_string()));
+        assert_eq!(map.tileset_for(12), Some((1, 3)));
+        assert_eq!(map.tileset_for(13), None);
+    }
+
+    #[test]
+    fn colors_with_the_wrong_digits_are_invalid() {
+        assert_eq!(parse_color("#102030").unwrap(), [0x10, 0x20, 0x30, 0xff]);
+        invalid(parse_color("#10203"));
+        invalid(parse_color("#10203g"));
+        // six bytes, but the second pair would split a char
+        invalid(parse_color("#a\u{e9}aaa"));
+    }
+
+    #[test]
+    fn tilesets_without_columns_that_cant_fit_are_invalid() {
+        let map = |tileset: &str| {
+            format!(r#"<map width="1" height="1" tilewidth="16" tileheight="16">
+                         <tileset firstgid="1" name="tiny" tileheight="16" tilecount="1" {}>
+                           <image source="tiny.png" width="4" height="4"/>
+                         </tileset>
+                       </map>"#,
+                    tileset)
+        };
+        invalid(TiledMap::from_tmx(&map(r#"tilewidth="16" margin="4""#), Path::new("")));
+        invalid(TiledMap::from_tmx(&map(r#"tilewidth="0""#), Path::new("")));
+    }
+
+    #[test]
+    fn json_ids_out_of_range_are_invalid() {
+        let layer = |data: &str| {
+            format!(r#"{{"width": 2, "height": 1, "tilewidth": 16, "tileheight": 16,
+                        "layers": [{{"type": "tilelayer", "width": 2, "height": 1,
+                                     "data": {}}}]}}"#,
+                    data)
+        };
+        assert!(TiledMap::from_json(&layer("[1, 3221225482]"), Path::new("")).is_ok());
+        invalid(TiledMap::from_json(&layer("[1, -1]"), Path::new("")));
+        invalid(TiledMap::from_json(&layer("[1, 4294967296]"), Path::new("")));
+        invalid(TiledMap::from_json(&layer("[1, 2.5]"), Path::new("")));
+
+        let property = |value: &str| {
+            format!(r#"{{"width": 1, "height": 1, "tilewidth": 16, "tileheight": 16,
+                        "properties": [{{"name": "target", "type": "object",
+                                         "value": {}}}]}}"#,
+                    value)
+        };
+        assert!(TiledMap::from_json(&property("7"), Path::new("")).is_ok());
+        invalid(TiledMap::from_json(&property("-7"), Path::new("")));
+    }
+}
@@ -0,0 +1,269 @@
+use cgmath::{self, Matrix4, Vector3};
+use rect::Rect;
+use sprite::{FAR_PLANE, NEAR_PLANE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalingPolicy {
+    // the virtual resolution fills the window, distorting it when the aspect ratios differ
+    Stretch,
+    // the virtual resolution is scaled as large as it fits and bordered by bars
+    Letterbox,
+    // the virtual resolution is scaled as large as it fits and more of the world is shown
+    // around it instead of bars
+    Expand,
+    // like Letterbox, but only scaled by whole numbers so every pixel stays square. a window
+    // smaller than the virtual resolution is letterboxed as usual, since nothing whole fits.
+    PixelPerfect,
+}
+
+// where a game's virtual resolution ends up in the window, and how much of the world it shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    // the part of the window drawn to, in pixels from the window's bottom left
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // the part of the world shown, in world units from the bottom left of the virtual area
+    pub world: Rect,
+    window: [u32; 2],
+}
+
+impl Viewport {
+    pub fn new(policy: ScalingPolicy, virtual_size: [f32; 2], window_size: [u32; 2]) -> Self {
+        // a minimized window reports zero, which would leave nothing to scale by
+        let window = [window_size[0].max(1), window_size[1].max(1)];
+        let window_width = window[0] as f32;
+        let window_height = window[1] as f32;
+        let fit = (window_width / virtual_size[0]).min(window_height / virtual_size[1]);
+        let virtual_area = Rect::new(0.0, 0.0, virtual_size[0], virtual_size[1]);
+
+        match policy {
+            ScalingPolicy::Stretch => Viewport::whole_window(window, virtual_area),
+            ScalingPolicy::Letterbox => Viewport::centered(window, virtual_area, fit),
+            ScalingPolicy::PixelPerfect if fit >= 1.0 => {
+                Viewport::centered(window, virtual_area, fit.floor())
+            }
+            ScalingPolicy::PixelPerfect => Viewport::centered(window, virtual_area, fit),
+            ScalingPolicy::Expand => {
+                let world_width = window_width / fit;
+                let world_height = window_height / fit;
+                let world = Rect::new(-(world_width - virtual_size[0]) / 2.0,
+                                      -(world_height - virtual_size[1]) / 2.0,
+                                      world_width,
+                                      world_height);
+                Viewport::whole_window(window, world)
+            }
+        }
+    }
+
+    fn whole_window(window: [u32; 2], world: Rect) -> Self {
+        Viewport {
+            x: 0,
+            y: 0,
+            width: window[0],
+            height: window[1],
+            world: world,
+            window: window,
+        }
+    }
+
+    fn centered(window: [u32; 2], world: Rect, scale: f32) -> Self {
+        let width = ((world.width * scale).round() as u32).min(window[0]);
+        let height = ((world.height * scale).round() as u32).min(window[1]);
+        Viewport {
+            x: (window[0] - width) / 2,
+            y: (window[1] - height) / 2,
+            width: width,
+            height: height,
+            world: world,
+            window: window,
+        }
+    }
+
+    pub fn window_size(&self) -> [u32; 2] {
+        self.window
+    }
+
+    // window pixels per world unit along each axis
+    pub fn scale(&self) -> [f32; 2] {
+        [self.width as f32 / self.world.width, self.height as f32 / self.world.height]
+    }
+
+    // squeezes clip space into the viewport's part of the window, for use in front of any
+    // other projection such as a Camera2D's
+    pub fn clip_transform(&self) -> Matrix4<f32> {
+        let window_width = self.window[0] as f32;
+        let window_height = self.window[1] as f32;
+        let scale_x = self.width as f32 / window_width;
+        let scale_y = self.height as f32 / window_height;
+        let center_x = (self.x as f32 * 2.0 + self.width as f32) / window_width - 1.0;
+        let center_y = (self.y as f32 * 2.0 + self.height as f32) / window_height - 1.0;
+        Matrix4::from_translation(Vector3::new(center_x, center_y, 0.0)) *
+        Matrix4::from_nonuniform_scale(scale_x, scale_y, 1.0)
+    }
+
+    // shows the viewport's world area, in place of sprite::pixel_projection
+    pub fn projection(&self) -> Matrix4<f32> {
+        self.clip_transform() *
+        cgmath::ortho(self.world.x,
+                      self.world.x + self.world.width,
+                      self.world.y,
+                      self.world.y + self.world.height,
+                      NEAR_PLANE,
+                      FAR_PLANE)
+    }
+
+    // whether a window position, with the origin at the top left as glutin reports it, is
+    // inside the viewport rather than on a bar
+    pub fn contains(&self, window_position: [f32; 2]) -> bool {
+        let x = window_position[0] - self.x as f32;
+        let y = self.window[1] as f32 - window_position[1] - self.y as f32;
+        x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32
+    }
+
+    // a window position in world units, with the world's y pointing up
+    pub fn window_to_world(&self, window_position: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale();
+        let x = window_position[0] - self.x as f32;
+        let y = self.window[1] as f32 - window_position[1] - self.y as f32;
+        [self.world.x + x / scale[0], self.world.y + y / scale[1]]
+    }
+
+    // a window position in the viewport's own screen space, top left origin and y pointing
+    // down, which is what a Camera2D with a viewport the size of world expects
+    pub fn window_to_virtual(&self, window_position: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale();
+        let x = window_position[0] - self.x as f32;
+        let y = window_position[1] - (self.window[1] - self.y - self.height) as f32;
+        [x / scale[0], y / scale[1]]
+    }
+
+    // the other way from window_to_world
+    pub fn world_to_window(&self, world_position: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale();
+        let x = (world_position[0] - self.world.x) * scale[0] + self.x as f32;
+        let y = (world_position[1] - self.world.y) * scale[1] + self.y as f32;
+        [x, self.window[1] as f32 - y]
+    }
+
+    // the other way from window_to_virtual
+    pub fn virtual_to_window(&self, virtual_position: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale();
+        let top = (self.window[1] - self.y - self.height) as f32;
+        [virtual_position[0] * scale[0] + self.x as f32, virtual_position[1] * scale[1] + top]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIRTUAL: [f32; 2] = [320.0, 180.0];
+
+    // where the viewport is in the window, as x, y, width and height
+    fn placed(policy: ScalingPolicy, window: [u32; 2]) -> [u32; 4] {
+        let viewport = Viewport::new(policy, VIRTUAL, window);
+        [viewport.x, viewport.y, viewport.width, viewport.height]
+    }
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 0.001 && (a[1] - b[1]).abs() < 0.001
+    }
+
+    #[test]
+    fn letterbox_bars_the_longer_side() {
+        assert_eq!(placed(ScalingPolicy::Letterbox, [1280, 720]), [0, 0, 1280, 720]);
+        // wider than the virtual resolution, so the bars are on the left and right
+        assert_eq!(placed(ScalingPolicy::Letterbox, [1600, 720]), [160, 0, 1280, 720]);
+        // taller, so they're above and below
+        assert_eq!(placed(ScalingPolicy::Letterbox, [1280, 1000]), [0, 140, 1280, 720]);
+        // and the scale is fractional when it has to be
+        assert_eq!(placed(ScalingPolicy::Letterbox, [800, 600]), [0, 75, 800, 450]);
+    }
+
+    #[test]
+    fn stretch_fills_the_window() {
+        for &window in &[[1600, 720], [1280, 1000], [100, 100]] {
+            let viewport = Viewport::new(ScalingPolicy::Stretch, VIRTUAL, window);
+            assert_eq!([viewport.x, viewport.y, viewport.width, viewport.height],
+                       [0, 0, window[0], window[1]]);
+            assert_eq!(viewport.world, Rect::new(0.0, 0.0, VIRTUAL[0], VIRTUAL[1]));
+        }
+        let wide = Viewport::new(ScalingPolicy::Stretch, VIRTUAL, [1600, 720]);
+        assert_eq!(wide.scale(), [5.0, 4.0]);
+    }
+
+    #[test]
+    fn pixel_perfect_scales_by_whole_numbers() {
+        assert_eq!(placed(ScalingPolicy::PixelPerfect, [1280, 720]), [0, 0, 1280, 720]);
+        assert_eq!(placed(ScalingPolicy::PixelPerfect, [1000, 700]), [20, 80, 960, 540]);
+        assert_eq!(placed(ScalingPolicy::PixelPerfect, [700, 1000]), [30, 320, 640, 360]);
+        let viewport = Viewport::new(ScalingPolicy::PixelPerfect, VIRTUAL, [1000, 700]);
+        assert_eq!(viewport.scale(), [3.0, 3.0]);
+    }
+
+    #[test]
+    fn pixel_perfect_letterboxes_windows_too_small_for_it() {
+        assert_eq!(placed(ScalingPolicy::PixelPerfect, [160, 120]),
+                   placed(ScalingPolicy::Letterbox, [160, 120]));
+        assert_eq!(placed(ScalingPolicy::PixelPerfect, [160, 120]), [0, 15, 160, 90]);
+        let viewport = Viewport::new(ScalingPolicy::PixelPerfect, VIRTUAL, [160, 120]);
+        assert_eq!(viewport.scale(), [0.5, 0.5]);
+    }
+
+    #[test]
+    fn expand_shows_more_world_instead_of_bars() {
+        let viewport = Viewport::new(ScalingPolicy::Expand, VIRTUAL, [1600, 720]);
+        assert_eq!([viewport.x, viewport.y, viewport.width, viewport.height],
+                   [0, 0, 1600, 720]);
+        assert_eq!(viewport.world, Rect::new(-40.0, 0.0, 400.0, 180.0));
+    }
+
+    #[test]
+    fn window_positions_round_trip() {
+        let policies = [ScalingPolicy::Stretch,
+                        ScalingPolicy::Letterbox,
+                        ScalingPolicy::Expand,
+                        ScalingPolicy::PixelPerfect];
+        let windows = [[1280, 720], [1600, 720], [1280, 1000], [1000, 700], [160, 120]];
+        for &policy in &policies {
+            for &window in &windows {
+                let viewport = Viewport::new(policy, VIRTUAL, window);
+                for &position in &[[0.0, 0.0], [317.0, 41.5], [window[0] as f32, 12.0]] {
+                    let virtual_position = viewport.window_to_virtual(position);
+                    assert!(close(viewport.virtual_to_window(virtual_position), position),
+                            "{:?} {:?} {:?}",
+                            policy,
+                            window,
+                            position);
+                    let world = viewport.window_to_world(position);
+                    assert!(close(viewport.world_to_window(world), position),
+                            "{:?} {:?} {:?}",
+                            policy,
+                            window,
+                            position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_viewport_corners_are_the_virtual_corners() {
+        // bars on the left and right, 160 pixels wide
+        let viewport = Viewport::new(ScalingPolicy::Letterbox, VIRTUAL, [1600, 720]);
+        assert!(close(viewport.window_to_virtual([160.0, 0.0]), [0.0, 0.0]));
+        assert!(close(viewport.window_to_virtual([1440.0, 720.0]), [320.0, 180.0]));
+        // the world's y points up, so the top left of the viewport is the top of the world
+        assert!(close(viewport.window_to_world([160.0, 0.0]), [0.0, 180.0]));
+        assert!(close(viewport.window_to_world([800.0, 360.0]), [160.0, 90.0]));
+        assert!(viewport.contains([800.0, 360.0]));
+        assert!(!viewport.contains([100.0, 360.0]));
+
+        // bars above and below, 140 pixels tall
+        let viewport = Viewport::new(ScalingPolicy::Letterbox, VIRTUAL, [1280, 1000]);
+        assert!(close(viewport.window_to_virtual([0.0, 140.0]), [0.0, 0.0]));
+        assert!(close(viewport.virtual_to_window([320.0, 180.0]), [1280.0, 860.0]));
+        assert!(!viewport.contains([640.0, 100.0]));
+    }
+}
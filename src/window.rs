@@ -0,0 +1,91 @@
+use gfx::format::{ChannelType, DepthFormat, Format, RenderFormat};
+use gfx::handle::{DepthStencilView, RawDepthStencilView, RawRenderTargetView, RenderTargetView};
+use gfx::memory::Typed;
+use gfx::texture;
+use gfx_device_gl;
+use glutin::{self, ContextBuilder, EventsLoop, GlContext, GlWindow, WindowBuilder};
+use std::os::raw::c_void;
+
+pub type Device = gfx_device_gl::Device;
+pub type Factory = gfx_device_gl::Factory;
+pub type Resources = gfx_device_gl::Resources;
+
+// opens a window with a gl context whose buffers match the color and depth formats, and wraps
+// its default framebuffer in views gfx can draw to. this is what gfx_window_glutin's init does,
+// for the glutin this crate is built against.
+#[allow(clippy::type_complexity)]
+pub fn init<Cf, Df>(window: WindowBuilder,
+                    context: ContextBuilder,
+                    events_loop: &EventsLoop)
+                    -> Result<(GlWindow,
+                               Device,
+                               Factory,
+                               RenderTargetView<Resources, Cf>,
+                               DepthStencilView<Resources, Df>),
+                              String>
+    where Cf: RenderFormat,
+          Df: DepthFormat
+{
+    let (window, device, factory, color, depth) =
+        init_raw(window, context, events_loop, Cf::get_format(), Df::get_format())?;
+    Ok((window, device, factory, Typed::new(color), Typed::new(depth)))
+}
+
+#[allow(clippy::type_complexity)]
+fn init_raw(window: WindowBuilder,
+            context: ContextBuilder,
+            events_loop: &EventsLoop,
+            color_format: Format,
+            depth_format: Format)
+            -> Result<(GlWindow,
+                       Device,
+                       Factory,
+                       RawRenderTargetView<Resources>,
+                       RawDepthStencilView<Resources>),
+                      String> {
+    let color_bits = color_format.0.get_total_bits();
+    let alpha_bits = color_format.0.get_alpha_stencil_bits();
+    let depth_bits = depth_format.0.get_total_bits();
+    let stencil_bits = depth_format.0.get_alpha_stencil_bits();
+    let context = context.with_depth_buffer(depth_bits - stencil_bits)
+        .with_stencil_buffer(stencil_bits)
+        .with_pixel_format(color_bits - alpha_bits, alpha_bits)
+        .with_srgb(color_format.1 == ChannelType::Srgb);
+    let window = GlWindow::new(window, context, events_loop).map_err(|e| format!("{:?}", e))?;
+    unsafe {
+        window.make_current().map_err(|e| format!("{:?}", e))?;
+    }
+
+    let (device, factory) =
+        gfx_device_gl::create(|name| window.get_proc_address(name) as *const c_void);
+    let (color, depth) =
+        gfx_device_gl::create_main_targets_raw(dimensions(&window), color_format.0, depth_format.0);
+    Ok((window, device, factory, color, depth))
+}
+
+// makes new views after the window has been resized, leaving them alone if its size is the same
+pub fn update_views<Cf, Df>(window: &GlWindow,
+                            color: &mut RenderTargetView<Resources, Cf>,
+                            depth: &mut DepthStencilView<Resources, Df>)
+    where Cf: RenderFormat,
+          Df: DepthFormat
+{
+    let size = dimensions(window);
+    if size == color.get_dimensions() {
+        return;
+    }
+    let (new_color, new_depth) = gfx_device_gl::create_main_targets_raw(size,
+                                                                        Cf::get_format().0,
+                                                                        Df::get_format().0);
+    *color = Typed::new(new_color);
+    *depth = Typed::new(new_depth);
+}
+
+// the size of the window's framebuffer in physical pixels
+fn dimensions(window: &GlWindow) -> texture::Dimensions {
+    let size = window.get_inner_size()
+        .unwrap_or_else(|| glutin::dpi::LogicalSize::new(0.0, 0.0))
+        .to_physical(window.get_hidpi_factor());
+    let samples = window.get_pixel_format().multisampling.unwrap_or(0) as texture::NumSamples;
+    (size.width as texture::Size, size.height as texture::Size, 1, samples.into())
+}
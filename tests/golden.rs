@@ -0,0 +1,219 @@
+#[macro_use]
+extern crate gfx;
+extern crate glutin;
+extern crate image;
+extern crate learn_gfx_2d;
+
+use glutin::dpi::LogicalSize;
+use learn_gfx_2d::{ColorFormat, DepthFormat};
+use learn_gfx_2d::capture::Capture;
+use learn_gfx_2d::golden::GoldenImages;
+use learn_gfx_2d::raster::{ColorVertex, Rasterizer};
+use learn_gfx_2d::scenes::{HEIGHT, WIDTH, kitten_bouncing, kitten_keyboard, square_elements,
+                           square_interpolated, triangle_basic, triangle_flashing,
+                           triangle_interpolated};
+use learn_gfx_2d::sprite::{SpriteRenderer, pixel_projection};
+use learn_gfx_2d::texture::load_texture;
+use learn_gfx_2d::window;
+use gfx::traits::FactoryExt;
+use image::RgbaImage;
+use std::env;
+use std::path::Path;
+
+// checks that what each example draws still matches its reference image.
+//
+// the references in tests/golden were drawn by the software rasterizer, so the software check
+// is only a regression snapshot: it catches changes to the scenes or to the rasterizer, but
+// can't say whether the examples look right on a gpu. the gpu check compares real frames
+// against the same images. it needs a window, so it only runs when asked for with
+// `cargo test --test golden -- --ignored`, and setting GOLDEN_BLESS=1 while it runs replaces
+// the references with its captures.
+
+// where the references are kept, and where failed renders and their diffs are written
+const REFERENCES: &'static str = "tests/golden";
+const OUTPUT: &'static str = "target/golden";
+
+// the animated scenes are checked at this point in time
+const SECONDS: f32 = 1.0;
+
+const KITTEN: &'static [u8] = include_bytes!("../examples/kitten_bouncing/images/kitty.png");
+
+gfx_defines! {
+    vertex Vertex {
+        position: [f32; 2] = "position",
+        color: [f32; 3] = "color",
+    }
+
+    pipeline pipe {
+        vertex_buffer: gfx::VertexBuffer<Vertex> = (),
+        out_color: gfx::RenderTarget<ColorFormat> = "out_color",
+    }
+}
+
+enum Draw {
+    // clip space triangles, indexed or not
+    Colored(Vec<ColorVertex>, Option<Vec<u16>>),
+    // the kitten sprite centered on a point in pixels
+    Kitten([f32; 2], [f32; 2]),
+}
+
+struct Scene {
+    name: &'static str,
+    clear: [f32; 4],
+    draw: Draw,
+}
+
+// the frame each example shows, built from the same functions the examples take their data from
+fn scenes() -> Vec<Scene> {
+    let flashing = triangle_flashing::color(SECONDS);
+    let flashing_triangle = triangle_flashing::triangle()
+        .iter()
+        .map(|&position| {
+            ColorVertex {
+                position: position,
+                color: flashing,
+            }
+        })
+        .collect();
+
+    vec![Scene {
+             name: "triangle_basic",
+             clear: triangle_basic::CLEAR_COLOR,
+             draw: Draw::Colored(triangle_basic::triangle().to_vec(), None),
+         },
+         Scene {
+             name: "triangle_interpolated",
+             clear: triangle_interpolated::CLEAR_COLOR,
+             draw: Draw::Colored(triangle_interpolated::triangle().to_vec(), None),
+         },
+         // the example hands the color to its shader as a uniform, which comes out the same as
+         // giving it to every vertex
+         Scene {
+             name: "triangle_flashing",
+             clear: triangle_flashing::CLEAR_COLOR,
+             draw: Draw::Colored(flashing_triangle, None),
+         },
+         Scene {
+             name: "square_interpolated",
+             clear: square_interpolated::CLEAR_COLOR,
+             draw: Draw::Colored(square_interpolated::square().to_vec(), None),
+         },
+         Scene {
+             name: "square_elements",
+             clear: square_elements::CLEAR_COLOR,
+             draw: Draw::Colored(square_elements::square_vertices().to_vec(),
+                                 Some(square_elements::square_indices().to_vec())),
+         },
+         Scene {
+             name: "kitten_bouncing",
+             clear: kitten_bouncing::CLEAR_COLOR,
+             draw: Draw::Kitten(kitten_bouncing::kitten_position(SECONDS),
+                                kitten_bouncing::SPRITE_SIZE),
+         },
+         Scene {
+             name: "kitten_keyboard",
+             clear: kitten_keyboard::CLEAR_COLOR,
+             draw: Draw::Kitten(kitten_keyboard::start_position(), kitten_keyboard::SPRITE_SIZE),
+         }]
+}
+
+fn golden(output: &str) -> GoldenImages {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    GoldenImages::new(root.join(REFERENCES), root.join(OUTPUT).join(output))
+}
+
+// checks every image before failing, so one run shows everything that changed
+fn check_all<F>(golden: &GoldenImages, mut render: F)
+    where F: FnMut(&Scene) -> RgbaImage
+{
+    let mut failures = Vec::new();
+    for scene in scenes() {
+        if let Err(err) = golden.check(scene.name, &render(&scene)) {
+            failures.push(format!("{}: {}", scene.name, err));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn rasterizer_matches_snapshots() {
+    let kitten = image::load_from_memory(KITTEN).unwrap().to_rgba8();
+    check_all(&golden("software"), |scene| {
+        let mut target = Rasterizer::new(WIDTH, HEIGHT);
+        target.clear(scene.clear);
+        match scene.draw {
+            Draw::Colored(ref vertices, None) => target.draw(vertices, None),
+            Draw::Colored(ref vertices, Some(ref indices)) => {
+                target.draw_indexed(vertices, indices, None)
+            }
+            Draw::Kitten(position, size) => {
+                target.set_projection(pixel_projection(WIDTH as f32, HEIGHT as f32));
+                target.draw_sprite(&kitten, position, size);
+            }
+        }
+        target.into_image()
+    });
+}
+
+// draws the scenes through the examples' shaders and the sprite renderer, reading each frame
+// back from the gpu
+#[test]
+#[ignore]
+fn gpu_matches_references() {
+    let events_loop = glutin::EventsLoop::new();
+    let builder = glutin::WindowBuilder::new()
+        .with_dimensions(LogicalSize::new(WIDTH as f64, HEIGHT as f64))
+        .with_visibility(false);
+    let context = glutin::ContextBuilder::new()
+        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
+        .with_gl_profile(glutin::GlProfile::Core);
+    let (_window, mut device, mut factory, main_color, _) =
+        window::init::<ColorFormat, DepthFormat>(builder, context, &events_loop).unwrap();
+
+    // the window's own target can't be read back, so everything is drawn into a capture
+    let capture = Capture::new(&mut factory, WIDTH, HEIGHT).unwrap();
+    let out = capture.target().target().clone();
+    let pso = factory.create_pipeline_simple(
+            include_bytes!("../examples/triangle_basic/src/shaders/vert.glsl"),
+            include_bytes!("../examples/triangle_basic/src/shaders/frag.glsl"),
+            pipe::new())
+        .unwrap();
+    let mut sprites = SpriteRenderer::new(&mut factory, main_color, WIDTH as f32, HEIGHT as f32);
+    sprites.set_render_target(capture.target());
+    let kitten = load_texture(&mut factory, KITTEN).unwrap();
+
+    let bless = env::var("GOLDEN_BLESS").map(|bless| bless == "1").unwrap_or(false);
+    check_all(&golden("gpu").with_bless(bless), |scene| {
+        let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+        encoder.clear(&out, scene.clear);
+        match scene.draw {
+            Draw::Colored(ref vertices, ref indices) => {
+                let vertices: Vec<Vertex> = vertices.iter()
+                    .map(|vertex| {
+                        Vertex {
+                            position: vertex.position,
+                            color: vertex.color,
+                        }
+                    })
+                    .collect();
+                let (vertex_buffer, slice) = match *indices {
+                    Some(ref indices) => {
+                        factory.create_vertex_buffer_with_slice(&vertices, &indices[..])
+                    }
+                    None => factory.create_vertex_buffer_with_slice(&vertices, ()),
+                };
+                let data = pipe::Data {
+                    vertex_buffer: vertex_buffer,
+                    out_color: out.clone(),
+                };
+                encoder.draw(&slice, &pso, &data);
+            }
+            Draw::Kitten(position, size) => {
+                sprites.draw_sprite(&mut encoder, &kitten, position, size)
+            }
+        }
+        capture.request(&mut encoder).unwrap();
+        encoder.flush(&mut device);
+        capture.read(&mut factory).unwrap()
+    });
+}